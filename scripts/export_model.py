@@ -0,0 +1,182 @@
+"""Exporta un modelo o scaler .pkl de QuantOS al formato JSON que evalúa el bot en Rust.
+
+Uso:
+    python scripts/export_model.py models/quantos_brain_v1.pkl models/quantos_brain_v1.json
+    python scripts/export_model.py models/quantos_scaler_v1.pkl models/quantos_scaler_v1.json
+    python scripts/export_model.py xgb_calibrated_model.pkl models/xgb_calibrated.json --samples features.csv
+
+Soporta RandomForest/ExtraTrees/DecisionTree de sklearn, XGBClassifier
+(binary:logistic) y CalibratedClassifierCV (sigmoid o isotonic) sobre ellos,
+además de StandardScaler y MinMaxScaler.
+
+El JSON incluye predicciones de control calculadas aquí con `predict_proba`;
+el bot se niega a cargar el modelo si no las reproduce con tolerancia 1e-6.
+
+Cada modelo necesita su esquema de features (`modelo.schema.json`). Genera el
+del binario con `quantos-core schema features.schema.json`, entrena con ese
+orden de columnas y pásalo con `--schema` para que quede junto al modelo.
+Los modelos de 8 features (quantos_brain_v1) usan el esquema v1:
+`quantos-core schema v1.schema.json --version 1`.
+"""
+import argparse
+import json
+import math
+
+import joblib
+import numpy as np
+
+FORMAT = "quantos-trees-v1"
+SCALER_FORMAT = "quantos-scaler-v1"
+
+
+def export_sklearn_tree(tree):
+    t = tree.tree_
+    value = t.value[:, 0, :]
+    totals = value.sum(axis=1)
+    totals[totals == 0] = 1.0
+    return {
+        "left": t.children_left.tolist(),
+        "right": t.children_right.tolist(),
+        "feature": t.feature.tolist(),
+        "threshold": t.threshold.tolist(),
+        "value": (value[:, 1] / totals).tolist(),
+    }
+
+
+def export_forest(model):
+    estimators = getattr(model, "estimators_", [model])
+    return {"kind": "random_forest", "split": "le", "trees": [export_sklearn_tree(e) for e in estimators]}
+
+
+def parse_base_score(raw):
+    # XGBoost >= 2 lo guarda como "[5E-1]"
+    return float(str(raw).strip("[]"))
+
+
+def export_xgboost(model):
+    raw = json.loads(model.get_booster().save_raw("json"))
+    learner = raw["learner"]
+    booster = learner["gradient_booster"]
+    if booster["name"] != "gbtree":
+        raise SystemExit(f"Booster {booster['name']} no soportado (solo gbtree)")
+    if learner["objective"]["name"] != "binary:logistic":
+        raise SystemExit(f"Objetivo {learner['objective']['name']} no soportado")
+
+    p = parse_base_score(learner["learner_model_param"]["base_score"])
+    trees = []
+    for t in booster["model"]["trees"]:
+        left = t["left_children"]
+        trees.append({
+            "left": left,
+            "right": t["right_children"],
+            "feature": t["split_indices"],
+            "threshold": t["split_conditions"],
+            # En las hojas, split_conditions guarda el peso de la hoja
+            "value": [c if l < 0 else 0.0 for c, l in zip(t["split_conditions"], left)],
+            "default_left": [bool(d) for d in t["default_left"]],
+        })
+    return {"kind": "gradient_boosting", "split": "lt", "base_score": math.log(p / (1 - p)), "trees": trees}
+
+
+def export_base(model):
+    if type(model).__name__ == "XGBClassifier":
+        return export_xgboost(model)
+    if hasattr(model, "tree_") or hasattr(model, "estimators_"):
+        return export_forest(model)
+    raise SystemExit(f"Modelo {type(model).__name__} no soportado")
+
+
+def export_calibrator(calibrator):
+    name = type(calibrator).__name__
+    if name == "_SigmoidCalibration":
+        return {"method": "sigmoid", "a": float(calibrator.a_), "b": float(calibrator.b_)}
+    if name == "IsotonicRegression":
+        return {"method": "isotonic", "x": calibrator.X_thresholds_.tolist(), "y": calibrator.y_thresholds_.tolist()}
+    raise SystemExit(f"Calibrador {name} no soportado")
+
+
+def export_members(model):
+    if type(model).__name__ != "CalibratedClassifierCV":
+        return [export_base(model)]
+    members = []
+    for cc in model.calibrated_classifiers_:
+        estimator = getattr(cc, "estimator", None) or cc.base_estimator
+        member = export_base(estimator)
+        member["calibration"] = export_calibrator(cc.calibrators[0])
+        members.append(member)
+    return members
+
+
+def export_scaler(scaler):
+    """Traduce el scaler a x' = (x - mean) / scale."""
+    n = int(scaler.n_features_in_)
+    name = type(scaler).__name__
+    if name == "StandardScaler":
+        mean = scaler.mean_ if scaler.with_mean else np.zeros(n)
+        scale = scaler.scale_ if scaler.with_std else np.ones(n)
+    elif name == "MinMaxScaler":
+        # x * scale_ + min_  ==  (x - (-min_ / scale_)) / (1 / scale_)
+        mean = -scaler.min_ / scaler.scale_
+        scale = 1.0 / scaler.scale_
+    else:
+        raise SystemExit(f"Scaler {name} no soportado")
+
+    names = getattr(scaler, "feature_names_in_", None)
+    return {
+        "format": SCALER_FORMAT,
+        "feature_names": [] if names is None else [str(x) for x in names],
+        "mean": np.asarray(mean, dtype=float).tolist(),
+        "scale": np.asarray(scale, dtype=float).tolist(),
+    }
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("model")
+    parser.add_argument("output")
+    parser.add_argument("--samples", help="CSV de features para las predicciones de control")
+    parser.add_argument("--checks", type=int, default=32)
+    parser.add_argument("--schema", help="Esquema de features con el que se entrenó el modelo")
+    args = parser.parse_args()
+
+    model = joblib.load(args.model)
+    if not hasattr(model, "predict_proba"):
+        with open(args.output, "w") as f:
+            json.dump(export_scaler(model), f)
+        print(f"{args.model} -> {args.output} (scaler {type(model).__name__})")
+        return
+
+    n_features = int(model.n_features_in_)
+
+    if args.samples:
+        X = np.loadtxt(args.samples, delimiter=",", ndmin=2)[: args.checks]
+    else:
+        X = np.random.default_rng(42).normal(size=(args.checks, n_features))
+    proba = model.predict_proba(X)[:, 1]
+
+    out = {
+        "format": FORMAT,
+        "n_features": n_features,
+        "members": export_members(model),
+        "checks": [{"features": x.tolist(), "proba": float(p)} for x, p in zip(X, proba)],
+    }
+    with open(args.output, "w") as f:
+        json.dump(out, f)
+    print(f"{args.model} -> {args.output} ({n_features} features, {len(out['members'])} miembro(s))")
+
+    if args.schema:
+        with open(args.schema) as f:
+            schema = json.load(f)
+        names = getattr(model, "feature_names_in_", None)
+        if len(schema["features"]) != n_features:
+            raise SystemExit(f"El esquema tiene {len(schema['features'])} features y el modelo {n_features}")
+        if names is not None and [str(n) for n in names] != [f["name"] for f in schema["features"]]:
+            raise SystemExit(f"Columnas de entrenamiento {list(names)} distintas del esquema")
+        schema_path = (args.output[: -len(".json")] if args.output.endswith(".json") else args.output) + ".schema.json"
+        with open(schema_path, "w") as f:
+            json.dump(schema, f, indent=2)
+        print(f"Esquema v{schema['version']} -> {schema_path}")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,57 @@
+use crate::data::binance_client::{BinanceAggTrade, PriceMessage};
+use std::error::Error;
+use std::fs;
+
+/// Carga un stream de aggTrade grabado y lo convierte en la misma secuencia de
+/// `PriceMessage` que recibe el bot en vivo.
+///
+/// Formatos aceptados (detectados por línea):
+/// - JSON crudo del WebSocket, uno por línea (lo que graba `start_market_stream`).
+///   El símbolo sale del campo `s`.
+/// - CSV de data.binance.vision: `agg_trade_id,price,quantity,first_id,last_id,transact_time,...`.
+///   No trae símbolo: los mensajes quedan con `symbol` vacío.
+pub fn load_agg_trades(path: &str) -> Result<Vec<PriceMessage>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    let mut messages = Vec::new();
+
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+
+        let parsed = if line.starts_with('{') { parse_json_line(line)? } else { parse_csv_line(line) };
+        match parsed {
+            Some(msg) => messages.push(msg),
+            // La cabecera del CSV es la única línea que se permite ignorar
+            None if line_no == 0 => continue,
+            None => return Err(format!("Línea {} inválida en {}: {}", line_no + 1, path, line).into()),
+        }
+    }
+
+    // Los dumps diarios vienen ordenados, pero una grabación con reconexiones puede no estarlo
+    messages.sort_by_key(|m| m.timestamp);
+    Ok(messages)
+}
+
+fn parse_json_line(line: &str) -> Result<Option<PriceMessage>, Box<dyn Error>> {
+    let trade: BinanceAggTrade = serde_json::from_str(line)?;
+    Ok(Some(PriceMessage {
+        symbol: trade.symbol,
+        price: trade.price.parse()?,
+        volume: trade.quantity.parse()?,
+        timestamp: trade.trade_time,
+    }))
+}
+
+fn parse_csv_line(line: &str) -> Option<PriceMessage> {
+    let cols: Vec<&str> = line.split(',').collect();
+    if cols.len() < 6 { return None; }
+
+    let price = cols[1].trim().parse::<f64>().ok()?;
+    let volume = cols[2].trim().parse::<f64>().ok()?;
+    let mut timestamp = cols[5].trim().parse::<u64>().ok()?;
+
+    // Desde 2025 los dumps de Spot vienen en microsegundos
+    if timestamp > 100_000_000_000_000 { timestamp /= 1000; }
+
+    Some(PriceMessage { symbol: String::new(), price, volume, timestamp })
+}
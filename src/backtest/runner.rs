@@ -0,0 +1,176 @@
+use crate::brain::NoiseModel;
+use crate::config::Config;
+use crate::data::binance_client::PriceMessage;
+use crate::data::macro_filter::MacroFilter;
+use crate::trading::engine::TradeEvent;
+use crate::trading::gateway::paper::PaperGateway;
+use crate::trading::gateway::ExchangeGateway;
+use crate::trading::portfolio::Portfolio;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+
+#[derive(Debug, Clone)]
+pub struct TradeRecord {
+    pub symbol: String,
+    pub entry_time: u64,
+    pub exit_time: u64,
+    pub entry_price: f64,
+    pub exit_price: f64,
+    pub qty: f64,
+    pub pnl_pct: f64,
+    /// Comisiones de entrada y salida, ya descontadas de `pnl_usd`
+    pub fees: f64,
+    pub pnl_usd: f64,
+    pub reason: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub struct EquityPoint {
+    pub timestamp: u64,
+    pub equity: f64,
+}
+
+pub struct BacktestReport {
+    pub initial_balance: f64,
+    pub trades: Vec<TradeRecord>,
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+impl BacktestReport {
+    pub fn final_equity(&self) -> f64 {
+        self.equity_curve.last().map(|p| p.equity).unwrap_or(self.initial_balance)
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        if self.trades.is_empty() { return 0.0; }
+        let wins = self.trades.iter().filter(|t| t.pnl_usd > 0.0).count();
+        wins as f64 / self.trades.len() as f64
+    }
+
+    /// Máxima caída desde un pico de la curva de equity, en porcentaje
+    pub fn max_drawdown_pct(&self) -> f64 {
+        let mut peak = self.initial_balance;
+        let mut max_dd = 0.0;
+        for point in &self.equity_curve {
+            peak = peak.max(point.equity);
+            max_dd = f64::max(max_dd, (peak - point.equity) / peak * 100.0);
+        }
+        max_dd
+    }
+
+    pub fn print_summary(&self) {
+        let total_pnl = self.final_equity() - self.initial_balance;
+        println!("--- 📊 RESULTADO DEL BACKTEST ---");
+        println!("Trades: {} | Win Rate: {:.1}%", self.trades.len(), self.win_rate() * 100.0);
+        println!("Equity: ${:.2} -> ${:.2} | PnL: ${:.2} ({:.2}%)",
+            self.initial_balance, self.final_equity(), total_pnl, total_pnl / self.initial_balance * 100.0);
+        let fees = self.trades.iter().fold(0.0, |acc, t| acc + t.fees);
+        println!("PnL bruto: ${:.2} | Comisiones: ${:.2} | PnL neto: ${:.2}", total_pnl + fees, fees, total_pnl);
+        println!("Max Drawdown: {:.2}%", self.max_drawdown_pct());
+    }
+
+    /// Escribe `trades.csv` y `equity.csv` en el directorio indicado
+    pub fn write_csv(&self, dir: &str) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(dir)?;
+
+        let mut trades = fs::File::create(format!("{}/trades.csv", dir))?;
+        writeln!(trades, "symbol,entry_time,exit_time,entry_price,exit_price,qty,pnl_pct,fees,pnl_usd,reason")?;
+        for t in &self.trades {
+            writeln!(trades, "{},{},{},{:.2},{:.2},{:.8},{:.4},{:.4},{:.4},{}",
+                t.symbol, t.entry_time, t.exit_time, t.entry_price, t.exit_price, t.qty, t.pnl_pct, t.fees, t.pnl_usd, t.reason)?;
+        }
+
+        let mut equity = fs::File::create(format!("{}/equity.csv", dir))?;
+        writeln!(equity, "timestamp,equity")?;
+        for p in &self.equity_curve {
+            writeln!(equity, "{},{:.4}", p.timestamp, p.equity)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reproduce la secuencia de ticks a través del mismo `Portfolio` que usa el bot
+/// en vivo, llenando contra el propio tape con `PaperGateway`. `brains` asigna un
+/// modelo a cada símbolo; los ticks sin símbolo (CSV) son del primero de la config.
+/// Los ticks del activo correlacionado deben venir mezclados en `messages`.
+/// El régimen macro sale de los CSV de `macro_filter.csv_dir`, si existen.
+/// Si al final quedan posiciones abiertas, se cierran al último precio.
+pub async fn run_backtest<M: NoiseModel>(messages: &[PriceMessage], brains: &HashMap<String, M>, config: &Config) -> BacktestReport {
+    let default_symbol = config.market.symbols.first().cloned().unwrap_or_default();
+    let gateway = PaperGateway::new(&[(config.market.quote_asset(), config.backtest.account_balance)], config.backtest.fee_rate, config.backtest.slippage)
+        .with_maker_fee(config.backtest.maker_fee_rate);
+    let mut portfolio = Portfolio::new(config);
+    if let Some((maker_fee, taker_fee)) = gateway.fee_rates() {
+        portfolio.set_fee_tier(maker_fee, taker_fee);
+    }
+
+    // Régimen macro con las klines locales cerradas antes del primer tick (sin mirar al futuro)
+    if let (true, Some(first)) = (config.macro_filter.enabled, messages.first()) {
+        for symbol in &config.market.symbols {
+            match MacroFilter::from_csv(&config.macro_filter, symbol, Some(first.timestamp)) {
+                Ok(context) => portfolio.set_macro(symbol, context),
+                Err(e) => println!("⚠️ Macro {} sin datos ({}): se usan los flags asumidos", symbol, e),
+            }
+        }
+    }
+
+    // La curva parte del mismo saldo con el que se fondea la cuenta simulada
+    let mut report = BacktestReport {
+        initial_balance: config.backtest.account_balance,
+        trades: Vec::new(),
+        equity_curve: Vec::new(),
+    };
+    let mut equity = config.backtest.account_balance;
+    let mut entry_times: HashMap<String, u64> = HashMap::new();
+
+    if let Some(first) = messages.first() {
+        report.equity_curve.push(EquityPoint { timestamp: first.timestamp, equity });
+    }
+
+    let mut events = Vec::new();
+    for msg in messages {
+        let msg = if msg.symbol.is_empty() {
+            Cow::Owned(PriceMessage { symbol: default_symbol.clone(), ..msg.clone() })
+        } else {
+            Cow::Borrowed(msg)
+        };
+        for ack in gateway.on_market_trade(&msg.symbol, msg.price) {
+            events.extend(portfolio.on_order_update(&ack, msg.timestamp));
+        }
+        if let Some(intent) = portfolio.on_tick(&msg, brains) {
+            events.extend(portfolio.execute(&gateway, &intent, msg.timestamp).await);
+        }
+    }
+    if let Some(last) = messages.last() {
+        for intent in portfolio.close_all() {
+            events.extend(portfolio.execute(&gateway, &intent, last.timestamp).await);
+        }
+    }
+
+    for event in events {
+        match event {
+            TradeEvent::Entry { symbol, timestamp, .. } => { entry_times.insert(symbol, timestamp); }
+            TradeEvent::Exit { symbol, timestamp, reason, entry, exit, qty, pnl_pct, fees, pnl_usd, .. } => {
+                equity += pnl_usd;
+                report.trades.push(TradeRecord {
+                    entry_time: entry_times.get(&symbol).copied().unwrap_or(0),
+                    symbol,
+                    exit_time: timestamp,
+                    entry_price: entry,
+                    exit_price: exit,
+                    qty,
+                    pnl_pct,
+                    fees,
+                    pnl_usd,
+                    reason,
+                });
+                report.equity_curve.push(EquityPoint { timestamp, equity });
+            }
+        }
+    }
+
+    report
+}
@@ -1 +1,29 @@
-pub mod model_loader;
\ No newline at end of file
+pub mod model_loader;
+pub mod tree_ensemble;
+pub mod scaler;
+
+use std::error::Error;
+use std::sync::Arc;
+
+/// Contrato mínimo de cualquier cerebro que estime la probabilidad de ruido.
+/// Permite alimentar el mismo loop de decisión con el modelo real, con uno
+/// alternativo o con un stub determinista durante un backtest.
+pub trait NoiseModel {
+    fn predict_noise(&self, features: Vec<f64>) -> Result<f64, Box<dyn Error>>;
+
+    /// Espera también las features del activo correlacionado (esquema v2)
+    fn uses_cross_asset(&self) -> bool {
+        false
+    }
+}
+
+/// Varios símbolos pueden compartir el mismo modelo cargado una sola vez
+impl<T: NoiseModel + ?Sized> NoiseModel for Arc<T> {
+    fn predict_noise(&self, features: Vec<f64>) -> Result<f64, Box<dyn Error>> {
+        (**self).predict_noise(features)
+    }
+
+    fn uses_cross_asset(&self) -> bool {
+        (**self).uses_cross_asset()
+    }
+}
@@ -1,44 +1,123 @@
-use pyo3::prelude::*;
-use pyo3::types::PyList;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use super::NoiseModel;
+use super::scaler::FeatureScaler;
+use super::tree_ensemble::TreeEnsemble;
+use crate::config::ModelConfig;
+use crate::data::feature_schema::FeatureSchema;
 
+/// Cerebro de QuantOS: árboles exportados desde los .pkl y evaluados en Rust puro,
+/// sin intérprete de Python ni GIL en el loop de trading.
 pub struct QuantosBrain {
-    model: PyObject,
+    model: TreeEnsemble,
+    scaler: Option<FeatureScaler>,
+    pub schema: FeatureSchema,
 }
 
-
 impl QuantosBrain {
-    pub fn new(model_path: &str) -> PyResult<Self> {
-        Python::with_gil(|py| {
-            // 1. Forzamos la ruta del venv en el sys.path de Python
-
-            
-
-            // Dentro de Python::with_gil(|py| { ...
-            let warnings = py.import("warnings")?;
-            warnings.call_method1("filterwarnings", ("ignore",))?;
-            let sys = py.import("sys")?;
-            let path: &PyList = sys.getattr("path")?.downcast()?;
-            
-            // Reemplaza con tu ruta exacta a site-packages
-            let venv_site_packages = r"C:\Users\USER\Downloads\QuantOS-Core\venv\Lib\site-packages";
-            path.insert(0, venv_site_packages)?;
-
-            // 2. Ahora intentamos cargar joblib
-            let joblib = py.import("joblib")?;
-            let model = joblib.call_method1("load", (model_path,))?;
-            
-            Ok(QuantosBrain {
-                model: model.into(),
-            })
-        })
-    }
-
-    pub fn predict_noise(&self, features: Vec<f64>) -> PyResult<f64> {
-        Python::with_gil(|py| {
-            let model = self.model.as_ref(py);
-            let prediction = model.call_method1("predict_proba", (vec![features],))?;
-            let proba: Vec<Vec<f64>> = prediction.extract()?;
-            Ok(proba[0][1])
-        })
-    }
-}
\ No newline at end of file
+    /// `model_path` y `scaler_path` son los JSON generados con `scripts/export_model.py`.
+    /// Sin scaler, las features llegan al modelo tal cual salen del buffer.
+    ///
+    /// El modelo debe venir acompañado de su `.schema.json`; si no coincide con el
+    /// esquema que genera el buffer para esa versión, el cerebro se niega a arrancar.
+    /// Un modelo v1 recibe solo las 8 features del activo principal.
+    pub fn new(model_path: &str, scaler_path: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let model = TreeEnsemble::load(model_path)?;
+        let schema = FeatureSchema::load(&FeatureSchema::path_for_model(model_path))?;
+        schema.check_supported()?;
+
+        if model.n_features != schema.len() {
+            return Err(format!("El modelo espera {} features y su esquema declara {}", model.n_features, schema.len()).into());
+        }
+
+        let scaler = scaler_path.map(FeatureScaler::load).transpose()?;
+        if let Some(scaler) = &scaler {
+            scaler.check_features(&schema.names())?;
+        }
+
+        Ok(QuantosBrain { model, scaler, schema })
+    }
+
+    /// Cerebro de cada símbolo según `model.per_symbol`. Los símbolos que
+    /// comparten modelo y scaler comparten también la instancia cargada.
+    pub fn load_for_symbols(config: &ModelConfig, symbols: &[String]) -> Result<HashMap<String, Arc<QuantosBrain>>, Box<dyn Error>> {
+        let mut loaded: HashMap<(&str, Option<&str>), Arc<QuantosBrain>> = HashMap::new();
+        let mut brains = HashMap::new();
+        for symbol in symbols {
+            let key = config.for_symbol(symbol);
+            let brain = match loaded.get(&key) {
+                Some(brain) => brain.clone(),
+                None => {
+                    let brain = Arc::new(QuantosBrain::new(key.0, key.1).map_err(|e| format!("{}: {}", symbol, e))?);
+                    loaded.insert(key, brain.clone());
+                    brain
+                }
+            };
+            brains.insert(symbol.clone(), brain);
+        }
+        Ok(brains)
+    }
+}
+
+impl NoiseModel for QuantosBrain {
+    fn uses_cross_asset(&self) -> bool {
+        self.schema.uses_cross_asset()
+    }
+
+    fn predict_noise(&self, features: Vec<f64>) -> Result<f64, Box<dyn Error>> {
+        // Sin predicción no hay trade: un vector fuera de esquema nunca llega al modelo
+        if features.len() != self.schema.len() {
+            return Err(format!("Vector de {} features fuera del esquema v{} ({})", features.len(), self.schema.version, self.schema.len()).into());
+        }
+        match &self.scaler {
+            Some(scaler) => self.model.predict_proba(&scaler.transform(&features)?),
+            None => self.model.predict_proba(&features),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Árbol de un solo corte sobre `feature`: izquierda 0.2, derecha 0.8 (NaN → izquierda)
+    fn write_model(name: &str, version: u32, feature: usize) -> String {
+        let schema = FeatureSchema::for_version(version).unwrap();
+        let dir = std::env::temp_dir().join(format!("quantos_brain_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.json").to_string_lossy().to_string();
+        let model = format!(r#"{{"format":"quantos-trees-v1","n_features":{},"members":[{{"kind":"random_forest","split":"le",
+            "trees":[{{"left":[1,-1,-1],"right":[2,-1,-1],"feature":[{},-2,-2],"threshold":[0.5,0,0],"value":[0,0.2,0.8]}}]}}]}}"#,
+            schema.len(), feature);
+        std::fs::write(&path, model).unwrap();
+        schema.save(&FeatureSchema::path_for_model(&path)).unwrap();
+        path
+    }
+
+    #[test]
+    fn v1_model_loads_and_predicts_without_cross_asset() {
+        let brain = QuantosBrain::new(&write_model("v1", 1, 0), None).unwrap();
+        assert!(!brain.uses_cross_asset());
+        assert!((brain.predict_noise(vec![1.0; 8]).unwrap() - 0.8).abs() < 1e-9);
+        assert!(brain.predict_noise(vec![1.0; 12]).is_err());
+    }
+
+    #[test]
+    fn v2_model_sends_missing_correlated_features_down_the_default_branch() {
+        let brain = QuantosBrain::new(&write_model("v2", 2, 8), None).unwrap();
+        assert!(brain.uses_cross_asset());
+        let mut features = vec![1.0; 8];
+        features.extend([f64::NAN; 4]);
+        assert!((brain.predict_noise(features).unwrap() - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unknown_schema_version_is_rejected() {
+        let path = write_model("v9", 1, 0);
+        let mut schema = FeatureSchema::for_version(1).unwrap();
+        schema.version = 9;
+        schema.save(&FeatureSchema::path_for_model(&path)).unwrap();
+        assert!(QuantosBrain::new(&path, None).is_err());
+    }
+}
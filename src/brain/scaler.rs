@@ -0,0 +1,121 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fs;
+
+/// Formato exportado por `scripts/export_model.py` a partir del scaler .pkl
+pub const SCALER_FORMAT: &str = "quantos-scaler-v1";
+
+/// Transformación afín por feature: x' = (x - mean) / scale.
+/// Cubre StandardScaler y MinMaxScaler de sklearn (el exportador traduce sus parámetros).
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeatureScaler {
+    pub format: String,
+    #[serde(default)]
+    pub feature_names: Vec<String>,
+    pub mean: Vec<f64>,
+    pub scale: Vec<f64>,
+}
+
+impl FeatureScaler {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path).map_err(|e| format!("No se pudo leer {}: {}", path, e))?;
+        let scaler: FeatureScaler = serde_json::from_str(&content)?;
+
+        if scaler.format != SCALER_FORMAT {
+            return Err(format!("Formato de scaler '{}' no soportado (esperado '{}')", scaler.format, SCALER_FORMAT).into());
+        }
+        if scaler.mean.len() != scaler.scale.len() {
+            return Err("Scaler con mean y scale de distinto tamaño".into());
+        }
+        if !scaler.feature_names.is_empty() && scaler.feature_names.len() != scaler.mean.len() {
+            return Err("Scaler con feature_names de tamaño incorrecto".into());
+        }
+        Ok(scaler)
+    }
+
+    pub fn n_features(&self) -> usize {
+        self.mean.len()
+    }
+
+    /// Comprueba que el vector en vivo tiene las mismas features y en el mismo orden
+    /// que se usaron al entrenar. Sin nombres en el scaler solo se compara el tamaño.
+    pub fn check_features(&self, live_names: &[&str]) -> Result<(), Box<dyn Error>> {
+        if live_names.len() != self.n_features() {
+            return Err(format!("El scaler espera {} features y el buffer genera {}", self.n_features(), live_names.len()).into());
+        }
+        for (i, (trained, live)) in self.feature_names.iter().zip(live_names).enumerate() {
+            if !trained.eq_ignore_ascii_case(live) {
+                return Err(format!("Feature {} distinta: entrenada '{}' vs en vivo '{}'", i, trained, live).into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn transform(&self, features: &[f64]) -> Result<Vec<f64>, Box<dyn Error>> {
+        if features.len() != self.n_features() {
+            return Err(format!("El scaler espera {} features y recibió {}", self.n_features(), features.len()).into());
+        }
+        Ok(features.iter().zip(self.mean.iter().zip(&self.scale)).map(|(x, (mean, scale))| {
+            // sklearn reemplaza escalas nulas por 1.0 (features constantes)
+            let scale = if *scale == 0.0 { 1.0 } else { *scale };
+            (x - mean) / scale
+        }).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scaler(json: &str) -> FeatureScaler {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn transforms_with_mean_and_scale() {
+        let s = scaler(r#"{"format":"quantos-scaler-v1","feature_names":["a","b"],"mean":[1.0,-2.0],"scale":[2.0,0.5]}"#);
+        assert_eq!(s.transform(&[3.0, -1.0]).unwrap(), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn zero_scale_leaves_the_feature_centered() {
+        let s = scaler(r#"{"format":"quantos-scaler-v1","mean":[5.0,0.0],"scale":[0.0,1.0]}"#);
+        let out = s.transform(&[7.0, 3.0]).unwrap();
+        assert_eq!(out, vec![2.0, 3.0]);
+        assert!(out.iter().all(|x| x.is_finite()));
+    }
+
+    #[test]
+    fn feature_count_mismatch_is_an_error() {
+        let s = scaler(r#"{"format":"quantos-scaler-v1","feature_names":["a","b"],"mean":[0.0,0.0],"scale":[1.0,1.0]}"#);
+        assert!(s.transform(&[1.0, 2.0, 3.0]).unwrap_err().to_string().contains("espera 2 features y recibió 3"));
+        assert!(s.check_features(&["a"]).unwrap_err().to_string().contains("espera 2 features y el buffer genera 1"));
+    }
+
+    #[test]
+    fn feature_name_mismatch_is_an_error() {
+        let s = scaler(r#"{"format":"quantos-scaler-v1","feature_names":["pct_change","SMA"],"mean":[0.0,0.0],"scale":[1.0,1.0]}"#);
+        assert!(s.check_features(&["pct_change", "sma"]).is_ok());
+        let err = s.check_features(&["sma", "pct_change"]).unwrap_err().to_string();
+        assert!(err.starts_with("Feature 0 distinta"), "{}", err);
+
+        // Sin nombres exportados solo cuenta el tamaño
+        let unnamed = scaler(r#"{"format":"quantos-scaler-v1","mean":[0.0,0.0],"scale":[1.0,1.0]}"#);
+        assert!(unnamed.check_features(&["x", "y"]).is_ok());
+    }
+
+    #[test]
+    fn load_rejects_inconsistent_files() {
+        let dir = std::env::temp_dir().join(format!("quantos_scaler_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, json: &str| {
+            let path = dir.join(name).to_string_lossy().to_string();
+            std::fs::write(&path, json).unwrap();
+            path
+        };
+        assert!(FeatureScaler::load(&write("ok.json", r#"{"format":"quantos-scaler-v1","mean":[0.0],"scale":[1.0]}"#)).is_ok());
+        assert!(FeatureScaler::load(&write("format.json", r#"{"format":"pickle","mean":[0.0],"scale":[1.0]}"#)).is_err());
+        assert!(FeatureScaler::load(&write("sizes.json", r#"{"format":"quantos-scaler-v1","mean":[0.0,1.0],"scale":[1.0]}"#)).is_err());
+        assert!(FeatureScaler::load(&write("names.json", r#"{"format":"quantos-scaler-v1","feature_names":["a","b"],"mean":[0.0],"scale":[1.0]}"#)).is_err());
+    }
+}
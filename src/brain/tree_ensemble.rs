@@ -0,0 +1,292 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fs;
+
+/// Formato exportado por `scripts/export_model.py` a partir de los .pkl entrenados
+pub const MODEL_FORMAT: &str = "quantos-trees-v1";
+
+/// Tolerancia de las predicciones de control guardadas junto al modelo
+const CHECK_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnsembleKind {
+    /// sklearn RandomForest: cada hoja guarda P(ruido), se promedia entre árboles
+    RandomForest,
+    /// XGBoost binary:logistic: las hojas suman un margen que pasa por una sigmoide
+    GradientBoosting,
+}
+
+/// Regla de corte: sklearn manda a la izquierda con `x <= t`, XGBoost con `x < t`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitRule {
+    Le,
+    Lt,
+}
+
+/// Árbol en formato de arrays paralelos. Un nodo es hoja si `left[i] < 0`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tree {
+    pub left: Vec<i32>,
+    pub right: Vec<i32>,
+    pub feature: Vec<i32>,
+    pub threshold: Vec<f64>,
+    pub value: Vec<f64>,
+    #[serde(default)]
+    pub default_left: Vec<bool>,
+}
+
+impl Tree {
+    fn validate(&self, n_features: usize) -> Result<(), String> {
+        let n = self.left.len();
+        if self.right.len() != n || self.feature.len() != n || self.threshold.len() != n || self.value.len() != n {
+            return Err("árbol con arrays de distinto tamaño".to_string());
+        }
+        if !self.default_left.is_empty() && self.default_left.len() != n {
+            return Err("default_left con tamaño incorrecto".to_string());
+        }
+        for i in 0..n {
+            if self.left[i] < 0 { continue; }
+            let (l, r, f) = (self.left[i] as usize, self.right[i] as usize, self.feature[i]);
+            if l >= n || r >= n || self.right[i] < 0 {
+                return Err(format!("nodo {} apunta fuera del árbol", i));
+            }
+            if f < 0 || f as usize >= n_features {
+                return Err(format!("nodo {} usa la feature {} (el modelo tiene {})", i, f, n_features));
+            }
+        }
+        Ok(())
+    }
+
+    fn leaf_value(&self, features: &[f64], split: SplitRule) -> f64 {
+        let mut node = 0usize;
+        while self.left[node] >= 0 {
+            // sklearn y XGBoost comparan en float32: replicamos el redondeo
+            let x = features[self.feature[node] as usize] as f32 as f64;
+            let threshold = self.threshold[node];
+            let go_left = if x.is_nan() {
+                self.default_left.get(node).copied().unwrap_or(true)
+            } else {
+                match split {
+                    SplitRule::Le => x <= threshold,
+                    SplitRule::Lt => x < threshold,
+                }
+            };
+            node = if go_left { self.left[node] } else { self.right[node] } as usize;
+        }
+        self.value[node]
+    }
+}
+
+/// Calibración de `CalibratedClassifierCV` aplicada sobre la probabilidad cruda
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum Calibration {
+    /// Platt: p = 1 / (1 + exp(a·f + b))
+    Sigmoid { a: f64, b: f64 },
+    /// Interpolación lineal por tramos, recortada a los extremos (np.interp)
+    Isotonic { x: Vec<f64>, y: Vec<f64> },
+}
+
+impl Calibration {
+    fn apply(&self, f: f64) -> f64 {
+        match self {
+            Calibration::Sigmoid { a, b } => 1.0 / (1.0 + (a * f + b).exp()),
+            Calibration::Isotonic { x, y } => {
+                if x.is_empty() { return f; }
+                if f <= x[0] { return y[0]; }
+                if f >= x[x.len() - 1] { return y[y.len() - 1]; }
+                let i = x.partition_point(|&xi| xi <= f);
+                let (x0, x1, y0, y1) = (x[i - 1], x[i], y[i - 1], y[i]);
+                if x1 == x0 { y0 } else { y0 + (y1 - y0) * (f - x0) / (x1 - x0) }
+            }
+        }
+    }
+}
+
+/// Un clasificador de árboles, opcionalmente calibrado
+#[derive(Debug, Clone, Deserialize)]
+pub struct TreeModel {
+    pub kind: EnsembleKind,
+    pub split: SplitRule,
+    #[serde(default)]
+    pub base_score: f64, // Margen inicial (solo gradient boosting)
+    pub trees: Vec<Tree>,
+    #[serde(default)]
+    pub calibration: Option<Calibration>,
+}
+
+impl TreeModel {
+    fn predict(&self, features: &[f64]) -> f64 {
+        let sum: f64 = self.trees.iter().map(|t| t.leaf_value(features, self.split)).sum();
+        let raw = match self.kind {
+            EnsembleKind::RandomForest => sum / self.trees.len() as f64,
+            EnsembleKind::GradientBoosting => 1.0 / (1.0 + (-(self.base_score + sum)).exp()),
+        };
+        match &self.calibration {
+            Some(calibration) => calibration.apply(raw),
+            None => raw,
+        }
+    }
+}
+
+/// Predicción de referencia calculada en Python al exportar
+#[derive(Debug, Clone, Deserialize)]
+pub struct PredictionCheck {
+    pub features: Vec<f64>,
+    pub proba: f64,
+}
+
+/// Modelo completo. `members` tiene un único elemento salvo en
+/// `CalibratedClassifierCV`, donde sklearn promedia un clasificador por fold.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TreeEnsemble {
+    pub format: String,
+    pub n_features: usize,
+    pub members: Vec<TreeModel>,
+    #[serde(default)]
+    pub checks: Vec<PredictionCheck>,
+}
+
+impl TreeEnsemble {
+    /// Carga el modelo y verifica que reproduce las predicciones de control de Python
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path).map_err(|e| format!("No se pudo leer {}: {}", path, e))?;
+        let model: TreeEnsemble = serde_json::from_str(&content)?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.format != MODEL_FORMAT {
+            return Err(format!("Formato de modelo '{}' no soportado (esperado '{}')", self.format, MODEL_FORMAT).into());
+        }
+        if self.members.is_empty() || self.members.iter().any(|m| m.trees.is_empty()) {
+            return Err("El modelo no contiene árboles".into());
+        }
+        for member in &self.members {
+            for (i, tree) in member.trees.iter().enumerate() {
+                tree.validate(self.n_features).map_err(|e| format!("Árbol {}: {}", i, e))?;
+            }
+        }
+        for (i, check) in self.checks.iter().enumerate() {
+            let proba = self.predict_proba(&check.features)?;
+            if (proba - check.proba).abs() > CHECK_TOLERANCE {
+                return Err(format!("Predicción de control {} no coincide: Rust {:.8} vs Python {:.8}", i, proba, check.proba).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Probabilidad de la clase 1 (ruido), equivalente a `predict_proba(X)[0][1]`
+    pub fn predict_proba(&self, features: &[f64]) -> Result<f64, Box<dyn Error>> {
+        if features.len() != self.n_features {
+            return Err(format!("El modelo espera {} features y recibió {}", self.n_features, features.len()).into());
+        }
+        let total: f64 = self.members.iter().map(|m| m.predict(features)).sum();
+        Ok(total / self.members.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Un árbol de un corte sobre la feature 0: hojas `left` / `right`
+    fn stump(threshold: f64, left: f64, right: f64, default_left: bool) -> String {
+        format!(r#"{{"left":[1,-1,-1],"right":[2,-1,-1],"feature":[0,-2,-2],"threshold":[{},0,0],"value":[0,{},{}],"default_left":[{},false,false]}}"#,
+            threshold, left, right, default_left)
+    }
+
+    fn ensemble(member: &str) -> TreeEnsemble {
+        let json = format!(r#"{{"format":"quantos-trees-v1","n_features":1,"members":[{}]}}"#, member);
+        let model: TreeEnsemble = serde_json::from_str(&json).unwrap();
+        model.validate().unwrap();
+        model
+    }
+
+    fn forest(split: &str, trees: &[String], calibration: &str) -> TreeEnsemble {
+        ensemble(&format!(r#"{{"kind":"random_forest","split":"{}","trees":[{}]{}}}"#, split, trees.join(","), calibration))
+    }
+
+    fn predict(model: &TreeEnsemble, x: f64) -> f64 {
+        model.predict_proba(&[x]).unwrap()
+    }
+
+    #[test]
+    fn split_direction_at_the_f32_threshold() {
+        // Umbral exportado en float32: 0.1f32 = 0.10000000149011612
+        let threshold = 0.1f32 as f64;
+        let le = forest("le", &[stump(threshold, 0.2, 0.8, true)], "");
+        let lt = forest("lt", &[stump(threshold, 0.2, 0.8, true)], "");
+
+        // 0.1 en f64 es menor que el umbral, pero en f32 coincide con él
+        assert!(0.1 < threshold);
+        assert_eq!(predict(&le, 0.1), 0.2);
+        assert_eq!(predict(&lt, 0.1), 0.8);
+        assert_eq!(predict(&lt, 0.09), 0.2);
+        assert_eq!(predict(&le, 0.11), 0.8);
+    }
+
+    #[test]
+    fn nan_takes_the_default_branch() {
+        assert_eq!(predict(&forest("le", &[stump(0.5, 0.2, 0.8, true)], ""), f64::NAN), 0.2);
+        assert_eq!(predict(&forest("le", &[stump(0.5, 0.2, 0.8, false)], ""), f64::NAN), 0.8);
+
+        // Sin `default_left` exportado (sklearn) va a la izquierda
+        let legacy = r#"{"left":[1,-1,-1],"right":[2,-1,-1],"feature":[0,-2,-2],"threshold":[0.5,0,0],"value":[0,0.2,0.8]}"#;
+        assert_eq!(predict(&forest("le", &[legacy.to_string()], ""), f64::NAN), 0.2);
+    }
+
+    #[test]
+    fn random_forest_averages_the_trees() {
+        let model = forest("le", &[stump(0.5, 0.2, 0.8, true), stump(0.5, 0.4, 0.6, true)], "");
+        assert!((predict(&model, 0.0) - 0.3).abs() < 1e-12);
+        assert!((predict(&model, 1.0) - 0.7).abs() < 1e-12);
+    }
+
+    #[test]
+    fn gradient_boosting_adds_base_score_and_leaves() {
+        let trees = [stump(0.5, -0.25, 0.5, true), stump(0.5, 0.75, -1.0, true)];
+        let model = ensemble(&format!(r#"{{"kind":"gradient_boosting","split":"lt","base_score":0.1,"trees":[{}]}}"#, trees.join(",")));
+        let sigmoid = |m: f64| 1.0 / (1.0 + (-m).exp());
+        assert!((predict(&model, 0.0) - sigmoid(0.1 - 0.25 + 0.75)).abs() < 1e-12);
+        assert!((predict(&model, 1.0) - sigmoid(0.1 + 0.5 - 1.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sigmoid_calibration_applies_platt_scaling() {
+        let model = forest("le", &[stump(0.5, 0.2, 0.8, true)], r#","calibration":{"method":"sigmoid","a":-2.0,"b":0.5}"#);
+        assert!((predict(&model, 0.0) - 1.0 / (1.0 + (-2.0 * 0.2 + 0.5f64).exp())).abs() < 1e-12);
+        assert!((predict(&model, 1.0) - 1.0 / (1.0 + (-2.0 * 0.8 + 0.5f64).exp())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn isotonic_calibration_interpolates_and_clips() {
+        let calibration = Calibration::Isotonic { x: vec![0.2, 0.4, 0.8], y: vec![0.1, 0.3, 0.9] };
+        assert_eq!(calibration.apply(0.0), 0.1);
+        assert_eq!(calibration.apply(1.0), 0.9);
+        assert!((calibration.apply(0.3) - 0.2).abs() < 1e-12);
+        assert!((calibration.apply(0.6) - 0.6).abs() < 1e-12);
+        assert_eq!(calibration.apply(0.4), 0.3);
+
+        let model = forest("le", &[stump(0.5, 0.3, 0.8, true)], r#","calibration":{"method":"isotonic","x":[0.2,0.4,0.8],"y":[0.1,0.3,0.9]}"#);
+        assert!((predict(&model, 0.0) - 0.2).abs() < 1e-12);
+        assert_eq!(predict(&model, 1.0), 0.9);
+    }
+
+    #[test]
+    fn invalid_models_are_rejected() {
+        let json = r#"{"format":"quantos-trees-v1","n_features":1,"members":[{"kind":"random_forest","split":"le",
+            "trees":[{"left":[1,-1,-1],"right":[2,-1,-1],"feature":[3,-2,-2],"threshold":[0.5,0,0],"value":[0,0.2,0.8]}]}],
+            "checks":[]}"#;
+        let model: TreeEnsemble = serde_json::from_str(json).unwrap();
+        assert!(model.validate().unwrap_err().to_string().contains("usa la feature 3"));
+
+        let mut model = forest("le", &[stump(0.5, 0.2, 0.8, true)], "");
+        model.checks.push(PredictionCheck { features: vec![0.0], proba: 0.25 });
+        assert!(model.validate().unwrap_err().to_string().contains("control 0"));
+        assert!(model.predict_proba(&[0.0, 1.0]).is_err());
+    }
+}
@@ -0,0 +1,832 @@
+// --- CONFIGURACIÓN DEL SISTEMA QuantOS ---
+//
+// Capas (cada una pisa a la anterior):
+//   1. Valores por defecto de este archivo
+//   2. Archivo TOML (`quantos.toml` o `--config <ruta>`)
+//   3. Variables de entorno `QUANTOS__SECCION__CLAVE` (ej: QUANTOS__STRATEGY__ENTRY_CONFIDENCE=0.8).
+//      Las claves de mapas conservan su caso: QUANTOS__MODEL__PER_SYMBOL__ETHUSDT__PATH=...
+//   4. CLI: `--set seccion.clave=valor` y los atajos `--exchange`, `--interval`, `--model`, `--scaler`
+
+use crate::data::correlation::CrossAssetStats;
+use crate::data::resampler::parse_interval;
+use crate::trading::gateway::paper::split_symbol;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::{env, fs, path::Path};
+
+pub const DEFAULT_CONFIG_PATH: &str = "quantos.toml";
+const ENV_PREFIX: &str = "QUANTOS__";
+/// Secciones cuyas claves son datos (símbolos) y no campos: no se pasan a minúsculas
+const ENV_MAP_SECTIONS: [&str; 1] = ["model.per_symbol"];
+
+/// Intervalos que acepta `/api/v3/klines` a partir de 1h
+const KLINE_INTERVALS: [&str; 10] = ["1h", "2h", "4h", "6h", "8h", "12h", "1d", "3d", "1w", "1M"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub market: MarketConfig,
+    pub model: ModelConfig,
+    pub strategy: StrategyConfig,
+    pub risk: RiskConfig,
+    pub sizing: SizingConfig,
+    pub risk_gate: RiskGateConfig,
+    pub viability: ViabilityConfig,
+    pub exchange: ExchangeConfig,
+    pub macro_filter: MacroConfig,
+    pub persistence: PersistenceConfig,
+    pub reconcile: ReconcileConfig,
+    pub protection: ProtectionConfig,
+    pub execution: ExecutionConfig,
+    pub backtest: BacktestSettings,
+}
+
+/// Símbolos y resampler (Pilar 6: Correlación Latente)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarketConfig {
+    /// Símbolos operados a la vez; todos con la misma moneda de cotización
+    pub symbols: Vec<String>,
+    pub corr_asset: String,
+    pub candle_interval: String,
+    pub buffer_limit: usize,
+    /// Niveles del stream de profundidad parcial (5, 10 o 20)
+    pub depth_levels: u32,
+    /// Libro L2 local (snapshot + diffs) para OFI, profundidad y slippage
+    pub local_book: bool,
+    /// Ventana del Order Flow Imbalance, en ms
+    pub ofi_window_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelConfig {
+    pub path: String,
+    /// Vacío = sin scaler
+    pub scaler_path: String,
+    /// Modelo propio por símbolo (`[model.per_symbol.ETHUSDT]`); el resto usa `path`
+    pub per_symbol: BTreeMap<String, SymbolModel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SymbolModel {
+    pub path: String,
+    #[serde(default)]
+    pub scaler_path: String,
+}
+
+/// Multiplicador de tamaño a partir de cierta confianza
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfidenceTier {
+    pub min_confidence: f64,
+    pub multiplier: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StrategyConfig {
+    /// Confianza mínima para entrar
+    pub entry_confidence: f64,
+    /// Probabilidad de ruido que fuerza la salida
+    pub exit_noise: f64,
+    /// Spread máximo permitido como fracción del ATR%
+    pub max_spread_atr_factor: f64,
+    /// Spread (%) que se supone mientras no hay libro (backtest sobre aggTrades)
+    pub assumed_spread_pct: f64,
+    /// Slippage máximo (%) estimado sobre el libro L2 para la cantidad de entrada
+    pub max_slippage_pct: f64,
+    /// Ordenados de menor a mayor confianza; se aplica el último alcanzado
+    pub confidence_tiers: Vec<ConfidenceTier>,
+    /// No-Trade Intelligence (Pilar 1): con ruido por encima, el bot se bloquea
+    pub no_trade_threshold: f64,
+    /// Risk Engine No Lineal (Pilar 4): con ruido por debajo, operamos al 100%
+    pub high_confidence_threshold: f64,
+    /// Factor de reducción de posición en zona de incertidumbre
+    pub risk_reduction_factor: f64,
+    /// Pilar 6: correlación mínima para que el activo correlacionado cuente en la confianza
+    pub cross_asset_min_correlation: f64,
+    /// Confianza que suma (o resta) la fuerza relativa frente al activo correlacionado
+    pub cross_asset_weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskConfig {
+    pub balance_usd: f64,
+    /// Fracción del balance arriesgada por trade (0.01 = 1%)
+    pub risk_per_trade: f64,
+    /// Stop loss a `stop_atr_mult` × ATR% bajo la entrada: el mismo que dimensiona la posición
+    pub stop_atr_mult: f64,
+    /// Trailing stop a `trail_atr_mult` × ATR% bajo el máximo alcanzado
+    pub trail_atr_mult: f64,
+    /// Límites (%) de ambas distancias, para ATR% anómalos
+    pub min_stop_pct: f64,
+    pub max_stop_pct: f64,
+    /// Presupuesto compartido: riesgo total de las posiciones abiertas, en fracción del balance
+    pub max_open_risk: f64,
+    /// Posiciones abiertas a la vez entre todos los símbolos
+    pub max_open_positions: usize,
+}
+
+/// Tamaño de cada entrada (ver `PositionManager::calculate_order_size`). La política
+/// da el tamaño base, el multiplicador de confianza lo escala y los topes lo limitan:
+/// ningún multiplicador lleva una entrada por encima de `max_risk_per_trade` ni de `max_notional`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SizingConfig {
+    pub policy: SizingPolicy,
+    /// vol_target: volatilidad (ATR% de una vela) de la posición en % del balance
+    pub target_vol_pct: f64,
+    /// kelly: fracción del Kelly completo que se arriesga (0.25 = cuarto de Kelly)
+    pub kelly_fraction: f64,
+    /// kelly: operaciones cerradas que se miran para la tasa de acierto y el payoff
+    pub kelly_lookback: usize,
+    /// kelly: por debajo de estas operaciones se usan `default_win_rate` y `default_payoff_ratio`
+    pub kelly_min_trades: usize,
+    pub default_win_rate: f64,
+    pub default_payoff_ratio: f64,
+    /// Tope del riesgo hasta el stop de una entrada, en fracción del balance
+    pub max_risk_per_trade: f64,
+    /// Tope del nocional de una entrada, en fracción del balance (1.0 = sin apalancamiento)
+    pub max_notional: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizingPolicy {
+    /// Arriesga `risk.risk_per_trade` del balance hasta el stop
+    FixedFractional,
+    /// Nocional cuyo ATR% equivale a `target_vol_pct` del balance
+    VolTarget,
+    /// Fracción del Kelly con la tasa de acierto y el payoff históricos
+    Kelly,
+}
+
+/// Límites que se comprueban antes de cada entrada (ver `trading::risk_gate`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskGateConfig {
+    pub enabled: bool,
+    /// Pérdida realizada del día UTC que detiene las entradas hasta el día siguiente
+    pub max_daily_loss_usd: f64,
+    /// Pérdidas seguidas que detienen las entradas hasta el día siguiente
+    pub max_consecutive_losses: u32,
+    /// Entradas de toda la cuenta en una hora móvil
+    pub max_trades_per_hour: u32,
+    /// Nocional máximo de un símbolo (posición abierta más la entrada nueva)
+    pub max_symbol_notional_usd: f64,
+    /// Nocional máximo de todas las posiciones juntas
+    pub max_account_notional_usd: f64,
+    /// Tras un stop loss, el símbolo no vuelve a entrar en este tiempo
+    pub stop_loss_cooldown_secs: u64,
+}
+
+/// Pilar 12: una entrada solo se envía si su rendimiento esperado, descontadas
+/// comisiones, spread y slippage, llega a `min_edge_pct` (ver `trading::viability`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViabilityConfig {
+    pub enabled: bool,
+    /// Movimiento esperado a favor en múltiplos de la distancia al stop (R), que ya
+    /// sale del ATR con los límites de `risk.min_stop_pct`/`max_stop_pct`
+    pub target_r_multiple: f64,
+    /// Slippage (%) estimado en cada orden a mercado
+    pub slippage_pct: f64,
+    /// Rendimiento neto esperado mínimo (%) para entrar
+    pub min_edge_pct: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExchangeConfig {
+    /// testnet | mainnet | paper
+    pub mode: String,
+    /// Comisión taker de la cuenta (Pilar 12); también la que cobra el paper trading
+    pub trading_fee: f64,
+    /// Comisión maker de la cuenta
+    pub maker_fee: f64,
+    /// Las comisiones se pagan en BNB (descuento de Binance spot)
+    pub bnb_fee_discount: bool,
+    pub paper_slippage: f64,
+    pub paper_balance: f64,
+    /// User data stream (executionReport / outboundAccountPosition); solo testnet y mainnet
+    pub user_stream: bool,
+    /// Con el user stream, espera máxima del executionReport de una orden cuya respuesta REST falló
+    pub order_confirm_timeout_secs: u64,
+    /// WebSocket de datos de mercado (aggTrade, bookTicker, libro L2); vacío = el de la red de `mode`
+    pub market_stream_endpoint: String,
+    /// REST de los snapshots del libro L2; debe ser la misma red que `market_stream_endpoint`
+    pub market_rest_endpoint: String,
+}
+
+/// Régimen macro (tendencia diaria, RSI intradía, volatilidad) de cada símbolo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MacroConfig {
+    pub enabled: bool,
+    /// Klines para tendencia y volatilidad
+    pub daily_interval: String,
+    /// Klines para el RSI
+    pub intraday_interval: String,
+    pub ma_fast: usize,
+    pub ma_slow: usize,
+    pub rsi_period: usize,
+    /// RSI por debajo = sobreventa
+    pub rsi_oversold: f64,
+    /// Velas diarias de la volatilidad reciente
+    pub vol_window: usize,
+    /// Volatilidad reciente / de toda la ventana a partir de la cual el régimen es alto
+    pub high_vol_ratio: f64,
+    pub low_vol_ratio: f64,
+    pub refresh_minutes: u64,
+    /// Siempre mainnet: las klines de testnet no reflejan el mercado real
+    pub rest_endpoint: String,
+    /// Caché y fallback: `<csv_dir>/<SYMBOL>_<intervalo>.csv`
+    pub csv_dir: String,
+    /// Flags usados hasta tener datos (o con el filtro desactivado)
+    pub assume_bull: bool,
+    pub assume_rsi_oversold: bool,
+}
+
+/// Estado de las posiciones en disco para retomarlas tras un reinicio
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PersistenceConfig {
+    pub enabled: bool,
+    /// `<state_dir>/positions_<modo>.json`, uno por modo de exchange
+    pub state_dir: String,
+    /// Diferencia relativa entre lo guardado y el saldo real que se da por buena
+    pub qty_tolerance: f64,
+}
+
+/// Conciliación periódica con la cuenta real (saldos y ejecuciones)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconcileConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// Fracción del equity real que pasa a ser el capital de riesgo (`risk.balance_usd`).
+    /// Por debajo de 1 porque los multiplicadores de confianza pueden superar el 100%.
+    pub capital_fraction: f64,
+    /// Diferencia sin explicar (en USD) entre la cuenta y las posiciones del bot que dispara la alerta
+    pub max_drift_usd: f64,
+    /// Conciliaciones seguidas con deriva antes de bloquear las entradas del símbolo
+    pub drift_strikes: u32,
+}
+
+/// Stop residente en el exchange (STOP_LOSS_LIMIT) que protege cada posición abierta
+/// aunque el bot se caiga. Sigue al stop loss y al trailing y se cancela al salir.
+/// No hay OCO: las salidas por beneficio son dinámicas (trailing y ruido), no un precio fijo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProtectionConfig {
+    pub enabled: bool,
+    /// % bajo el stop del bot: en marcha, sale antes el bot y el del exchange no se toca
+    pub stop_buffer_pct: f64,
+    /// % bajo el stop al que se pone la limit (margen para llenarse en una caída rápida)
+    pub limit_offset_pct: f64,
+    /// Subida mínima (%) del stop antes de reemplazar la orden
+    pub amend_step_pct: f64,
+    /// Espera antes de reintentar si el exchange rechaza el stop
+    pub retry_secs: u64,
+}
+
+/// Cómo se ejecutan las entradas. En `maker` se postea una LIMIT_MAKER en el mejor
+/// bid (o dentro del spread) que sube con el libro; sin libro (backtest) se entra a mercado.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecutionConfig {
+    /// market | maker
+    pub entry_mode: String,
+    /// Ticks por encima del mejor bid, siempre por debajo del ask (0 = en el bid)
+    pub maker_inside_ticks: u32,
+    /// Tiempo mínimo entre re-precios de la orden
+    pub reprice_interval_ms: u64,
+    /// Espera máxima desde la señal hasta completar la entrada
+    pub maker_timeout_secs: u64,
+    /// Subida (%) sobre el precio de la señal a partir de la cual se deja de perseguir
+    pub max_chase_pct: f64,
+    /// Al agotar el tiempo o la subida: market (el resto a mercado) | abandon
+    pub fallback: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BacktestSettings {
+    /// Saldo de la cuenta simulada. Como en testnet, es mayor que el capital de riesgo
+    /// porque `sizing.max_notional` puede superar el 100% del balance.
+    pub account_balance: f64,
+    /// Comisión taker (market y stops disparados)
+    pub fee_rate: f64,
+    /// Comisión de las limit que se llenan en reposo (entradas maker)
+    pub maker_fee_rate: f64,
+    pub slippage: f64,
+}
+
+impl Default for MarketConfig {
+    fn default() -> Self {
+        Self { symbols: vec!["BTCUSDT".to_string()], corr_asset: "ETHUSDT".to_string(), candle_interval: "1s".to_string(), buffer_limit: 14, depth_levels: 10, local_book: true, ofi_window_ms: 5_000 }
+    }
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        Self { path: "models/quantos_brain_v1.json".to_string(), scaler_path: "models/quantos_scaler_v1.json".to_string(), per_symbol: BTreeMap::new() }
+    }
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        Self {
+            entry_confidence: 0.75,
+            exit_noise: 0.75,
+            max_spread_atr_factor: 0.15,
+            assumed_spread_pct: 0.02,
+            max_slippage_pct: 0.05,
+            confidence_tiers: vec![
+                ConfidenceTier { min_confidence: 0.90, multiplier: 1.8 },
+                ConfidenceTier { min_confidence: 0.95, multiplier: 2.5 },
+            ],
+            no_trade_threshold: 0.70,
+            high_confidence_threshold: 0.25,
+            risk_reduction_factor: 0.10,
+            cross_asset_min_correlation: 0.5,
+            cross_asset_weight: 0.05,
+        }
+    }
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            balance_usd: 1000.0,
+            risk_per_trade: 0.01,
+            stop_atr_mult: 2.0,
+            trail_atr_mult: 1.5,
+            min_stop_pct: 0.3,
+            max_stop_pct: 3.0,
+            max_open_risk: 0.05,
+            max_open_positions: 3,
+        }
+    }
+}
+
+impl RiskConfig {
+    /// Distancia del stop loss en fracción del precio para un ATR% dado
+    pub fn stop_distance(&self, atrp: f64) -> f64 {
+        self.atr_distance(atrp, self.stop_atr_mult)
+    }
+
+    /// Distancia del trailing stop en fracción del precio para un ATR% dado
+    pub fn trail_distance(&self, atrp: f64) -> f64 {
+        self.atr_distance(atrp, self.trail_atr_mult)
+    }
+
+    fn atr_distance(&self, atrp: f64, mult: f64) -> f64 {
+        (atrp * mult).clamp(self.min_stop_pct, self.max_stop_pct) / 100.0
+    }
+}
+
+impl Default for SizingConfig {
+    fn default() -> Self {
+        Self {
+            policy: SizingPolicy::FixedFractional,
+            target_vol_pct: 0.1,
+            kelly_fraction: 0.25,
+            kelly_lookback: 100,
+            kelly_min_trades: 20,
+            default_win_rate: 0.55,
+            default_payoff_ratio: 1.0,
+            max_risk_per_trade: 0.02,
+            max_notional: 1.0,
+        }
+    }
+}
+
+impl Default for RiskGateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_daily_loss_usd: 30.0,
+            max_consecutive_losses: 4,
+            max_trades_per_hour: 12,
+            max_symbol_notional_usd: 3_000.0,
+            max_account_notional_usd: 6_000.0,
+            stop_loss_cooldown_secs: 300,
+        }
+    }
+}
+
+impl Default for ViabilityConfig {
+    fn default() -> Self {
+        Self { enabled: true, target_r_multiple: 2.0, slippage_pct: 0.01, min_edge_pct: 0.02 }
+    }
+}
+
+impl Default for ExchangeConfig {
+    fn default() -> Self {
+        Self {
+            mode: "testnet".to_string(),
+            trading_fee: 0.001,
+            maker_fee: 0.001,
+            bnb_fee_discount: false,
+            paper_slippage: 0.0005,
+            paper_balance: 10_000.0,
+            user_stream: true,
+            order_confirm_timeout_secs: 15,
+            market_stream_endpoint: String::new(),
+            market_rest_endpoint: String::new(),
+        }
+    }
+}
+
+impl ExchangeConfig {
+    /// Testnet tiene su propio mercado; paper opera sobre el de mainnet
+    pub fn market_stream_endpoint(&self) -> &str {
+        match (self.market_stream_endpoint.as_str(), self.mode.as_str()) {
+            ("", "testnet") => "wss://stream.testnet.binance.vision",
+            ("", _) => "wss://stream.binance.com:9443",
+            (endpoint, _) => endpoint,
+        }
+    }
+
+    pub fn market_rest_endpoint(&self) -> &str {
+        match (self.market_rest_endpoint.as_str(), self.mode.as_str()) {
+            ("", "testnet") => "https://testnet.binance.vision",
+            ("", _) => "https://api.binance.com",
+            (endpoint, _) => endpoint,
+        }
+    }
+}
+
+impl Default for MacroConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            daily_interval: "1d".to_string(),
+            intraday_interval: "4h".to_string(),
+            ma_fast: 50,
+            ma_slow: 200,
+            rsi_period: 14,
+            rsi_oversold: 30.0,
+            vol_window: 20,
+            high_vol_ratio: 1.5,
+            low_vol_ratio: 0.75,
+            refresh_minutes: 60,
+            rest_endpoint: "https://api.binance.com".to_string(),
+            csv_dir: "data/klines".to_string(),
+            assume_bull: true,
+            assume_rsi_oversold: true,
+        }
+    }
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        Self { enabled: true, state_dir: "state".to_string(), qty_tolerance: 0.01 }
+    }
+}
+
+impl Default for ReconcileConfig {
+    fn default() -> Self {
+        Self { enabled: true, interval_secs: 60, capital_fraction: 0.1, max_drift_usd: 5.0, drift_strikes: 2 }
+    }
+}
+
+impl Default for ProtectionConfig {
+    fn default() -> Self {
+        Self { enabled: true, stop_buffer_pct: 0.1, limit_offset_pct: 0.3, amend_step_pct: 0.1, retry_secs: 30 }
+    }
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self { entry_mode: "market".to_string(), maker_inside_ticks: 0, reprice_interval_ms: 1_000, maker_timeout_secs: 20, max_chase_pct: 0.1, fallback: "abandon".to_string() }
+    }
+}
+
+impl ExecutionConfig {
+    pub fn is_maker(&self) -> bool {
+        self.entry_mode == "maker"
+    }
+}
+
+impl Default for BacktestSettings {
+    fn default() -> Self {
+        Self { account_balance: 10_000.0, fee_rate: 0.0, maker_fee_rate: 0.0, slippage: 0.0 }
+    }
+}
+
+impl StrategyConfig {
+    pub fn risk_multiplier(&self, confidence: f64) -> f64 {
+        self.confidence_tiers.iter().rev()
+            .find(|tier| confidence >= tier.min_confidence)
+            .map(|tier| tier.multiplier)
+            .unwrap_or(1.0)
+    }
+
+    /// Con el activo correlacionado bien acoplado, ir por delante de él suma
+    /// confianza y quedarse atrás la resta. Sin acoplamiento no aporta nada.
+    pub fn cross_asset_bonus(&self, cross: Option<&CrossAssetStats>) -> f64 {
+        match cross {
+            Some(c) if c.correlation >= self.cross_asset_min_correlation => {
+                if c.relative_strength >= 0.0 { self.cross_asset_weight } else { -self.cross_asset_weight }
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+impl MarketConfig {
+    pub fn candle_interval_ms(&self) -> u64 {
+        parse_interval(&self.candle_interval).unwrap_or(1000)
+    }
+
+    /// Moneda de cotización común a todos los símbolos (USDT por defecto)
+    pub fn quote_asset(&self) -> &str {
+        self.symbols.first().and_then(|s| split_symbol(s)).map(|(_, q)| q).unwrap_or("USDT")
+    }
+}
+
+impl ModelConfig {
+    pub fn scaler(&self) -> Option<&str> {
+        Some(self.scaler_path.as_str()).filter(|p| !p.is_empty())
+    }
+
+    /// (modelo, scaler) que corresponde a un símbolo
+    pub fn for_symbol(&self, symbol: &str) -> (&str, Option<&str>) {
+        match self.per_symbol.get(symbol) {
+            Some(m) => (m.path.as_str(), Some(m.scaler_path.as_str()).filter(|p| !p.is_empty())),
+            None => (self.path.as_str(), self.scaler()),
+        }
+    }
+}
+
+impl MacroConfig {
+    pub fn csv_path(&self, symbol: &str, interval: &str) -> String {
+        format!("{}/{}_{}.csv", self.csv_dir, symbol, interval)
+    }
+}
+
+impl PersistenceConfig {
+    pub fn state_path(&self, mode: &str) -> String {
+        format!("{}/positions_{}.json", self.state_dir, mode)
+    }
+}
+
+impl Config {
+    /// Construye la configuración final a partir de las cuatro capas y la valida.
+    /// `cli_overrides` son pares `seccion.clave` → valor en sintaxis TOML.
+    pub fn load(config_path: Option<&str>, cli_overrides: &[(String, String)]) -> Result<Self, Box<dyn Error>> {
+        let mut root = toml::Value::try_from(Config::default())?;
+
+        let path = config_path.or_else(|| Path::new(DEFAULT_CONFIG_PATH).exists().then_some(DEFAULT_CONFIG_PATH));
+        if let Some(path) = path {
+            let content = fs::read_to_string(path).map_err(|e| format!("No se pudo leer {}: {}", path, e))?;
+            let file: toml::Value = toml::from_str(&content).map_err(|e| format!("{}: {}", path, e))?;
+            merge(&mut root, file);
+        }
+
+        for (key, value) in env::vars() {
+            if let Some(path) = key.strip_prefix(ENV_PREFIX) {
+                set_path(&mut root, &env_path(path), &value).map_err(|e| format!("{}: {}", key, e))?;
+            }
+        }
+
+        for (key, value) in cli_overrides {
+            set_path(&mut root, key, value).map_err(|e| format!("--set {}: {}", key, e))?;
+        }
+
+        let config: Config = root.try_into().map_err(|e| format!("Configuración inválida: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Revisa rangos y coherencia; devuelve todos los problemas juntos
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, msg: String| if !ok { errors.push(msg) };
+        let s = &self.strategy;
+
+        check(!self.market.corr_asset.is_empty(), "market.corr_asset está vacío (las features v2 lo necesitan)".into());
+        let symbols = &self.market.symbols;
+        check(!symbols.is_empty(), "market.symbols está vacío".into());
+        check(symbols.iter().collect::<HashSet<_>>().len() == symbols.len(), "market.symbols tiene símbolos repetidos".into());
+        let quotes: HashSet<_> = symbols.iter().map(|s| split_symbol(s).map(|(_, q)| q)).collect();
+        check(!quotes.contains(&None), "market.symbols contiene símbolos con cotización desconocida".into());
+        check(quotes.len() <= 1, "market.symbols debe compartir moneda de cotización (el presupuesto de riesgo es uno)".into());
+        for symbol in self.model.per_symbol.keys() {
+            check(symbols.contains(symbol), format!("model.per_symbol.{} no está en market.symbols", symbol));
+        }
+        check(parse_interval(&self.market.candle_interval).is_some(),
+            format!("market.candle_interval '{}' inválido (ejemplos: 1s, 5s, 1m)", self.market.candle_interval));
+        check(matches!(self.market.depth_levels, 5 | 10 | 20), format!("market.depth_levels debe ser 5, 10 o 20 (es {})", self.market.depth_levels));
+        check(self.market.ofi_window_ms >= 100, "market.ofi_window_ms debe ser >= 100".into());
+        check(self.market.buffer_limit >= 2, format!("market.buffer_limit debe ser >= 2 (es {})", self.market.buffer_limit));
+        check(!self.model.path.is_empty(), "model.path está vacío".into());
+
+        for (name, value) in [
+            ("strategy.entry_confidence", s.entry_confidence),
+            ("strategy.exit_noise", s.exit_noise),
+            ("strategy.no_trade_threshold", s.no_trade_threshold),
+            ("strategy.high_confidence_threshold", s.high_confidence_threshold),
+            ("strategy.risk_reduction_factor", s.risk_reduction_factor),
+            ("strategy.cross_asset_min_correlation", s.cross_asset_min_correlation),
+            ("strategy.cross_asset_weight", s.cross_asset_weight),
+        ] {
+            check((0.0..=1.0).contains(&value), format!("{} debe estar entre 0 y 1 (es {})", name, value));
+        }
+        check(s.high_confidence_threshold < s.no_trade_threshold,
+            "strategy.high_confidence_threshold debe ser menor que strategy.no_trade_threshold".into());
+        check(s.max_spread_atr_factor > 0.0, "strategy.max_spread_atr_factor debe ser > 0".into());
+        check(s.assumed_spread_pct >= 0.0, "strategy.assumed_spread_pct no puede ser negativo".into());
+        check(s.max_slippage_pct > 0.0, "strategy.max_slippage_pct debe ser > 0".into());
+        check(s.confidence_tiers.windows(2).all(|w| w[0].min_confidence < w[1].min_confidence),
+            "strategy.confidence_tiers debe estar ordenado por min_confidence creciente".into());
+        check(s.confidence_tiers.iter().all(|t| t.multiplier > 0.0), "strategy.confidence_tiers: multiplier debe ser > 0".into());
+
+        check(self.risk.balance_usd > 0.0, "risk.balance_usd debe ser > 0".into());
+        check(self.risk.risk_per_trade > 0.0 && self.risk.risk_per_trade <= 1.0,
+            format!("risk.risk_per_trade debe estar en (0, 1] (es {})", self.risk.risk_per_trade));
+        check(self.risk.stop_atr_mult > 0.0, "risk.stop_atr_mult debe ser > 0".into());
+        check(self.risk.trail_atr_mult > 0.0, "risk.trail_atr_mult debe ser > 0".into());
+        check(self.risk.min_stop_pct > 0.0 && self.risk.min_stop_pct <= self.risk.max_stop_pct && self.risk.max_stop_pct < 100.0,
+            "risk.min_stop_pct y risk.max_stop_pct deben cumplir 0 < min <= max < 100".into());
+        check(self.risk.max_open_risk > 0.0 && self.risk.max_open_risk <= 1.0,
+            format!("risk.max_open_risk debe estar en (0, 1] (es {})", self.risk.max_open_risk));
+        check(self.risk.max_open_positions >= 1, "risk.max_open_positions debe ser >= 1".into());
+
+        let z = &self.sizing;
+        check(z.target_vol_pct > 0.0, "sizing.target_vol_pct debe ser > 0".into());
+        check(z.kelly_fraction > 0.0 && z.kelly_fraction <= 1.0, format!("sizing.kelly_fraction debe estar en (0, 1] (es {})", z.kelly_fraction));
+        check(z.kelly_lookback >= z.kelly_min_trades && z.kelly_min_trades >= 1,
+            "sizing.kelly_min_trades debe ser >= 1 y no mayor que sizing.kelly_lookback".into());
+        check(z.default_win_rate > 0.0 && z.default_win_rate < 1.0, format!("sizing.default_win_rate debe estar en (0, 1) (es {})", z.default_win_rate));
+        check(z.default_payoff_ratio > 0.0, "sizing.default_payoff_ratio debe ser > 0".into());
+        check(z.max_risk_per_trade >= self.risk.risk_per_trade && z.max_risk_per_trade <= 1.0,
+            "sizing.max_risk_per_trade debe estar entre risk.risk_per_trade y 1".into());
+        check(z.max_notional > 0.0, "sizing.max_notional debe ser > 0".into());
+
+        let v = &self.viability;
+        check(v.target_r_multiple > 0.0, "viability.target_r_multiple debe ser > 0".into());
+        check(v.slippage_pct >= 0.0, "viability.slippage_pct no puede ser negativo".into());
+        check(v.min_edge_pct.is_finite(), "viability.min_edge_pct debe ser un número".into());
+
+        let g = &self.risk_gate;
+        check(g.max_daily_loss_usd > 0.0, "risk_gate.max_daily_loss_usd debe ser > 0".into());
+        check(g.max_consecutive_losses >= 1, "risk_gate.max_consecutive_losses debe ser >= 1".into());
+        check(g.max_trades_per_hour >= 1, "risk_gate.max_trades_per_hour debe ser >= 1".into());
+        check(g.max_symbol_notional_usd > 0.0 && g.max_symbol_notional_usd <= g.max_account_notional_usd,
+            "risk_gate.max_symbol_notional_usd debe ser > 0 y no mayor que risk_gate.max_account_notional_usd".into());
+
+        check(matches!(self.exchange.mode.as_str(), "testnet" | "mainnet" | "paper"),
+            format!("exchange.mode '{}' inválido (testnet, mainnet o paper)", self.exchange.mode));
+        for (name, value) in [
+            ("exchange.trading_fee", self.exchange.trading_fee),
+            ("exchange.maker_fee", self.exchange.maker_fee),
+            ("exchange.paper_slippage", self.exchange.paper_slippage),
+            ("backtest.fee_rate", self.backtest.fee_rate),
+            ("backtest.maker_fee_rate", self.backtest.maker_fee_rate),
+            ("backtest.slippage", self.backtest.slippage),
+        ] {
+            check((0.0..0.1).contains(&value), format!("{} debe estar en [0, 0.1) (es {})", name, value));
+        }
+        let m = &self.macro_filter;
+        for (name, interval) in [("macro_filter.daily_interval", &m.daily_interval), ("macro_filter.intraday_interval", &m.intraday_interval)] {
+            check(KLINE_INTERVALS.contains(&interval.as_str()), format!("{} '{}' no es un intervalo de klines de Binance", name, interval));
+        }
+        check(m.ma_fast >= 1 && m.ma_fast < m.ma_slow, "macro_filter.ma_fast debe ser >= 1 y menor que macro_filter.ma_slow".into());
+        check(m.ma_slow <= 999, "macro_filter.ma_slow no puede superar 999 (límite de /api/v3/klines)".into());
+        check(m.rsi_period >= 2 && m.rsi_period < m.ma_slow, "macro_filter.rsi_period debe estar entre 2 y macro_filter.ma_slow".into());
+        check(m.rsi_oversold > 0.0 && m.rsi_oversold < 100.0, format!("macro_filter.rsi_oversold debe estar en (0, 100) (es {})", m.rsi_oversold));
+        check(m.vol_window >= 2 && m.vol_window < m.ma_slow, "macro_filter.vol_window debe estar entre 2 y macro_filter.ma_slow".into());
+        check(m.low_vol_ratio < 1.0 && m.high_vol_ratio > 1.0,
+            "macro_filter.low_vol_ratio debe ser < 1 y macro_filter.high_vol_ratio > 1".into());
+        check(m.refresh_minutes >= 1, "macro_filter.refresh_minutes debe ser >= 1".into());
+
+        check(!self.persistence.state_dir.is_empty(), "persistence.state_dir está vacío".into());
+        check(self.persistence.qty_tolerance > 0.0 && self.persistence.qty_tolerance < 0.5,
+            format!("persistence.qty_tolerance debe estar en (0, 0.5) (es {})", self.persistence.qty_tolerance));
+
+        let r = &self.reconcile;
+        check(r.interval_secs >= 5, "reconcile.interval_secs debe ser >= 5 (límites de peso de la API)".into());
+        check(r.capital_fraction > 0.0 && r.capital_fraction <= 1.0,
+            format!("reconcile.capital_fraction debe estar en (0, 1] (es {})", r.capital_fraction));
+        check(r.max_drift_usd > 0.0, "reconcile.max_drift_usd debe ser > 0".into());
+        check(r.drift_strikes >= 1, "reconcile.drift_strikes debe ser >= 1".into());
+
+        let p = &self.protection;
+        for (name, value) in [
+            ("protection.stop_buffer_pct", p.stop_buffer_pct),
+            ("protection.limit_offset_pct", p.limit_offset_pct),
+            ("protection.amend_step_pct", p.amend_step_pct),
+        ] {
+            check((0.0..10.0).contains(&value), format!("{} debe estar en [0, 10) (es {})", name, value));
+        }
+        check(p.retry_secs >= 1, "protection.retry_secs debe ser >= 1".into());
+
+        let x = &self.execution;
+        check(matches!(x.entry_mode.as_str(), "market" | "maker"), format!("execution.entry_mode '{}' inválido (market o maker)", x.entry_mode));
+        check(matches!(x.fallback.as_str(), "market" | "abandon"), format!("execution.fallback '{}' inválido (market o abandon)", x.fallback));
+        check(x.reprice_interval_ms >= 100, "execution.reprice_interval_ms debe ser >= 100 (límites de órdenes de la API)".into());
+        check(x.maker_timeout_secs >= 1, "execution.maker_timeout_secs debe ser >= 1".into());
+        check(x.max_chase_pct > 0.0 && x.max_chase_pct < 10.0, format!("execution.max_chase_pct debe estar en (0, 10) (es {})", x.max_chase_pct));
+
+        check(self.exchange.order_confirm_timeout_secs >= 1, "exchange.order_confirm_timeout_secs debe ser >= 1".into());
+        check(self.exchange.paper_balance > 0.0, "exchange.paper_balance debe ser > 0".into());
+        check(self.exchange.market_stream_endpoint().starts_with("wss://"),
+            format!("exchange.market_stream_endpoint '{}' debe empezar por wss://", self.exchange.market_stream_endpoint));
+        check(self.exchange.market_rest_endpoint().starts_with("https://"),
+            format!("exchange.market_rest_endpoint '{}' debe empezar por https://", self.exchange.market_rest_endpoint));
+        check(self.backtest.account_balance > 0.0, "backtest.account_balance debe ser > 0".into());
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Configuración inválida:\n  - {}", errors.join("\n  - ")).into())
+        }
+    }
+}
+
+fn merge(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) if existing.is_table() && value.is_table() => merge(existing, value),
+                    _ => { base.insert(key, value); }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Asigna `valor` en la ruta `seccion.clave`. El valor se interpreta como TOML
+/// (números, booleanos, arrays); si no lo es, se guarda como texto.
+/// `MODEL__PER_SYMBOL__ETHUSDT__PATH` → `model.per_symbol.ETHUSDT.path`
+fn env_path(var: &str) -> String {
+    let mut path: Vec<String> = Vec::new();
+    for segment in var.split("__") {
+        let in_map = ENV_MAP_SECTIONS.contains(&path.join(".").as_str());
+        path.push(if in_map { segment.to_string() } else { segment.to_lowercase() });
+    }
+    path.join(".")
+}
+
+fn set_path(root: &mut toml::Value, dotted: &str, raw: &str) -> Result<(), String> {
+    let value = toml::from_str::<toml::Table>(&format!("v = {}", raw))
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()));
+
+    let mut node = root;
+    let mut parts = dotted.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let table = node.as_table_mut().ok_or_else(|| format!("'{}' no es una sección", part))?;
+        if parts.peek().is_none() {
+            table.insert(part.to_string(), value);
+            return Ok(());
+        }
+        node = table.entry(part.to_string()).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    }
+    Err("clave vacía".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn market_endpoints_follow_the_mode() {
+        let mut exchange = ExchangeConfig::default();
+        assert_eq!(exchange.market_stream_endpoint(), "wss://stream.testnet.binance.vision");
+        assert_eq!(exchange.market_rest_endpoint(), "https://testnet.binance.vision");
+
+        for mode in ["mainnet", "paper"] {
+            exchange.mode = mode.to_string();
+            assert_eq!(exchange.market_stream_endpoint(), "wss://stream.binance.com:9443");
+            assert_eq!(exchange.market_rest_endpoint(), "https://api.binance.com");
+        }
+
+        exchange.market_stream_endpoint = "wss://data-stream.binance.vision".to_string();
+        assert_eq!(exchange.market_stream_endpoint(), "wss://data-stream.binance.vision");
+    }
+
+    #[test]
+    fn env_path_keeps_map_keys() {
+        assert_eq!(env_path("STRATEGY__ENTRY_CONFIDENCE"), "strategy.entry_confidence");
+        assert_eq!(env_path("MODEL__PER_SYMBOL__ETHUSDT__PATH"), "model.per_symbol.ETHUSDT.path");
+    }
+
+    #[test]
+    fn env_override_reaches_the_symbol_model() {
+        let mut root = toml::Value::try_from(Config::default()).unwrap();
+        set_path(&mut root, &env_path("MODEL__PER_SYMBOL__ETHUSDT__PATH"), "eth.json").unwrap();
+        let config: Config = root.try_into().unwrap();
+        assert_eq!(config.model.for_symbol("ETHUSDT").0, "eth.json");
+    }
+}
@@ -2,31 +2,126 @@ use tokio::sync::mpsc::UnboundedSender;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 use futures_util::{StreamExt, SinkExt};
 use serde::Deserialize;
+use crate::data::order_book::BookUpdate;
 use std::time::Duration;
+use std::fs::OpenOptions;
+use std::io::Write;
 
 // Estructura para parsear el JSON de Binance
 #[derive(Debug, Deserialize)]
 pub struct BinanceAggTrade {
+    #[serde(rename = "s", default)]
+    pub symbol: String,
     #[serde(rename = "p")]
     pub price: String,
     #[serde(rename = "q")]
     pub quantity: String,
+    #[serde(rename = "T")]
+    pub trade_time: u64,
+}
+
+/// Envoltorio del stream combinado: `{"stream": "btcusdt@aggTrade", "data": {...}}`
+#[derive(Debug, Deserialize)]
+pub struct CombinedEvent<T> {
+    pub stream: String,
+    pub data: T,
+}
+
+/// `@bookTicker`: precios y cantidades como texto
+#[derive(Debug, Deserialize)]
+pub struct BinanceBookTicker {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "b")]
+    pub bid: String,
+    #[serde(rename = "B")]
+    pub bid_qty: String,
+    #[serde(rename = "a")]
+    pub ask: String,
+    #[serde(rename = "A")]
+    pub ask_qty: String,
+}
+
+/// `@depth<N>@100ms`: no trae símbolo, sale del nombre del stream
+#[derive(Debug, Deserialize)]
+pub struct BinancePartialDepth {
+    #[serde(rename = "lastUpdateId")]
+    pub last_update_id: u64,
+    pub bids: Vec<[String; 2]>,
+    pub asks: Vec<[String; 2]>,
+}
+
+/// Lo que el stream combinado entrega al loop principal
+#[derive(Clone)]
+pub enum MarketEvent {
+    Trade(PriceMessage),
+    Book(BookUpdate),
 }
 
 // Asegúrate de que esta estructura coincida con lo que espera tu MarketBuffer
 #[derive(Clone)]
 pub struct PriceMessage {
+    pub symbol: String,
     pub price: f64,
     pub volume: f64,
+    pub timestamp: u64, // Hora del trade en ms (campo "T" del aggTrade)
+}
+
+/// URL del stream combinado en `endpoint`: aggTrade de `trade_symbols` y, para `book_symbols`,
+/// bookTicker más profundidad parcial de `depth_levels` niveles (5, 10 o 20)
+pub fn combined_stream_url(endpoint: &str, trade_symbols: &[String], book_symbols: &[String], depth_levels: u32) -> String {
+    let mut streams: Vec<String> = trade_symbols.iter().map(|s| format!("{}@aggTrade", s.to_lowercase())).collect();
+    for symbol in book_symbols {
+        streams.push(format!("{}@bookTicker", symbol.to_lowercase()));
+        streams.push(format!("{}@depth{}@100ms", symbol.to_lowercase(), depth_levels));
+    }
+    format!("{}/stream?streams={}", endpoint, streams.join("/"))
+}
+
+/// Traduce un mensaje del stream combinado según el tipo de stream
+fn parse_combined(event: CombinedEvent<serde_json::Value>) -> Option<MarketEvent> {
+    let (stream_symbol, kind) = event.stream.split_once('@')?;
+    let level = |[price, qty]: &[String; 2]| Some((price.parse::<f64>().ok()?, qty.parse::<f64>().ok()?));
+
+    if kind == "aggTrade" {
+        let parsed = serde_json::from_value::<BinanceAggTrade>(event.data).ok()?;
+        let price = parsed.price.parse::<f64>().unwrap_or(0.0);
+        let volume = parsed.quantity.parse::<f64>().unwrap_or(0.0);
+        Some(MarketEvent::Trade(PriceMessage { symbol: parsed.symbol, price, volume, timestamp: parsed.trade_time }))
+    } else if kind == "bookTicker" {
+        let t = serde_json::from_value::<BinanceBookTicker>(event.data).ok()?;
+        Some(MarketEvent::Book(BookUpdate::Ticker {
+            symbol: t.symbol,
+            bid: t.bid.parse().ok()?,
+            bid_qty: t.bid_qty.parse().ok()?,
+            ask: t.ask.parse().ok()?,
+            ask_qty: t.ask_qty.parse().ok()?,
+        }))
+    } else if kind.starts_with("depth") {
+        let depth = serde_json::from_value::<BinancePartialDepth>(event.data).ok()?;
+        Some(MarketEvent::Book(BookUpdate::Depth {
+            symbol: stream_symbol.to_uppercase(),
+            bids: depth.bids.iter().filter_map(level).collect(),
+            asks: depth.asks.iter().filter_map(level).collect(),
+        }))
+    } else {
+        None
+    }
 }
 
-pub async fn start_market_stream(tx: UnboundedSender<PriceMessage>) {
-    let url = "wss://stream.binance.com:9443/ws/btcusdt@aggTrade";
+/// Una sola conexión para todos los símbolos. Si `record_path` está definido,
+/// cada aggTrade crudo (sin el envoltorio del stream combinado) se guarda como
+/// una línea JSON para poder reproducirlo después en el backtest.
+pub async fn start_market_stream(tx: UnboundedSender<MarketEvent>, endpoint: String, trade_symbols: Vec<String>, book_symbols: Vec<String>, depth_levels: u32, record_path: Option<String>) {
+    let url = combined_stream_url(&endpoint, &trade_symbols, &book_symbols, depth_levels);
+    let mut recorder = record_path.and_then(|path| {
+        OpenOptions::new().create(true).append(true).open(path).ok()
+    });
 
     loop {
-        println!("📡 Conectando al WebSocket de Binance (Testnet)...");
+        println!("📡 Conectando al WebSocket de mercado ({})...", endpoint);
 
-        match connect_async(url).await {
+        match connect_async(url.as_str()).await {
             Ok((mut ws_stream, _)) => {
                 println!("✅ Conexión establecida.");
                 let mut ping_interval = tokio::time::interval(Duration::from_secs(20));
@@ -37,12 +132,15 @@ pub async fn start_market_stream(tx: UnboundedSender<PriceMessage>) {
                         msg = ws_stream.next() => {
                             match msg {
                                 Some(Ok(Message::Text(text))) => {
-                                    if let Ok(parsed) = serde_json::from_str::<BinanceAggTrade>(&text) {
-                                        let price = parsed.price.parse::<f64>().unwrap_or(0.0);
-                                        let volume = parsed.quantity.parse::<f64>().unwrap_or(0.0);
-                                        
+                                    if let Ok(event) = serde_json::from_str::<CombinedEvent<serde_json::Value>>(&text) {
+                                        if let Some(file) = recorder.as_mut().filter(|_| event.stream.ends_with("@aggTrade")) {
+                                            let _ = writeln!(file, "{}", event.data);
+                                        }
+
                                         // Enviamos los datos limpios al main.rs
-                                        let _ = tx.send(PriceMessage { price, volume });
+                                        if let Some(market_event) = parse_combined(event) {
+                                            let _ = tx.send(market_event);
+                                        }
                                     }
                                 }
                                 Some(Ok(Message::Ping(payload))) => {
@@ -71,6 +169,10 @@ pub async fn start_market_stream(tx: UnboundedSender<PriceMessage>) {
                 println!("❌ Error de conexión: {:?}. Reintentando...", e);
             }
         }
+        // Sin conexión, el último bid/ask conocido ya no es el del mercado
+        for symbol in &book_symbols {
+            let _ = tx.send(MarketEvent::Book(BookUpdate::Reset { symbol: symbol.to_uppercase() }));
+        }
         tokio::time::sleep(Duration::from_secs(5)).await;
     }
 }
\ No newline at end of file
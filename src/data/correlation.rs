@@ -0,0 +1,88 @@
+use crate::data::binance_client::PriceMessage;
+use crate::data::resampler::CandleResampler;
+use std::collections::VecDeque;
+
+/// Pilar 6: Correlación Latente. Resamplea el activo correlacionado (ETHUSDT por
+/// defecto) con el mismo intervalo que el principal para alinear sus cierres
+/// vela a vela.
+pub struct CorrelatedAsset {
+    pub symbol: String,
+    resampler: CandleResampler,
+    closes: VecDeque<(u64, f64)>, // (open_time, cierre) de las velas ya cerradas
+    limit: usize,
+}
+
+impl CorrelatedAsset {
+    pub fn new(symbol: &str, interval_ms: u64, limit: usize) -> Self {
+        Self { symbol: symbol.to_string(), resampler: CandleResampler::new(interval_ms), closes: VecDeque::with_capacity(limit), limit: limit.max(1) }
+    }
+
+    pub fn push_trade(&mut self, msg: &PriceMessage) {
+        if let Some(candle) = self.resampler.push_trade(msg.price, msg.volume, msg.timestamp) {
+            if self.closes.len() >= self.limit {
+                self.closes.pop_front();
+            }
+            self.closes.push_back((candle.open_time, candle.close));
+        }
+    }
+
+    /// Último precio conocido del activo correlacionado dentro de la vela `open_time`
+    /// del principal o antes (as-of, nunca mira trades posteriores). Si no operó en
+    /// esa vela, se arrastra el cierre anterior.
+    pub fn close_at(&self, open_time: u64) -> Option<f64> {
+        if let Some(current) = self.resampler.current().filter(|c| c.open_time <= open_time) {
+            return Some(current.close);
+        }
+        self.closes.iter().rev().find(|(t, _)| *t <= open_time).map(|(_, close)| *close)
+    }
+}
+
+/// Relación entre el activo principal y el correlacionado sobre la ventana del buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossAssetStats {
+    /// Correlación de Pearson de los retornos, -1..1
+    pub correlation: f64,
+    /// Sensibilidad del principal a los retornos del correlacionado
+    pub beta: f64,
+    /// Correlación del retorno del principal con el del correlacionado una vela antes
+    pub lead_lag: f64,
+    /// Rendimiento del principal frente al correlacionado en la ventana (0 = igual)
+    pub relative_strength: f64,
+}
+
+impl CrossAssetStats {
+    /// `main` y `corr` son cierres alineados vela a vela (mismo largo)
+    pub fn compute(main: &[f64], corr: &[f64]) -> Option<Self> {
+        if main.len() != corr.len() || main.len() < 3 || main.iter().chain(corr).any(|p| !p.is_finite() || *p <= 0.0) {
+            return None;
+        }
+
+        let main_ret = returns(main);
+        let corr_ret = returns(corr);
+        let var_corr = covariance(&corr_ret, &corr_ret);
+
+        Some(Self {
+            correlation: pearson(&main_ret, &corr_ret),
+            beta: if var_corr > 0.0 { covariance(&main_ret, &corr_ret) / var_corr } else { 0.0 },
+            lead_lag: pearson(&main_ret[1..], &corr_ret[..corr_ret.len() - 1]),
+            relative_strength: (main[main.len() - 1] / main[0]) / (corr[corr.len() - 1] / corr[0]) - 1.0,
+        })
+    }
+}
+
+fn returns(prices: &[f64]) -> Vec<f64> {
+    prices.windows(2).map(|w| (w[1] - w[0]) / w[0]).collect()
+}
+
+fn covariance(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    a.iter().zip(b).map(|(x, y)| (x - mean_a) * (y - mean_b)).sum::<f64>() / n
+}
+
+/// Sin varianza en alguna de las series la correlación se toma como 0
+fn pearson(a: &[f64], b: &[f64]) -> f64 {
+    let denom = (covariance(a, a) * covariance(b, b)).sqrt();
+    if denom > 0.0 { covariance(a, b) / denom } else { 0.0 }
+}
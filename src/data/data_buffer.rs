@@ -1,70 +1,93 @@
+use crate::data::correlation::CrossAssetStats;
+use crate::data::resampler::Candle;
+
+/// Ventana de las últimas `limit` velas OHLCV. `prices` guarda los cierres y
+/// `corr_prices` el precio del activo correlacionado alineado a cada vela
+/// (NaN mientras ese stream no haya enviado nada).
 pub struct MarketBuffer {
+    pub opens: Vec<f64>,
     pub prices: Vec<f64>,
     pub highs: Vec<f64>,
     pub lows: Vec<f64>,
     pub volumes: Vec<f64>,
+    pub trades: Vec<u64>,
+    pub vwaps: Vec<f64>,
+    pub corr_prices: Vec<f64>,
     pub limit: usize,
 }
 
 impl MarketBuffer {
     pub fn new(limit: usize) -> Self {
         Self {
+            opens: Vec::with_capacity(limit),
             prices: Vec::with_capacity(limit),
             highs: Vec::with_capacity(limit),
             lows: Vec::with_capacity(limit),
             volumes: Vec::with_capacity(limit),
+            trades: Vec::with_capacity(limit),
+            vwaps: Vec::with_capacity(limit),
+            corr_prices: Vec::with_capacity(limit),
             limit,
         }
     }
 
-    pub fn add_candle(&mut self, price: f64, volume: f64) {
+    pub fn add_candle(&mut self, candle: &Candle, corr_close: Option<f64>) {
         if self.prices.len() >= self.limit {
+            self.opens.remove(0);
             self.prices.remove(0);
             self.highs.remove(0);
             self.lows.remove(0);
             self.volumes.remove(0);
+            self.trades.remove(0);
+            self.vwaps.remove(0);
+            self.corr_prices.remove(0);
         }
-        
-        
-
-        // Como aggTrade nos da el precio actual, en este milisegundo
-        // el high y low inicial son el mismo precio.
-        self.prices.push(price);
-        self.highs.push(price); 
-        self.lows.push(price);
-        self.volumes.push(volume);
+
+        self.opens.push(candle.open);
+        self.prices.push(candle.close);
+        self.highs.push(candle.high);
+        self.lows.push(candle.low);
+        self.volumes.push(candle.volume);
+        self.trades.push(candle.trades);
+        self.vwaps.push(candle.vwap);
+        self.corr_prices.push(corr_close.unwrap_or(f64::NAN));
     }
 
+    /// ATR en porcentaje del último cierre, con el True Range clásico:
+    /// max(high - low, |high - cierre previo|, |low - cierre previo|)
     pub fn get_atrp(&self) -> f64 {
-    let count = self.prices.len();
-    if count < 2 { return 0.015; } // Valor base de seguridad
-
-    let mut total_tr = 0.0;
-    let mut movements = 0;
-
-    for i in 1..count {
-        let prev_close = self.prices[i-1];
-        let current_close = self.prices[i];
-        
-        // En micro-velas de 1s, el TR más fiable es el salto entre cierres
-        let tr = (current_close - prev_close).abs();
-        
-        if tr > 0.0 {
-            total_tr += tr;
-            movements += 1;
-        }
-    }
+        let count = self.prices.len();
+        if count < 2 { return 0.015; } // Valor base de seguridad
 
-    // Si no hay movimiento
-    if movements == 0 { return 0.005; }
+        let total_tr: f64 = (1..count).map(|i| {
+            let prev_close = self.prices[i - 1];
+            let range = self.highs[i] - self.lows[i];
+            range.max((self.highs[i] - prev_close).abs()).max((self.lows[i] - prev_close).abs())
+        }).sum();
 
-    let avg_tr = total_tr / movements as f64;
-    let current_price = *self.prices.last().unwrap_or(&1.0);
+        // Si no hay movimiento
+        if total_tr == 0.0 { return 0.005; }
 
-    // Retornamos el porcentaje
-    (avg_tr / current_price) * 100.0
-}
-    pub fn get_features(&self) -> Option<Vec<f64>> {
+        let avg_tr = total_tr / (count - 1) as f64;
+        let current_price = *self.prices.last().unwrap_or(&1.0);
+
+        // Retornamos el porcentaje
+        (avg_tr / current_price) * 100.0
+    }
+
+    /// Pilar 6: relación con el activo correlacionado sobre la ventana completa
+    pub fn get_cross_asset(&self) -> Option<CrossAssetStats> {
+        if self.prices.len() < self.limit {
+            return None;
+        }
+        CrossAssetStats::compute(&self.prices, &self.corr_prices)
+    }
+
+    /// Vector de features en el orden de `FeatureSchema::live()`. Con `cross_asset`
+    /// se añaden las 4 del activo correlacionado (esquema v2); si ese stream todavía
+    /// no tiene datos van como NaN, que los árboles envían por su rama por defecto:
+    /// el activo principal nunca se queda sin predicción por culpa del correlacionado.
+    pub fn get_features(&self, cross_asset: bool) -> Option<Vec<f64>> {
         if self.prices.len() < self.limit {
             return None;
         }
@@ -94,7 +117,7 @@ impl MarketBuffer {
         // 6. Volatilidad de los retornos
         let log_ret = (current_price / prev_price).ln();
 
-        // 7. Rango de precio en el buffer (Usando nuestros nuevos vectores de High/Low)
+        // 7. Rango de precio en el buffer (High/Low reales de cada vela)
         let high = self.highs.iter().fold(f64::MIN, |a, &b| a.max(b));
         let low = self.lows.iter().fold(f64::MAX, |a, &b| a.min(b));
         let range = (high - low) / low;
@@ -102,6 +125,53 @@ impl MarketBuffer {
         // 8. Distancia al High
         let dist_high = (high - current_price) / high;
 
-        Some(vec![pct_change, sma, price_dev, er, vol_momentum, log_ret, range, dist_high])
+        let mut features = vec![pct_change, sma, price_dev, er, vol_momentum, log_ret, range, dist_high];
+        if cross_asset {
+            // 9-12. Correlación Latente con el activo correlacionado
+            features.extend(match self.get_cross_asset() {
+                Some(cross) => [cross.correlation, cross.beta, cross.lead_lag, cross.relative_strength],
+                None => [f64::NAN; 4],
+            });
+        }
+        Some(features)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(close: f64) -> Candle {
+        Candle { open_time: 0, open: close, high: close + 1.0, low: close - 1.0, close, volume: 1.0, trades: 1, vwap: close }
+    }
+
+    fn filled(corr: bool) -> MarketBuffer {
+        let mut buffer = MarketBuffer::new(20);
+        for i in 0..20 {
+            let price = 100.0 + (i % 3) as f64;
+            buffer.add_candle(&candle(price), corr.then_some(50.0 + (i % 4) as f64));
+        }
+        buffer
+    }
+
+    #[test]
+    fn v1_vector_has_only_main_asset_features() {
+        let features = filled(false).get_features(false).unwrap();
+        assert_eq!(features.len(), 8);
+        assert!(features.iter().all(|f| f.is_finite()));
+    }
+
+    #[test]
+    fn missing_correlated_asset_does_not_block_the_prediction() {
+        let features = filled(false).get_features(true).unwrap();
+        assert_eq!(features.len(), 12);
+        assert!(features[..8].iter().all(|f| f.is_finite()));
+        assert!(features[8..].iter().all(|f| f.is_nan()));
+    }
+
+    #[test]
+    fn correlated_asset_fills_the_v2_columns() {
+        let features = filled(true).get_features(true).unwrap();
+        assert_eq!(features.len(), 12);
+        assert!(features[8..].iter().all(|f| f.is_finite()));
+    }
+}
@@ -0,0 +1,503 @@
+use crate::trading::gateway::OrderSide;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+const SNAPSHOT_LIMIT: u32 = 1000;
+/// Diffs que un libro sin sincronizar guarda a la espera del snapshot (~100 s a 100ms)
+const MAX_BUFFERED_DIFFS: usize = 1000;
+/// Espera mínima entre snapshots fallidos de un mismo símbolo
+const SNAPSHOT_BACKOFF: Duration = Duration::from_secs(1);
+/// Precios como enteros (1e-8) para poder ordenarlos en el BTreeMap
+const PRICE_SCALE: f64 = 1e8;
+
+pub type SharedBook = Arc<RwLock<LocalOrderBook>>;
+
+/// Evento `depthUpdate` de `<symbol>@depth@100ms`
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepthDiff {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+    #[serde(rename = "b")]
+    pub bids: Vec<[String; 2]>,
+    #[serde(rename = "a")]
+    pub asks: Vec<[String; 2]>,
+}
+
+/// Respuesta de `GET /api/v3/depth`
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepthSnapshot {
+    #[serde(rename = "lastUpdateId")]
+    pub last_update_id: u64,
+    pub bids: Vec<[String; 2]>,
+    pub asks: Vec<[String; 2]>,
+}
+
+/// Resultado de aplicar un diff al libro
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOutcome {
+    Applied,
+    /// Libro sin sincronizar: el diff espera al snapshot
+    Buffered,
+    /// Ya incluido en el snapshot
+    Stale,
+    /// Falta una secuencia: el libro se invalida y hay que resincronizar
+    Gap,
+    /// El snapshot no llega y los diffs superan `MAX_BUFFERED_DIFFS`: se descartan
+    /// los acumulados y hace falta un snapshot posterior
+    Overflow,
+}
+
+/// Coste estimado de barrer el libro con una orden a mercado
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlippageEstimate {
+    pub avg_price: f64,
+    /// Diferencia con el mejor precio, en %
+    pub slippage_pct: f64,
+    pub filled_qty: f64,
+    pub levels: usize,
+}
+
+/// Libro L2 completo de un símbolo: snapshot REST + diffs `@depth@100ms` con la
+/// validación de secuencia `U`/`u` de Binance. Si se pierde un diff, el libro se
+/// marca como no sincronizado y sus métricas devuelven `None` hasta el próximo snapshot.
+pub struct LocalOrderBook {
+    pub symbol: String,
+    bids: BTreeMap<u64, f64>,
+    asks: BTreeMap<u64, f64>,
+    pub last_update_id: u64,
+    pub synced: bool,
+    pub resyncs: u64,
+    buffer: Vec<DepthDiff>,
+    /// Contribuciones al Order Flow Imbalance (hora del evento, cantidad)
+    ofi_events: VecDeque<(u64, f64)>,
+    ofi_window_ms: u64,
+}
+
+impl LocalOrderBook {
+    pub fn new(symbol: &str, ofi_window_ms: u64) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            last_update_id: 0,
+            synced: false,
+            resyncs: 0,
+            buffer: Vec::new(),
+            ofi_events: VecDeque::new(),
+            ofi_window_ms,
+        }
+    }
+
+    /// Hace falta un snapshot y ya hay diffs esperando (Binance pide abrir el stream antes)
+    pub fn needs_snapshot(&self) -> bool {
+        !self.synced && !self.buffer.is_empty()
+    }
+
+    pub fn on_diff(&mut self, diff: DepthDiff) -> DiffOutcome {
+        if !self.synced {
+            let outcome = if self.buffer.len() >= MAX_BUFFERED_DIFFS {
+                self.buffer.clear();
+                self.resyncs += 1;
+                DiffOutcome::Overflow
+            } else {
+                DiffOutcome::Buffered
+            };
+            self.buffer.push(diff);
+            return outcome;
+        }
+        if diff.final_update_id <= self.last_update_id {
+            return DiffOutcome::Stale;
+        }
+        if diff.first_update_id > self.last_update_id + 1 {
+            self.invalidate();
+            self.buffer.push(diff);
+            return DiffOutcome::Gap;
+        }
+        self.apply_diff(&diff);
+        DiffOutcome::Applied
+    }
+
+    /// Carga el snapshot y reproduce los diffs acumulados. Un error deja el libro
+    /// sin sincronizar; el siguiente diff pedirá otro snapshot.
+    pub fn apply_snapshot(&mut self, snapshot: DepthSnapshot) -> Result<(), String> {
+        if let Some(first) = self.buffer.first() {
+            if snapshot.last_update_id + 1 < first.first_update_id {
+                return Err(format!("snapshot {} anterior al primer diff {}", snapshot.last_update_id, first.first_update_id));
+            }
+        }
+
+        self.bids = levels(&snapshot.bids).map(|(p, q)| (price_key(p), q)).collect();
+        self.asks = levels(&snapshot.asks).map(|(p, q)| (price_key(p), q)).collect();
+        self.last_update_id = snapshot.last_update_id;
+        self.ofi_events.clear();
+
+        let buffered = std::mem::take(&mut self.buffer);
+        let mut first = true;
+        for diff in buffered.iter().filter(|d| d.final_update_id > snapshot.last_update_id) {
+            let expected = self.last_update_id + 1;
+            let valid = if first {
+                diff.first_update_id <= expected && expected <= diff.final_update_id
+            } else {
+                diff.first_update_id == expected
+            };
+            if !valid {
+                self.invalidate();
+                return Err(format!("hueco en la secuencia: esperado {} y llegó {}..{}", expected, diff.first_update_id, diff.final_update_id));
+            }
+            self.apply_diff(diff);
+            first = false;
+        }
+
+        self.synced = true;
+        Ok(())
+    }
+
+    fn invalidate(&mut self) {
+        self.synced = false;
+        self.resyncs += 1;
+        self.bids.clear();
+        self.asks.clear();
+        self.ofi_events.clear();
+    }
+
+    fn apply_diff(&mut self, diff: &DepthDiff) {
+        let before = (self.best_bid(), self.best_ask());
+
+        for (price, qty) in levels(&diff.bids) {
+            if qty == 0.0 { self.bids.remove(&price_key(price)); } else { self.bids.insert(price_key(price), qty); }
+        }
+        for (price, qty) in levels(&diff.asks) {
+            if qty == 0.0 { self.asks.remove(&price_key(price)); } else { self.asks.insert(price_key(price), qty); }
+        }
+        self.last_update_id = diff.final_update_id;
+
+        if let ((Some(old_bid), Some(old_ask)), Some(new_bid), Some(new_ask)) = (before, self.best_bid(), self.best_ask()) {
+            self.ofi_events.push_back((diff.event_time, ofi_contribution(old_bid, new_bid, old_ask, new_ask)));
+        }
+        while self.ofi_events.front().is_some_and(|(t, _)| t + self.ofi_window_ms < diff.event_time) {
+            self.ofi_events.pop_front();
+        }
+    }
+
+    /// Mejor bid (precio, cantidad)
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids.iter().next_back().map(|(k, q)| (key_price(*k), *q))
+    }
+
+    /// Mejor ask (precio, cantidad)
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks.iter().next().map(|(k, q)| (key_price(*k), *q))
+    }
+
+    /// Niveles desde el mejor precio hacia fuera
+    fn side_levels(&self, side: OrderSide) -> Box<dyn Iterator<Item = (f64, f64)> + '_> {
+        match side {
+            OrderSide::Buy => Box::new(self.bids.iter().rev().map(|(k, q)| (key_price(*k), *q))),
+            OrderSide::Sell => Box::new(self.asks.iter().map(|(k, q)| (key_price(*k), *q))),
+        }
+    }
+
+    /// Cantidad en los primeros `levels` niveles del lado indicado (Buy = bids, Sell = asks)
+    pub fn queue_depth(&self, side: OrderSide, levels: usize) -> Option<f64> {
+        self.synced.then(|| self.side_levels(side).take(levels).map(|(_, q)| q).sum())
+    }
+
+    /// Order Flow Imbalance (Cont, Kukanov y Stoikov) acumulado en la ventana, en
+    /// unidades del activo base: positivo = presión compradora en el top del libro
+    pub fn order_flow_imbalance(&self) -> Option<f64> {
+        self.synced.then(|| self.ofi_events.iter().map(|(_, e)| e).sum())
+    }
+
+    /// Barre el libro con una orden a mercado de `qty` (Buy consume asks, Sell consume bids).
+    /// `None` si el libro no está sincronizado o no tiene cantidad suficiente.
+    pub fn estimate_slippage(&self, side: OrderSide, qty: f64) -> Option<SlippageEstimate> {
+        if !self.synced || qty <= 0.0 { return None; }
+        let opposite = match side { OrderSide::Buy => OrderSide::Sell, OrderSide::Sell => OrderSide::Buy };
+
+        let (mut remaining, mut cost, mut used, mut best) = (qty, 0.0, 0, None);
+        for (price, level_qty) in self.side_levels(opposite) {
+            if remaining <= 0.0 { break; }
+            best.get_or_insert(price);
+            let take = remaining.min(level_qty);
+            cost += take * price;
+            remaining -= take;
+            used += 1;
+        }
+
+        let best = best?;
+        if remaining > 0.0 { return None; }
+        let avg_price = cost / qty;
+        Some(SlippageEstimate {
+            avg_price,
+            slippage_pct: (avg_price - best).abs() / best * 100.0,
+            filled_qty: qty,
+            levels: used,
+        })
+    }
+}
+
+/// Contribución de un cambio del top del libro al OFI
+fn ofi_contribution(old_bid: (f64, f64), new_bid: (f64, f64), old_ask: (f64, f64), new_ask: (f64, f64)) -> f64 {
+    let mut e = 0.0;
+    if new_bid.0 >= old_bid.0 { e += new_bid.1; }
+    if new_bid.0 <= old_bid.0 { e -= old_bid.1; }
+    if new_ask.0 <= old_ask.0 { e -= new_ask.1; }
+    if new_ask.0 >= old_ask.0 { e += old_ask.1; }
+    e
+}
+
+fn levels(raw: &[[String; 2]]) -> impl Iterator<Item = (f64, f64)> + '_ {
+    raw.iter().filter_map(|[p, q]| Some((p.parse::<f64>().ok()?, q.parse::<f64>().ok()?)))
+}
+
+fn price_key(price: f64) -> u64 {
+    (price * PRICE_SCALE).round() as u64
+}
+
+fn key_price(key: u64) -> f64 {
+    key as f64 / PRICE_SCALE
+}
+
+pub async fn fetch_depth_snapshot(rest_endpoint: &str, symbol: &str) -> Result<DepthSnapshot, Box<dyn Error + Send + Sync>> {
+    let url = format!("{}/api/v3/depth?symbol={}&limit={}", rest_endpoint, symbol, SNAPSHOT_LIMIT);
+    Ok(reqwest::get(url).await?.error_for_status()?.json::<DepthSnapshot>().await?)
+}
+
+/// Mantiene sincronizados los libros locales con su propia conexión `@depth@100ms`.
+/// Los diffs se acumulan en cada libro hasta que llega el snapshot; ante un hueco
+/// de secuencia o una reconexión el libro se invalida y se vuelve a pedir.
+/// Los snapshots se piden en tareas aparte para no dejar de leer el WebSocket
+/// mientras responde el REST.
+/// Stream y REST deben ser de la misma red: el snapshot y los diffs, del mismo libro.
+pub async fn start_depth_sync(books: Vec<SharedBook>, stream_endpoint: String, rest_endpoint: String) {
+    let symbols: Vec<String> = books.iter().filter_map(|b| b.read().ok().map(|b| b.symbol.clone())).collect();
+    let streams: Vec<String> = symbols.iter().map(|s| format!("{}@depth@100ms", s.to_lowercase())).collect();
+    let url = format!("{}/stream?streams={}", stream_endpoint, streams.join("/"));
+    let by_symbol: HashMap<String, SharedBook> = symbols.into_iter().zip(books).collect();
+    let mut next_snapshot: HashMap<String, Instant> = HashMap::new();
+    let (snapshot_tx, mut snapshot_rx) = mpsc::unbounded_channel::<(String, Result<DepthSnapshot, String>)>();
+    let mut in_flight: HashSet<String> = HashSet::new();
+
+    loop {
+        match connect_async(url.as_str()).await {
+            Ok((mut ws_stream, _)) => {
+                println!("📚 Libro L2 conectado ({} símbolos)", by_symbol.len());
+                loop {
+                    tokio::select! {
+                        msg = ws_stream.next() => {
+                            let text = match msg {
+                                Some(Ok(Message::Text(text))) => text,
+                                Some(Ok(Message::Ping(payload))) => { let _ = ws_stream.send(Message::Pong(payload)).await; continue; }
+                                Some(Ok(_)) => continue,
+                                Some(Err(e)) => { println!("\n❌ Error en el stream L2: {:?}", e); break; }
+                                None => break,
+                            };
+                            let Ok(event) = serde_json::from_str::<crate::data::binance_client::CombinedEvent<DepthDiff>>(&text) else { continue };
+                            let Some(book) = by_symbol.get(&event.data.symbol) else { continue };
+                            let symbol = event.data.symbol.clone();
+
+                            let needs_snapshot = match book.write() {
+                                Ok(mut book) => {
+                                    match book.on_diff(event.data) {
+                                        DiffOutcome::Gap => println!("\n⚠️ {} | Hueco en el libro L2, resincronizando...", symbol),
+                                        DiffOutcome::Overflow => println!("\n⚠️ {} | Demasiados diffs sin snapshot: se descartan y se pide otro", symbol),
+                                        _ => {}
+                                    }
+                                    book.needs_snapshot()
+                                }
+                                Err(_) => false,
+                            };
+                            if !needs_snapshot || in_flight.contains(&symbol) || next_snapshot.get(&symbol).is_some_and(|t| Instant::now() < *t) {
+                                continue;
+                            }
+
+                            in_flight.insert(symbol.clone());
+                            let (tx, rest_endpoint) = (snapshot_tx.clone(), rest_endpoint.clone());
+                            tokio::spawn(async move {
+                                let result = fetch_depth_snapshot(&rest_endpoint, &symbol).await.map_err(|e| e.to_string());
+                                let _ = tx.send((symbol, result));
+                            });
+                        }
+                        Some((symbol, result)) = snapshot_rx.recv() => {
+                            in_flight.remove(&symbol);
+                            let Some(book) = by_symbol.get(&symbol) else { continue };
+                            // Un snapshot pedido antes de un hueco o una reconexión no pasa la validación de secuencia
+                            let result = result.and_then(|snapshot| book.write().map_err(|e| e.to_string()).and_then(|mut b| b.apply_snapshot(snapshot)));
+                            if let Err(e) = result {
+                                println!("\n⚠️ {} | Snapshot L2 descartado: {}", symbol, e);
+                                next_snapshot.insert(symbol, Instant::now() + SNAPSHOT_BACKOFF);
+                            }
+                        }
+                    }
+                }
+            }
+            Err(e) => println!("❌ Error de conexión L2: {:?}. Reintentando...", e),
+        }
+
+        // Tras una desconexión los diffs perdidos invalidan todos los libros
+        for book in by_symbol.values() {
+            if let Ok(mut book) = book.write() {
+                if book.synced { book.invalidate(); }
+                book.buffer.clear();
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(first_update_id: u64, final_update_id: u64) -> DepthDiff {
+        DepthDiff { symbol: "BTCUSDT".to_string(), event_time: final_update_id, first_update_id, final_update_id,
+            bids: vec![["100.0".to_string(), "1.0".to_string()]], asks: vec![["101.0".to_string(), "1.0".to_string()]] }
+    }
+
+    fn snapshot(last_update_id: u64) -> DepthSnapshot {
+        DepthSnapshot { last_update_id, bids: vec![["99.0".to_string(), "2.0".to_string()]], asks: vec![["102.0".to_string(), "2.0".to_string()]] }
+    }
+
+    fn level(price: &str, qty: &str) -> [String; 2] {
+        [price.to_string(), qty.to_string()]
+    }
+
+    /// Diff con los niveles indicados en lugar de los de `diff`
+    fn diff_with(id: u64, bids: Vec<[String; 2]>, asks: Vec<[String; 2]>) -> DepthDiff {
+        DepthDiff { bids, asks, ..diff(id, id) }
+    }
+
+    /// Libro sincronizado en el `snapshot(100)`, sin diffs pendientes
+    fn synced_book() -> LocalOrderBook {
+        let mut book = LocalOrderBook::new("BTCUSDT", 5_000);
+        book.on_diff(diff(100, 101));
+        book.apply_snapshot(snapshot(100)).unwrap();
+        book
+    }
+
+    #[test]
+    fn stale_diffs_before_the_snapshot_are_dropped() {
+        let mut book = LocalOrderBook::new("BTCUSDT", 5_000);
+        for (first, last) in [(90, 95), (96, 100), (101, 103)] {
+            assert_eq!(book.on_diff(diff(first, last)), DiffOutcome::Buffered);
+        }
+        book.apply_snapshot(snapshot(100)).unwrap();
+        assert!(book.synced);
+        assert_eq!(book.last_update_id, 103);
+        assert_eq!(book.on_diff(diff(102, 103)), DiffOutcome::Stale);
+        assert_eq!(book.on_diff(diff(104, 105)), DiffOutcome::Applied);
+    }
+
+    #[test]
+    fn first_diff_must_straddle_the_snapshot() {
+        // U <= lastUpdateId + 1 <= u: el primer diff válido cubre el 101
+        let mut book = LocalOrderBook::new("BTCUSDT", 5_000);
+        book.on_diff(diff(95, 105));
+        book.on_diff(diff(106, 106));
+        book.apply_snapshot(snapshot(100)).unwrap();
+        assert_eq!(book.last_update_id, 106);
+        assert_eq!(book.best_bid(), Some((100.0, 1.0)));
+
+        // Si el primer diff posterior empieza en el 102, falta el 101
+        let mut book = LocalOrderBook::new("BTCUSDT", 5_000);
+        book.on_diff(diff(102, 104));
+        assert!(book.apply_snapshot(snapshot(100)).is_err());
+        assert!(!book.synced);
+    }
+
+    #[test]
+    fn gap_invalidates_the_book_until_a_new_snapshot() {
+        let mut book = synced_book();
+        assert_eq!(book.on_diff(diff(103, 104)), DiffOutcome::Gap);
+        assert!(!book.synced);
+        assert_eq!(book.resyncs, 1);
+        assert!(book.needs_snapshot());
+        assert_eq!(book.best_bid(), None);
+        assert_eq!(book.queue_depth(OrderSide::Buy, 5), None);
+        assert_eq!(book.order_flow_imbalance(), None);
+
+        // El diff que descubrió el hueco espera al siguiente snapshot
+        book.apply_snapshot(snapshot(102)).unwrap();
+        assert!(book.synced);
+        assert_eq!(book.last_update_id, 104);
+    }
+
+    #[test]
+    fn slippage_walks_several_levels() {
+        let mut book = synced_book();
+        book.on_diff(diff_with(102, vec![], vec![level("101.0", "0"), level("103.0", "1.0"), level("104.0", "2.0")]));
+        // Asks: 102 × 2, 103 × 1, 104 × 2
+        let estimate = book.estimate_slippage(OrderSide::Buy, 4.0).unwrap();
+        assert_eq!(estimate.levels, 3);
+        assert_eq!(estimate.filled_qty, 4.0);
+        assert!((estimate.avg_price - 102.75).abs() < 1e-9);
+        assert!((estimate.slippage_pct - 0.75 / 102.0 * 100.0).abs() < 1e-9);
+
+        let top = book.estimate_slippage(OrderSide::Buy, 1.0).unwrap();
+        assert_eq!((top.levels, top.slippage_pct), (1, 0.0));
+        // Sell barre los bids: 100 × 1 y 99 × 2
+        assert!((book.estimate_slippage(OrderSide::Sell, 2.0).unwrap().avg_price - 99.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn slippage_is_none_without_enough_depth() {
+        let book = synced_book();
+        assert!(book.estimate_slippage(OrderSide::Buy, 3.0).is_some());
+        assert_eq!(book.estimate_slippage(OrderSide::Buy, 3.5), None);
+        assert_eq!(book.estimate_slippage(OrderSide::Buy, 0.0), None);
+        assert_eq!(LocalOrderBook::new("BTCUSDT", 5_000).estimate_slippage(OrderSide::Buy, 1.0), None);
+    }
+
+    #[test]
+    fn ofi_sign_follows_the_pressure_at_the_top() {
+        // Sube el bid: presión compradora
+        let mut book = synced_book();
+        book.on_diff(diff_with(102, vec![level("100.5", "3.0")], vec![]));
+        assert!(book.order_flow_imbalance().unwrap() > 0.0);
+
+        // Baja el ask: presión vendedora
+        let mut book = synced_book();
+        book.on_diff(diff_with(102, vec![], vec![level("100.5", "3.0")]));
+        assert!(book.order_flow_imbalance().unwrap() < 0.0);
+
+        // Los eventos fuera de la ventana dejan de contar
+        let mut later = diff_with(10_000, vec![], vec![]);
+        later.first_update_id = 103;
+        book.on_diff(later);
+        assert_eq!(book.order_flow_imbalance(), Some(0.0));
+    }
+
+    #[test]
+    fn buffer_overflow_drops_old_diffs_and_needs_a_newer_snapshot() {
+        let mut book = LocalOrderBook::new("BTCUSDT", 5_000);
+        for id in 1..=MAX_BUFFERED_DIFFS as u64 {
+            assert_eq!(book.on_diff(diff(id, id)), DiffOutcome::Buffered);
+        }
+        let next = MAX_BUFFERED_DIFFS as u64 + 1;
+        assert_eq!(book.on_diff(diff(next, next)), DiffOutcome::Overflow);
+        assert_eq!(book.buffer.len(), 1);
+        assert_eq!(book.resyncs, 1);
+        assert!(book.needs_snapshot());
+
+        // El snapshot pedido antes del desbordamiento ya no sirve
+        assert!(book.apply_snapshot(snapshot(10)).is_err());
+        assert!(!book.synced);
+
+        book.apply_snapshot(snapshot(next - 1)).unwrap();
+        assert!(book.synced);
+        assert_eq!(book.last_update_id, next);
+        assert_eq!(book.on_diff(diff(next + 1, next + 1)), DiffOutcome::Applied);
+    }
+}
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+
+/// Versión más reciente del vector de features que genera `MarketBuffer::get_features`.
+/// Cualquier cambio de nombre, orden o unidad obliga a subirla y reentrenar.
+pub const FEATURE_SCHEMA_VERSION: u32 = 2;
+
+/// Columnas de cada versión soportada: cada una añade las suyas al final de la
+/// anterior, así los modelos v1 siguen cargando con las 8 primeras.
+const VERSION_FEATURES: [(u32, usize); 2] = [(1, 8), (2, 12)];
+
+/// (nombre, unidad) de cada feature, en el orden exacto del vector
+const LIVE_FEATURES: [(&str, &str); 12] = [
+    ("pct_change", "ratio"),       // Cambio porcentual de la última vela
+    ("sma", "quote"),              // Media móvil simple, en moneda de cotización (USDT)
+    ("price_dev", "ratio"),        // Desviación del cierre respecto a la SMA
+    ("efficiency_ratio", "unit"),  // Efficiency Ratio de Kaufman, 0..1
+    ("vol_momentum", "multiple"),  // Volumen actual / volumen medio
+    ("log_return", "log"),         // Retorno logarítmico de la última vela
+    ("range", "ratio"),            // (High - Low) / Low de la ventana
+    ("dist_high", "ratio"),        // Distancia del cierre al High de la ventana
+    ("corr", "coef"),              // v2: correlación de retornos con el activo correlacionado, -1..1
+    ("corr_beta", "multiple"),     // v2: beta frente al activo correlacionado
+    ("corr_lead_lag", "coef"),     // v2: correlación con el correlacionado desfasado una vela
+    ("rel_strength", "ratio"),     // v2: rendimiento relativo frente al correlacionado
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureSpec {
+    pub name: String,
+    pub unit: String,
+}
+
+/// Contrato entre el buffer y el modelo: se guarda junto a cada artefacto
+/// (`modelo.json` → `modelo.schema.json`) y se compara al arrancar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureSchema {
+    pub version: u32,
+    pub features: Vec<FeatureSpec>,
+}
+
+impl FeatureSchema {
+    /// Esquema más reciente que produce este binario (el que se publica para entrenar)
+    pub fn live() -> Self {
+        Self::for_version(FEATURE_SCHEMA_VERSION).unwrap_or_else(|| Self { version: FEATURE_SCHEMA_VERSION, features: Vec::new() })
+    }
+
+    /// Esquema que genera el buffer para una versión soportada
+    pub fn for_version(version: u32) -> Option<Self> {
+        let (_, count) = VERSION_FEATURES.iter().find(|(v, _)| *v == version)?;
+        Some(Self {
+            version,
+            features: LIVE_FEATURES[..*count].iter().map(|(name, unit)| FeatureSpec { name: name.to_string(), unit: unit.to_string() }).collect(),
+        })
+    }
+
+    /// El vector lleva las features del activo correlacionado (v2 en adelante)
+    pub fn uses_cross_asset(&self) -> bool {
+        self.version >= 2
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.features.iter().map(|f| f.name.as_str()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path).map_err(|e| format!("No se pudo leer el esquema {}: {}", path, e))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Ruta del esquema que acompaña a un modelo: `x/modelo.json` → `x/modelo.schema.json`
+    pub fn path_for_model(model_path: &str) -> String {
+        format!("{}.schema.json", model_path.strip_suffix(".json").unwrap_or(model_path))
+    }
+
+    /// El esquema del modelo debe ser exactamente el que el buffer genera para su versión
+    pub fn check_supported(&self) -> Result<(), Box<dyn Error>> {
+        let live = Self::for_version(self.version)
+            .ok_or_else(|| format!("Esquema de features v{} no soportado (este binario genera v1..v{})", self.version, FEATURE_SCHEMA_VERSION))?;
+        self.check_compatible(&live)
+    }
+
+    /// Error descriptivo con la primera diferencia entre el esquema del modelo y el vivo
+    pub fn check_compatible(&self, live: &FeatureSchema) -> Result<(), Box<dyn Error>> {
+        if self.version != live.version {
+            return Err(format!("Esquema de features v{} del modelo vs v{} en vivo", self.version, live.version).into());
+        }
+        if self.len() != live.len() {
+            return Err(format!("El modelo usa {} features y el buffer genera {}", self.len(), live.len()).into());
+        }
+        for (i, (model, live)) in self.features.iter().zip(&live.features).enumerate() {
+            if model != live {
+                return Err(format!("Feature {} distinta: modelo '{}' ({}) vs en vivo '{}' ({})",
+                    i, model.name, model.unit, live.name, live.unit).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_mismatch_is_rejected() {
+        let mut model = FeatureSchema::live();
+        model.version = FEATURE_SCHEMA_VERSION + 1;
+        let err = model.check_compatible(&FeatureSchema::live()).unwrap_err();
+        assert!(err.to_string().contains("Esquema de features"));
+    }
+
+    #[test]
+    fn length_mismatch_is_rejected() {
+        let mut model = FeatureSchema::live();
+        model.features.pop();
+        let err = model.check_compatible(&FeatureSchema::live()).unwrap_err();
+        assert!(err.to_string().contains("features y el buffer genera"));
+    }
+
+    #[test]
+    fn name_order_mismatch_reports_the_first_difference() {
+        let mut model = FeatureSchema::live();
+        model.features.swap(1, 2);
+        let err = model.check_compatible(&FeatureSchema::live()).unwrap_err();
+        assert!(err.to_string().starts_with("Feature 1 distinta"));
+    }
+
+    #[test]
+    fn every_supported_version_is_a_prefix_of_the_live_schema() {
+        let v1 = FeatureSchema::for_version(1).unwrap();
+        assert_eq!(v1.len(), 8);
+        assert!(v1.check_supported().is_ok());
+        assert!(!v1.uses_cross_asset());
+        assert_eq!(v1.features[..], FeatureSchema::live().features[..8]);
+        assert!(FeatureSchema::live().check_supported().is_ok());
+        assert!(FeatureSchema::for_version(0).is_none());
+    }
+}
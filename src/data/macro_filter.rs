@@ -1,18 +1,359 @@
+use crate::config::MacroConfig;
 use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
 
+type MacroResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Régimen de volatilidad: volatilidad reciente frente a la de toda la ventana diaria
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolatilityRegime {
+    Low,
+    Normal,
+    High,
+}
+
+/// Vela de `/api/v3/klines` (o de un CSV de data.binance.vision)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Kline {
+    pub open_time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub close_time: u64,
+}
+
+/// Contexto macro de un símbolo: tendencia diaria (MA rápida/lenta), RSI en el
+/// intervalo intradía y régimen de volatilidad. Alimenta la confianza del motor.
+#[derive(Debug, Clone)]
 pub struct MacroFilter {
     pub is_bull_market: bool,
     pub rsi_oversold: bool,
+    pub rsi: f64,
+    pub ma_fast: f64,
+    pub ma_slow: f64,
+    /// Desviación típica de los retornos diarios recientes, en %
+    pub volatility_pct: f64,
+    pub volatility_regime: VolatilityRegime,
+    /// "REST", "CSV" o "ASUMIDO"
+    pub source: &'static str,
 }
 
 impl MacroFilter {
-    pub async fn get_market_context() -> Result<Self, Box<dyn Error>> {
-        // En una versión futura, aquí usaremos reqwest para bajar el CSV de Yahoo
-        // Por ahora, devolvemos un estado por defecto para que el bot compile y corra
-        
+    /// Flags de la config mientras no haya datos (o con el filtro desactivado)
+    pub fn assumed(config: &MacroConfig) -> Self {
+        Self {
+            is_bull_market: config.assume_bull,
+            rsi_oversold: config.assume_rsi_oversold,
+            rsi: 50.0,
+            ma_fast: 0.0,
+            ma_slow: 0.0,
+            volatility_pct: 0.0,
+            volatility_regime: VolatilityRegime::Normal,
+            source: "ASUMIDO",
+        }
+    }
+
+    /// Baja las klines de Binance y las cachea en CSV. Si la API falla,
+    /// se usa la última copia local.
+    pub async fn get_market_context(config: &MacroConfig, symbol: &str) -> MacroResult<Self> {
+        let limit = config.ma_slow + 1;
+        let fetched = async {
+            let daily = fetch_klines(&config.rest_endpoint, symbol, &config.daily_interval, limit).await?;
+            let intraday = fetch_klines(&config.rest_endpoint, symbol, &config.intraday_interval, limit).await?;
+            MacroResult::Ok((daily, intraday))
+        }.await;
+
+        match fetched {
+            Ok((daily, intraday)) => {
+                // La caché es opcional: un disco de solo lectura no debe parar el bot
+                let _ = save_klines_csv(&config.csv_path(symbol, &config.daily_interval), &daily);
+                let _ = save_klines_csv(&config.csv_path(symbol, &config.intraday_interval), &intraday);
+                Ok(Self { source: "REST", ..Self::from_klines(config, &daily, &intraday)? })
+            }
+            Err(e) => Self::from_csv(config, symbol, None).map_err(|csv| format!("REST: {} | CSV: {}", e, csv).into()),
+        }
+    }
+
+    /// Contexto a partir de los CSV locales. Con `as_of` (ms) solo cuentan las velas
+    /// cerradas antes de ese instante, como al arrancar un backtest.
+    pub fn from_csv(config: &MacroConfig, symbol: &str, as_of: Option<u64>) -> MacroResult<Self> {
+        let cutoff = |klines: Vec<Kline>| -> Vec<Kline> {
+            klines.into_iter().filter(|k| as_of.is_none_or(|t| k.close_time < t)).collect()
+        };
+        let daily = cutoff(load_klines_csv(&config.csv_path(symbol, &config.daily_interval))?);
+        let intraday = cutoff(load_klines_csv(&config.csv_path(symbol, &config.intraday_interval))?);
+        Ok(Self { source: "CSV", ..Self::from_klines(config, &daily, &intraday)? })
+    }
+
+    pub fn from_klines(config: &MacroConfig, daily: &[Kline], intraday: &[Kline]) -> MacroResult<Self> {
+        if daily.len() < config.ma_slow {
+            return Err(format!("Se necesitan {} velas {} y hay {}", config.ma_slow, config.daily_interval, daily.len()).into());
+        }
+        let closes: Vec<f64> = daily.iter().map(|k| k.close).collect();
+        let intraday_closes: Vec<f64> = intraday.iter().map(|k| k.close).collect();
+        let rsi = rsi(&intraday_closes, config.rsi_period)
+            .ok_or_else(|| format!("Se necesitan más de {} velas {} para el RSI", config.rsi_period, config.intraday_interval))?;
+
+        // 1. Tendencia: precio y MA rápida por encima de la lenta
+        let ma_fast = sma(&closes, config.ma_fast);
+        let ma_slow = sma(&closes, config.ma_slow);
+        let last_close = closes[closes.len() - 1];
+
+        // 2. Volatilidad reciente vs la de toda la ventana
+        let returns: Vec<f64> = closes.windows(2).map(|w| (w[1] / w[0]).ln()).collect();
+        let recent = std_dev(&returns[returns.len().saturating_sub(config.vol_window)..]);
+        let long_run = std_dev(&returns);
+        let ratio = if long_run > 0.0 { recent / long_run } else { 1.0 };
+        let volatility_regime = if ratio >= config.high_vol_ratio {
+            VolatilityRegime::High
+        } else if ratio <= config.low_vol_ratio {
+            VolatilityRegime::Low
+        } else {
+            VolatilityRegime::Normal
+        };
+
         Ok(Self {
-            is_bull_market: true,   // El bot asume mercado alcista por defecto
-            rsi_oversold: false,
+            is_bull_market: last_close > ma_slow && ma_fast > ma_slow,
+            rsi_oversold: rsi < config.rsi_oversold,
+            rsi,
+            ma_fast,
+            ma_slow,
+            volatility_pct: recent * 100.0,
+            volatility_regime,
+            source: "REST",
         })
     }
-}
\ No newline at end of file
+}
+
+/// Refresca el contexto de cada símbolo cada `refresh_minutes` y lo envía al loop principal
+pub async fn run_macro_refresh(config: MacroConfig, symbols: Vec<String>, tx: UnboundedSender<(String, MacroFilter)>) {
+    loop {
+        for symbol in &symbols {
+            match MacroFilter::get_market_context(&config, symbol).await {
+                Ok(context) => {
+                    if tx.send((symbol.clone(), context)).is_err() { return; }
+                }
+                Err(e) => println!("\n⚠️ Macro {} sin datos: {}", symbol, e),
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(config.refresh_minutes * 60)).await;
+    }
+}
+
+/// Solo devuelve velas ya cerradas (Binance incluye la vela en curso al final)
+pub async fn fetch_klines(endpoint: &str, symbol: &str, interval: &str, limit: usize) -> MacroResult<Vec<Kline>> {
+    let url = format!("{}/api/v3/klines?symbol={}&interval={}&limit={}", endpoint, symbol, interval, limit.min(1000));
+    let rows = reqwest::get(url).await?.error_for_status()?.json::<Vec<Vec<serde_json::Value>>>().await?;
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+
+    let mut klines = Vec::with_capacity(rows.len());
+    for row in rows {
+        let field = |i: usize| -> MacroResult<f64> {
+            let value = row.get(i).ok_or("kline incompleta")?;
+            Ok(value.as_str().map(str::parse).transpose()?.or(value.as_f64()).ok_or("kline con campo inválido")?)
+        };
+        let kline = Kline {
+            open_time: field(0)? as u64,
+            open: field(1)?,
+            high: field(2)?,
+            low: field(3)?,
+            close: field(4)?,
+            volume: field(5)?,
+            close_time: field(6)? as u64,
+        };
+        if kline.close_time < now {
+            klines.push(kline);
+        }
+    }
+    Ok(klines)
+}
+
+/// CSV de klines: `open_time,open,high,low,close,volume,close_time[,...]`, con o sin cabecera
+pub fn load_klines_csv(path: &str) -> MacroResult<Vec<Kline>> {
+    let content = fs::read_to_string(path).map_err(|e| format!("No se pudo leer {}: {}", path, e))?;
+    let mut klines = Vec::new();
+
+    for (line_no, line) in content.lines().enumerate() {
+        let cols: Vec<&str> = line.trim().split(',').map(str::trim).collect();
+        let parsed = (|| {
+            // Desde 2025 los dumps de Spot vienen en microsegundos
+            let time = |s: &str| s.parse::<u64>().ok().map(|t| if t > 100_000_000_000_000 { t / 1000 } else { t });
+            Some(Kline {
+                open_time: time(cols.first()?)?,
+                open: cols.get(1)?.parse().ok()?,
+                high: cols.get(2)?.parse().ok()?,
+                low: cols.get(3)?.parse().ok()?,
+                close: cols.get(4)?.parse().ok()?,
+                volume: cols.get(5)?.parse().ok()?,
+                close_time: time(cols.get(6)?)?,
+            })
+        })();
+        match parsed {
+            Some(kline) => klines.push(kline),
+            None if line_no == 0 || line.trim().is_empty() => continue,
+            None => return Err(format!("Línea {} inválida en {}", line_no + 1, path).into()),
+        }
+    }
+
+    klines.sort_by_key(|k| k.open_time);
+    Ok(klines)
+}
+
+pub fn save_klines_csv(path: &str, klines: &[Kline]) -> MacroResult<()> {
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut content = String::from("open_time,open,high,low,close,volume,close_time\n");
+    for k in klines {
+        content.push_str(&format!("{},{},{},{},{},{},{}\n", k.open_time, k.open, k.high, k.low, k.close, k.volume, k.close_time));
+    }
+    fs::write(path, content)?;
+    Ok(())
+}
+
+fn sma(values: &[f64], period: usize) -> f64 {
+    let window = &values[values.len().saturating_sub(period)..];
+    window.iter().sum::<f64>() / window.len() as f64
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 { return 0.0; }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+}
+
+/// RSI de Wilder sobre los cierres
+fn rsi(closes: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || closes.len() <= period { return None; }
+    let changes: Vec<f64> = closes.windows(2).map(|w| w[1] - w[0]).collect();
+
+    let mut avg_gain = changes[..period].iter().filter(|c| **c > 0.0).sum::<f64>() / period as f64;
+    let mut avg_loss = -changes[..period].iter().filter(|c| **c < 0.0).sum::<f64>() / period as f64;
+    for change in &changes[period..] {
+        avg_gain = (avg_gain * (period - 1) as f64 + change.max(0.0)) / period as f64;
+        avg_loss = (avg_loss * (period - 1) as f64 + (-change).max(0.0)) / period as f64;
+    }
+
+    if avg_loss == 0.0 { return Some(100.0); }
+    Some(100.0 - 100.0 / (1.0 + avg_gain / avg_loss))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_MS: u64 = 86_400_000;
+
+    fn config() -> MacroConfig {
+        MacroConfig { ma_fast: 3, ma_slow: 5, rsi_period: 3, vol_window: 3, ..MacroConfig::default() }
+    }
+
+    /// Velas diarias consecutivas con esos cierres; la i-ésima cierra al final del día i
+    fn klines(closes: &[f64]) -> Vec<Kline> {
+        closes.iter().enumerate().map(|(i, &close)| {
+            let open_time = i as u64 * DAY_MS;
+            Kline { open_time, open: close, high: close, low: close, close, volume: 1.0, close_time: open_time + DAY_MS - 1 }
+        }).collect()
+    }
+
+    fn rising() -> Vec<f64> {
+        (0..10).map(|i| 100.0 + i as f64).collect()
+    }
+
+    #[test]
+    fn sma_uses_the_last_period_values() {
+        assert_eq!(sma(&[1.0, 2.0, 3.0, 4.0], 2), 3.5);
+        assert_eq!(sma(&[1.0, 2.0, 3.0, 4.0], 10), 2.5);
+    }
+
+    #[test]
+    fn rsi_follows_wilder_smoothing() {
+        // Cambios +1 -1 +1: medias iniciales 0.5 / 0.5, luego 0.75 / 0.25 → RS = 3
+        assert!((rsi(&[1.0, 2.0, 1.0, 2.0], 2).unwrap() - 75.0).abs() < 1e-9);
+        assert_eq!(rsi(&[1.0, 2.0, 3.0, 4.0], 3), Some(100.0));
+        assert_eq!(rsi(&[4.0, 3.0, 2.0, 1.0], 3), Some(0.0));
+        assert_eq!(rsi(&[1.0, 2.0, 3.0], 3), None);
+        assert_eq!(rsi(&[1.0, 2.0], 0), None);
+    }
+
+    #[test]
+    fn trend_flips_from_bull_to_bear() {
+        let c = config();
+        let bull = MacroFilter::from_klines(&c, &klines(&rising()), &klines(&rising())).unwrap();
+        assert!(bull.is_bull_market);
+        assert_eq!(bull.ma_fast, 108.0);
+        assert_eq!(bull.ma_slow, 107.0);
+
+        let mut closes = rising();
+        closes.extend([100.0, 95.0, 90.0]);
+        let bear = MacroFilter::from_klines(&c, &klines(&closes), &klines(&rising())).unwrap();
+        assert!(!bear.is_bull_market);
+        assert!(bear.ma_fast < bear.ma_slow);
+    }
+
+    #[test]
+    fn falling_intraday_closes_are_oversold() {
+        let c = config();
+        let falling: Vec<f64> = rising().into_iter().rev().collect();
+        let oversold = MacroFilter::from_klines(&c, &klines(&rising()), &klines(&falling)).unwrap();
+        assert!(oversold.rsi_oversold);
+        assert_eq!(oversold.rsi, 0.0);
+
+        let overbought = MacroFilter::from_klines(&c, &klines(&rising()), &klines(&rising())).unwrap();
+        assert!(!overbought.rsi_oversold);
+        assert!(MacroFilter::from_klines(&c, &klines(&rising()), &klines(&[1.0, 2.0])).is_err());
+    }
+
+    #[test]
+    fn volatility_regime_compares_recent_and_whole_window() {
+        let c = config();
+        let swings = |calm: usize, wild: usize, wild_first: bool| -> Vec<f64> {
+            let moves: Vec<f64> = if wild_first {
+                (0..wild).map(|i| if i % 2 == 0 { 1.05 } else { 0.95 }).chain((0..calm).map(|i| if i % 2 == 0 { 1.001 } else { 0.999 })).collect()
+            } else {
+                (0..calm).map(|i| if i % 2 == 0 { 1.001 } else { 0.999 }).chain((0..wild).map(|i| if i % 2 == 0 { 1.05 } else { 0.95 })).collect()
+            };
+            moves.iter().scan(100.0, |price, m| { *price *= m; Some(*price) }).collect()
+        };
+        let intraday = klines(&rising());
+
+        let high = MacroFilter::from_klines(&c, &klines(&swings(9, 3, false)), &intraday).unwrap();
+        assert_eq!(high.volatility_regime, VolatilityRegime::High);
+        assert!(high.volatility_pct > 4.0);
+
+        let low = MacroFilter::from_klines(&c, &klines(&swings(3, 9, true)), &intraday).unwrap();
+        assert_eq!(low.volatility_regime, VolatilityRegime::Low);
+
+        let normal = MacroFilter::from_klines(&c, &klines(&swings(0, 12, false)), &intraday).unwrap();
+        assert_eq!(normal.volatility_regime, VolatilityRegime::Normal);
+    }
+
+    #[test]
+    fn csv_as_of_excludes_candles_that_close_later() {
+        let dir = std::env::temp_dir().join(format!("quantos_macro_{}", std::process::id()));
+        let c = MacroConfig { csv_dir: dir.to_string_lossy().to_string(), ..config() };
+        let mut closes = rising();
+        closes.extend([100.0, 95.0, 90.0]);
+        let daily = klines(&closes);
+        save_klines_csv(&c.csv_path("BTCUSDT", &c.daily_interval), &daily).unwrap();
+        save_klines_csv(&c.csv_path("BTCUSDT", &c.intraday_interval), &klines(&rising())).unwrap();
+
+        let latest = MacroFilter::from_csv(&c, "BTCUSDT", None).unwrap();
+        assert_eq!(latest.source, "CSV");
+        assert!(!latest.is_bull_market);
+
+        // Las velas que cierran en el instante del corte o después no cuentan
+        let before_drop = MacroFilter::from_csv(&c, "BTCUSDT", Some(daily[10].close_time)).unwrap();
+        assert!(before_drop.is_bull_market);
+        assert_eq!(before_drop.ma_fast, 108.0);
+        let with_first_drop = MacroFilter::from_csv(&c, "BTCUSDT", Some(daily[10].close_time + 1)).unwrap();
+        assert!((with_first_drop.ma_fast - (108.0 + 109.0 + 100.0) / 3.0).abs() < 1e-9);
+
+        assert!(MacroFilter::from_csv(&c, "BTCUSDT", Some(daily[3].close_time)).is_err());
+    }
+}
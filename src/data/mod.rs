@@ -1,3 +1,8 @@
 pub mod binance_client;
 pub mod data_buffer; 
-pub mod macro_filter; // Esto hace que el archivo macro_filter.rs sea visible
\ No newline at end of file
+pub mod macro_filter; // Esto hace que el archivo macro_filter.rs sea visible
+pub mod resampler;
+pub mod feature_schema;
+pub mod correlation;
+pub mod order_book;
+pub mod depth_book;
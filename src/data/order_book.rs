@@ -0,0 +1,141 @@
+/// Actualización de libro que llega por el stream combinado
+#[derive(Debug, Clone)]
+pub enum BookUpdate {
+    /// `@bookTicker`: mejor bid/ask en tiempo real
+    Ticker { symbol: String, bid: f64, bid_qty: f64, ask: f64, ask_qty: f64 },
+    /// `@depth<N>@100ms`: los N mejores niveles de cada lado, reemplazan a los anteriores
+    Depth { symbol: String, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)> },
+    /// El stream se cortó: lo conocido ya no vale hasta el próximo bookTicker
+    Reset { symbol: String },
+}
+
+impl BookUpdate {
+    pub fn symbol(&self) -> &str {
+        match self {
+            BookUpdate::Ticker { symbol, .. } | BookUpdate::Depth { symbol, .. } | BookUpdate::Reset { symbol } => symbol,
+        }
+    }
+}
+
+/// Top-of-book más los niveles de la profundidad parcial de un símbolo.
+/// `bids` va de mayor a menor precio y `asks` de menor a mayor: (precio, cantidad).
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    pub best_bid: f64,
+    pub bid_qty: f64,
+    pub best_ask: f64,
+    pub ask_qty: f64,
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
+}
+
+impl OrderBook {
+    pub fn apply(&mut self, update: &BookUpdate) {
+        match update {
+            BookUpdate::Ticker { bid, bid_qty, ask, ask_qty, .. } => {
+                self.best_bid = *bid;
+                self.bid_qty = *bid_qty;
+                self.best_ask = *ask;
+                self.ask_qty = *ask_qty;
+            }
+            BookUpdate::Depth { bids, asks, .. } => {
+                self.bids = bids.clone();
+                self.asks = asks.clone();
+                // Sin bookTicker todavía, el top sale de la profundidad
+                if self.best_bid <= 0.0 || self.best_ask <= 0.0 {
+                    if let (Some(&(bid, bid_qty)), Some(&(ask, ask_qty))) = (bids.first(), asks.first()) {
+                        self.best_bid = bid;
+                        self.bid_qty = bid_qty;
+                        self.best_ask = ask;
+                        self.ask_qty = ask_qty;
+                    }
+                }
+            }
+            BookUpdate::Reset { .. } => *self = OrderBook::default(),
+        }
+    }
+
+    /// Hay un bid y un ask válidos y sin cruzar
+    pub fn is_ready(&self) -> bool {
+        self.best_bid > 0.0 && self.best_ask >= self.best_bid
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        self.is_ready().then(|| (self.best_bid + self.best_ask) / 2.0)
+    }
+
+    /// Spread en % del precio medio, en la misma escala que `get_atrp`
+    pub fn spread_pct(&self) -> Option<f64> {
+        self.mid().map(|mid| (self.best_ask - self.best_bid) / mid * 100.0)
+    }
+
+    /// Precio medio ponderado por la cantidad del lado contrario: se acerca al
+    /// ask cuando el bid tiene más cola (presión compradora) y viceversa
+    pub fn microprice(&self) -> Option<f64> {
+        let total = self.bid_qty + self.ask_qty;
+        if !self.is_ready() || total <= 0.0 { return None; }
+        Some((self.best_bid * self.ask_qty + self.best_ask * self.bid_qty) / total)
+    }
+
+    /// (bids - asks) / (bids + asks) de la cantidad en los niveles conocidos, -1..1
+    pub fn depth_imbalance(&self) -> Option<f64> {
+        let bid_depth: f64 = self.bids.iter().map(|(_, q)| q).sum();
+        let ask_depth: f64 = self.asks.iter().map(|(_, q)| q).sum();
+        let total = bid_depth + ask_depth;
+        (total > 0.0).then(|| (bid_depth - ask_depth) / total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticker(bid: f64, bid_qty: f64, ask: f64, ask_qty: f64) -> BookUpdate {
+        BookUpdate::Ticker { symbol: "BTCUSDT".to_string(), bid, bid_qty, ask, ask_qty }
+    }
+
+    fn book(update: &BookUpdate) -> OrderBook {
+        let mut book = OrderBook::default();
+        book.apply(update);
+        book
+    }
+
+    #[test]
+    fn spread_is_a_percentage_of_the_mid() {
+        let b = book(&ticker(99.0, 1.0, 101.0, 1.0));
+        assert_eq!(b.mid(), Some(100.0));
+        assert!((b.spread_pct().unwrap() - 2.0).abs() < 1e-12);
+
+        assert_eq!(OrderBook::default().spread_pct(), None);
+        // Libro cruzado: no es utilizable
+        assert_eq!(book(&ticker(101.0, 1.0, 99.0, 1.0)).spread_pct(), None);
+    }
+
+    #[test]
+    fn microprice_leans_towards_the_thin_side() {
+        assert_eq!(book(&ticker(100.0, 1.0, 102.0, 1.0)).microprice(), Some(101.0));
+        // Más cola en el bid: presión compradora, el precio se acerca al ask
+        assert!((book(&ticker(100.0, 3.0, 102.0, 1.0)).microprice().unwrap() - 101.5).abs() < 1e-12);
+        assert!((book(&ticker(100.0, 1.0, 102.0, 3.0)).microprice().unwrap() - 100.5).abs() < 1e-12);
+        assert_eq!(book(&ticker(100.0, 0.0, 102.0, 0.0)).microprice(), None);
+    }
+
+    #[test]
+    fn depth_imbalance_compares_both_sides() {
+        let depth = BookUpdate::Depth { symbol: "BTCUSDT".to_string(), bids: vec![(100.0, 2.0), (99.0, 4.0)], asks: vec![(101.0, 1.0), (102.0, 1.0)] };
+        let b = book(&depth);
+        assert!((b.depth_imbalance().unwrap() - 0.5).abs() < 1e-12);
+        // Sin bookTicker, el top sale de la profundidad
+        assert_eq!((b.best_bid, b.best_ask), (100.0, 101.0));
+        assert_eq!(OrderBook::default().depth_imbalance(), None);
+    }
+
+    #[test]
+    fn reset_drops_stale_quotes() {
+        let mut b = book(&ticker(99.0, 1.0, 101.0, 1.0));
+        b.apply(&BookUpdate::Depth { symbol: "BTCUSDT".to_string(), bids: vec![(99.0, 1.0)], asks: vec![(101.0, 1.0)] });
+        b.apply(&BookUpdate::Reset { symbol: "BTCUSDT".to_string() });
+        assert!(!b.is_ready());
+        assert_eq!((b.mid(), b.microprice(), b.depth_imbalance()), (None, None, None));
+    }
+}
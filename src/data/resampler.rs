@@ -0,0 +1,155 @@
+/// Vela OHLCV construida a partir de todos los aggTrade de un intervalo
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub open_time: u64, // Inicio del intervalo en ms
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub trades: u64,
+    pub vwap: f64,
+}
+
+impl Candle {
+    fn from_trade(open_time: u64, price: f64, qty: f64) -> Self {
+        Self { open_time, open: price, high: price, low: price, close: price, volume: qty, trades: 1, vwap: price }
+    }
+
+    fn add_trade(&mut self, price: f64, qty: f64) {
+        let quote_volume = self.vwap * self.volume + price * qty;
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += qty;
+        self.trades += 1;
+        self.vwap = if self.volume > 0.0 { quote_volume / self.volume } else { price };
+    }
+}
+
+/// Agrega el stream de aggTrade en velas alineadas a múltiplos del intervalo
+/// (1s, 5s, 1m...). Una vela se cierra cuando llega el primer trade del
+/// siguiente intervalo; los intervalos sin trades no generan vela.
+pub struct CandleResampler {
+    pub interval_ms: u64,
+    current: Option<Candle>,
+}
+
+impl CandleResampler {
+    pub fn new(interval_ms: u64) -> Self {
+        Self { interval_ms: interval_ms.max(1), current: None }
+    }
+
+    /// Añade un trade y devuelve la vela anterior si este trade la cerró
+    pub fn push_trade(&mut self, price: f64, qty: f64, timestamp: u64) -> Option<Candle> {
+        let bucket = timestamp - timestamp % self.interval_ms;
+
+        match self.current.as_mut() {
+            Some(candle) if candle.open_time == bucket => {
+                candle.add_trade(price, qty);
+                None
+            }
+            // Un trade atrasado (reconexión) se suma a la vela en curso
+            Some(candle) if bucket < candle.open_time => {
+                candle.add_trade(price, qty);
+                None
+            }
+            _ => self.current.replace(Candle::from_trade(bucket, price, qty)),
+        }
+    }
+
+    /// Vela todavía abierta (útil para la UI)
+    pub fn current(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+}
+
+/// Convierte "500ms", "1s", "5s", "1m", "1h" en milisegundos
+pub fn parse_interval(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (number, unit_ms) = if let Some(n) = text.strip_suffix("ms") {
+        (n, 1)
+    } else if let Some(n) = text.strip_suffix('s') {
+        (n, 1_000)
+    } else if let Some(n) = text.strip_suffix('m') {
+        (n, 60_000)
+    } else if let Some(n) = text.strip_suffix('h') {
+        (n, 3_600_000)
+    } else {
+        (text, 1)
+    };
+    number.parse::<u64>().ok().filter(|n| *n > 0).map(|n| n * unit_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trades_are_bucketed_on_interval_multiples() {
+        let mut r = CandleResampler::new(1_000);
+        assert!(r.push_trade(100.0, 1.0, 5_250).is_none());
+        assert!(r.push_trade(101.0, 1.0, 5_999).is_none());
+        let candle = r.current().unwrap();
+        assert_eq!(candle.open_time, 5_000);
+        assert_eq!(candle.trades, 2);
+    }
+
+    #[test]
+    fn candle_closes_on_the_first_trade_of_the_next_interval() {
+        let mut r = CandleResampler::new(1_000);
+        r.push_trade(100.0, 1.0, 1_100);
+        r.push_trade(102.0, 2.0, 1_500);
+        r.push_trade(99.0, 1.0, 1_900);
+        let closed = r.push_trade(101.0, 1.0, 2_000).expect("la vela debía cerrarse");
+        assert_eq!(closed, Candle { open_time: 1_000, open: 100.0, high: 102.0, low: 99.0, close: 99.0, volume: 4.0, trades: 3, vwap: 100.75 });
+        assert_eq!(r.current().unwrap().open_time, 2_000);
+    }
+
+    #[test]
+    fn vwap_weights_prices_by_volume() {
+        let mut r = CandleResampler::new(60_000);
+        r.push_trade(100.0, 3.0, 0);
+        r.push_trade(110.0, 1.0, 10_000);
+        r.push_trade(90.0, 0.0, 20_000);
+        let candle = r.current().unwrap();
+        assert_eq!(candle.volume, 4.0);
+        assert!((candle.vwap - 102.5).abs() < 1e-9);
+        assert_eq!((candle.low, candle.close), (90.0, 90.0));
+    }
+
+    #[test]
+    fn empty_intervals_produce_no_candle() {
+        let mut r = CandleResampler::new(1_000);
+        r.push_trade(100.0, 1.0, 1_000);
+        // Sin trades entre 2s y 5s: la siguiente vela empieza en 5s, sin velas vacías entre medias
+        let closed = r.push_trade(105.0, 1.0, 5_400).unwrap();
+        assert_eq!(closed.open_time, 1_000);
+        assert_eq!(r.current().unwrap().open_time, 5_000);
+        assert_eq!(r.push_trade(106.0, 1.0, 6_000).unwrap().open_time, 5_000);
+    }
+
+    #[test]
+    fn late_trades_join_the_open_candle() {
+        let mut r = CandleResampler::new(1_000);
+        r.push_trade(100.0, 1.0, 3_100);
+        // Un trade atrasado tras una reconexión no reabre la vela de 2s ni cierra la actual
+        assert!(r.push_trade(98.0, 1.0, 2_900).is_none());
+        let candle = r.current().unwrap();
+        assert_eq!(candle.open_time, 3_000);
+        assert_eq!((candle.low, candle.close, candle.trades), (98.0, 98.0, 2));
+    }
+
+    #[test]
+    fn parses_interval_units() {
+        assert_eq!(parse_interval("500ms"), Some(500));
+        assert_eq!(parse_interval("1s"), Some(1_000));
+        assert_eq!(parse_interval(" 5s "), Some(5_000));
+        assert_eq!(parse_interval("1m"), Some(60_000));
+        assert_eq!(parse_interval("1h"), Some(3_600_000));
+        assert_eq!(parse_interval("250"), Some(250));
+        assert_eq!(parse_interval("0s"), None);
+        assert_eq!(parse_interval("abc"), None);
+        assert_eq!(parse_interval("1d"), None);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod config;
+pub mod brain;
+pub mod data;
+pub mod trading;
+pub mod backtest;
@@ -1,46 +1,228 @@
-mod constants;
-mod brain;
-mod data;
-mod trading;
-
-use brain::model_loader::QuantosBrain;
-use data::data_buffer::MarketBuffer;
-use data::binance_client::PriceMessage;
-use trading::position_manager::PositionManager;
-use trading::executor::Executor;
+use quantos_core::backtest;
+use quantos_core::config::Config;
+use quantos_core::brain::model_loader::QuantosBrain;
+use quantos_core::data;
+use quantos_core::data::binance_client::{MarketEvent, PriceMessage};
+use quantos_core::data::feature_schema::{FeatureSchema, FEATURE_SCHEMA_VERSION};
+use quantos_core::data::macro_filter::MacroFilter;
+use quantos_core::trading::gateway::ExchangeGateway;
+use quantos_core::trading::gateway::binance::{BinanceGateway, BinanceNetwork};
+use quantos_core::trading::gateway::paper::{split_symbol, PaperGateway};
+use quantos_core::trading::gateway::symbol_filters::fetch_symbol_filters;
+use quantos_core::trading::gateway::user_stream::{self, AccountEvent};
+use quantos_core::trading::engine::{Engine, TradeEvent};
+use quantos_core::trading::exposure::Exposure;
+use quantos_core::trading::portfolio::Portfolio;
+use quantos_core::trading::reconciler::AccountReconciler;
+use quantos_core::trading::recovery;
+use quantos_core::trading::state_store::StateStore;
 use tokio::sync::{mpsc, watch};
-use std::sync::Arc;
 use dotenv::dotenv;
 use std::{env, fs, time::Duration};
 use std::io::{self, Write};
 use std::fs::OpenOptions;
 use std::time::Instant;
-use chrono;
-
-// ... (Tus imports se mantienen igual)
 
+// Uso:
+//   quantos-core [live] [opciones]
+//   quantos-core schema [ruta.schema.json] [--version 1]   (por defecto la más reciente)
+//   quantos-core backtest <archivo> [opciones] [--corr <archivo del correlacionado>] [--out <directorio>]
+//
+// Opciones comunes:
+//   --config <quantos.toml>   --set seccion.clave=valor (repetible)
+//   --exchange testnet|mainnet|paper   --interval 1s   --model <modelo.json>   --scaler <scaler.json>|none
+//   --record <archivo.jsonl>  (solo live)
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("backtest") => run_backtest(&args[1..]).await,
+        Some("schema") => write_schema(&args[1..]),
+        Some("live") => run_live(&args[1..]).await,
+        _ => run_live(&args).await,
+    }
+}
+
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned()
+}
+
+/// Config final: defaults → TOML → entorno → CLI. Los atajos de la CLI son
+/// equivalentes a `--set` sobre su clave.
+fn load_config(args: &[String]) -> Option<Config> {
+    let mut overrides = Vec::new();
+    for (flag, key) in [("--exchange", "exchange.mode"), ("--interval", "market.candle_interval"), ("--model", "model.path")] {
+        if let Some(value) = flag_value(args, flag) {
+            overrides.push((key.to_string(), format!("{:?}", value)));
+        }
+    }
+    if let Some(value) = flag_value(args, "--scaler") {
+        let path = if value == "none" { String::new() } else { value };
+        overrides.push(("model.scaler_path".to_string(), format!("{:?}", path)));
+    }
+    for pair in args.windows(2).filter(|w| w[0] == "--set").map(|w| &w[1]) {
+        match pair.split_once('=') {
+            Some((key, value)) => overrides.push((key.trim().to_string(), value.trim().to_string())),
+            None => { println!("❌ --set espera seccion.clave=valor (recibido '{}')", pair); return None; }
+        }
+    }
+
+    match Config::load(flag_value(args, "--config").as_deref(), &overrides) {
+        Ok(config) => Some(config),
+        Err(e) => { println!("❌ {}", e); None }
+    }
+}
+
+/// Publica el esquema de features de este binario para el pipeline de entrenamiento
+fn write_schema(args: &[String]) {
+    let path = args.first().filter(|a| !a.starts_with("--")).map(String::as_str);
+    let schema = match flag_value(args, "--version").map(|v| v.parse::<u32>().ok().and_then(FeatureSchema::for_version)) {
+        None => FeatureSchema::live(),
+        Some(Some(schema)) => schema,
+        Some(None) => { println!("❌ --version debe ser una versión de esquema soportada (1..{})", FEATURE_SCHEMA_VERSION); return; }
+    };
+    match path {
+        Some(path) => match schema.save(path) {
+            Ok(()) => println!("📐 Esquema v{} ({} features) guardado en {}", schema.version, schema.len(), path),
+            Err(e) => println!("❌ Error guardando esquema: {}", e),
+        },
+        None => println!("{}", serde_json::to_string_pretty(&schema).unwrap_or_default()),
+    }
+}
+
+async fn run_backtest(args: &[String]) {
+    let Some(path) = args.first().filter(|a| !a.starts_with("--")) else {
+        println!("Uso: quantos-core backtest <archivo> [--config <quantos.toml>] [--set clave=valor] [--corr <archivo>] [--out <directorio>]");
+        return;
+    };
+    let Some(config) = load_config(args) else { return };
+    let out_dir = flag_value(args, "--out").unwrap_or_else(|| "logs/backtest".to_string());
+
+    println!("--- 🧪 QuantOS Backtest | {} ---", path);
+    let mut messages = match backtest::replay::load_agg_trades(path) {
+        Ok(m) => m,
+        Err(e) => { println!("❌ Error leyendo {}: {}", path, e); return; }
+    };
+    // Pilar 6: los trades del activo correlacionado pueden venir en un archivo aparte
+    if let Some(corr_path) = flag_value(args, "--corr") {
+        match backtest::replay::load_agg_trades(&corr_path) {
+            Ok(corr) => {
+                messages.extend(corr.into_iter().map(|m| PriceMessage {
+                    symbol: if m.symbol.is_empty() { config.market.corr_asset.clone() } else { m.symbol },
+                    ..m
+                }));
+                messages.sort_by_key(|m| m.timestamp);
+            }
+            Err(e) => { println!("❌ Error leyendo {}: {}", corr_path, e); return; }
+        }
+    }
+    let brains = match QuantosBrain::load_for_symbols(&config.model, &config.market.symbols) {
+        Ok(b) => b,
+        Err(e) => { println!("❌ Error IA: {}", e); return; }
+    };
+
+    println!("⏪ Reproduciendo {} ticks...", messages.len());
+    let report = backtest::runner::run_backtest(&messages, &brains, &config).await;
+    report.print_summary();
+
+    match report.write_csv(&out_dir) {
+        Ok(()) => println!("📁 Trades y equity guardados en {}/", out_dir),
+        Err(e) => println!("❌ Error guardando resultados: {}", e),
+    }
+}
+
+async fn run_live(args: &[String]) {
     dotenv().ok();
+    let Some(config) = load_config(args) else { return };
+
+    if config.exchange.mode == "paper" {
+        // La cuenta simulada no se guarda: se vuelve a fondear con las posiciones persistidas
+        let mut funds = vec![(config.market.quote_asset().to_string(), config.exchange.paper_balance)];
+        if config.persistence.enabled {
+            if let Ok(Some(snapshot)) = StateStore::new(config.persistence.state_path("paper")).load() {
+                funds.extend(snapshot.positions.iter().filter_map(|p| split_symbol(&p.symbol).map(|(base, _)| (base.to_string(), p.qty))));
+            }
+        }
+        let funds: Vec<(&str, f64)> = funds.iter().map(|(asset, qty)| (asset.as_str(), *qty)).collect();
+        let mut gateway = PaperGateway::new(&funds, config.exchange.trading_fee, config.exchange.paper_slippage)
+            .with_maker_fee(config.exchange.maker_fee);
+        // Filtros reales de mainnet para rechazar lo mismo que Binance; sin red se opera sin ellos
+        match fetch_symbol_filters(&BinanceNetwork::Mainnet.config().rest_api_endpoint, &config.market.symbols).await {
+            Ok(filters) => gateway = gateway.with_filters(filters),
+            Err(e) => println!("⚠️ Paper sin filtros de exchangeInfo: {}", e),
+        }
+        return trade_loop(gateway, &config, args, None).await;
+    }
+
+    // `Config::validate` ya garantiza testnet o mainnet aquí
+    let network = BinanceNetwork::from_name(&config.exchange.mode).unwrap_or(BinanceNetwork::Testnet);
+    let api_key = env::var("BINANCE_API_KEY").expect("API_KEY error").trim().to_string();
+    let secret_key = env::var("BINANCE_SECRET_KEY").expect("SECRET_KEY error").trim().to_string();
+    let mut gateway = BinanceGateway::new(api_key.clone(), secret_key, network);
+    if let Err(e) = gateway.load_symbol_filters(&config.market.symbols).await {
+        println!("❌ No se pudieron cargar los filtros de exchangeInfo: {}", e);
+        return;
+    }
+    // Órdenes y saldos en tiempo real, independientes de la respuesta REST
+    let account_rx = config.exchange.user_stream.then(|| {
+        let (account_tx, account_rx) = mpsc::unbounded_channel::<AccountEvent>();
+        tokio::spawn(user_stream::start_user_stream(network, api_key.clone(), account_tx));
+        account_rx
+    });
+    trade_loop(gateway, &config, args, account_rx).await;
+}
+
+async fn trade_loop<G: ExchangeGateway>(gateway: G, config: &Config, args: &[String], mut account_rx: Option<mpsc::UnboundedReceiver<AccountEvent>>) {
     let log_path = "logs/historial_binance.txt";
     let _ = fs::create_dir_all("logs");
 
-    println!("--- 🟢 QuantOS Core Engine v1.6 (ASYNCHRONOUS ARCHITECTURE) ---");
+    println!("--- 🟢 QuantOS Core Engine v1.6 (ASYNCHRONOUS ARCHITECTURE) | {} ---", config.exchange.mode.to_uppercase());
 
     // 1. Inicialización de Componentes
-    let api_key = env::var("BINANCE_API_KEY").expect("API_KEY error").trim().to_string();
-    let secret_key = env::var("BINANCE_SECRET_KEY").expect("SECRET_KEY error").trim().to_string();
-    let executor = Arc::new(Executor::new(api_key, secret_key));
-    let brain = Arc::new(QuantosBrain::new("models/quantos_brain_v1.pkl").expect("Error IA"));
+    let brains = match QuantosBrain::load_for_symbols(&config.model, &config.market.symbols) {
+        Ok(b) => b,
+        Err(e) => { println!("❌ Error IA: {} (exporta el modelo con scripts/export_model.py)", e); return; }
+    };
+    let record_path = flag_value(args, "--record");
 
     // 2. Canales
-    let (price_tx, mut price_rx) = mpsc::unbounded_channel::<PriceMessage>();
+    let (market_tx, mut market_rx) = mpsc::unbounded_channel::<MarketEvent>();
     let (ui_tx, _ui_rx) = watch::channel(0.0); 
     let (stop_tx, mut stop_rx) = mpsc::channel::<()>(1);
+    let (macro_tx, mut macro_rx) = mpsc::unbounded_channel::<(String, MacroFilter)>();
+
+    // 3. Estado persistido: se concilia con la cuenta antes de operar
+    let mut portfolio = Portfolio::new(config);
+    portfolio.set_stream_confirmations(account_rx.is_some());
+    if let Some((maker_fee, taker_fee)) = gateway.fee_rates() {
+        portfolio.set_fee_tier(maker_fee, taker_fee);
+    }
+    let mut store = config.persistence.enabled.then(|| StateStore::new(config.persistence.state_path(&config.exchange.mode)));
+    let saved = match store.as_ref().map(|store| store.load()).transpose() {
+        Ok(snapshot) => snapshot.flatten().map(|s| s.positions).unwrap_or_default(),
+        Err(e) => { println!("❌ Estado ilegible ({}). Revísalo o bórralo antes de arrancar.", e); return; }
+    };
+    // Sin estado guardado también: fija el saldo previo de cada activo que usa la conciliación
+    if let Err(e) = recovery::reconcile(&mut portfolio, &gateway, &saved, config.persistence.qty_tolerance).await {
+        println!("❌ No se pudo conciliar con la cuenta: {}. No se opera sin conocer el estado real.", e);
+        return;
+    }
+    if let Some(store) = &mut store { persist(store, &portfolio); }
 
-    // 3. Sensor y Monitor (Igual que antes)
-    let tx_ws = price_tx.clone();
-    tokio::spawn(async move { data::binance_client::start_market_stream(tx_ws).await; });
+    // 4. Sensor y Monitor
+    let tx_ws = market_tx.clone();
+    let (symbols, book_symbols, depth_levels) = (portfolio.stream_symbols(), config.market.symbols.clone(), config.market.depth_levels);
+    let stream_endpoint = config.exchange.market_stream_endpoint().to_string();
+    tokio::spawn(async move { data::binance_client::start_market_stream(tx_ws, stream_endpoint, symbols, book_symbols, depth_levels, record_path).await; });
+    if config.market.local_book {
+        let books = portfolio.depth_books();
+        let (stream_endpoint, rest_endpoint) = (config.exchange.market_stream_endpoint().to_string(), config.exchange.market_rest_endpoint().to_string());
+        tokio::spawn(async move { data::depth_book::start_depth_sync(books, stream_endpoint, rest_endpoint).await; });
+    }
+    if config.macro_filter.enabled {
+        let (macro_config, symbols) = (config.macro_filter.clone(), config.market.symbols.clone());
+        tokio::spawn(async move { data::macro_filter::run_macro_refresh(macro_config, symbols, macro_tx).await; });
+    }
     let stop_tx_clone = stop_tx.clone();
     tokio::spawn(async move {
         loop {
@@ -53,133 +235,144 @@ async fn main() {
     });
 
     // 5. VARIABLES DE ESTADO (Persistentes)
-    let mut buffer = MarketBuffer::new(14);
-    let mut risk_manager = PositionManager::new(1000.0, 0.01); 
-    let mut is_position_open = false;
-    let mut current_qty = 0.0;
-    let mut entry_price = 0.0;
     let mut last_tick_time = Instant::now();
-    let mut last_candle_time = Instant::now();
-    let candle_interval = Duration::from_secs(1);
-
-    // Variables de visualización
-    let mut current_prob = 0.5;
-    let mut current_conf = 0.0;
+    let mut reconciler = AccountReconciler::new(config);
+    let mut reconcile_timer = tokio::time::interval(Duration::from_secs(config.reconcile.interval_secs));
+    let mut confirm_timer = tokio::time::interval(Duration::from_secs(1));
 
     println!("📡 Patrullando mercado con No-Trade Intelligence activo. Presiona 'Q' para salir.");
 
     loop {
         tokio::select! {
             _ = stop_rx.recv() => {
-                if is_position_open { executor.execute_sell("BTCUSDT", current_qty).await; }
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                for intent in portfolio.close_all() {
+                    if let Some(store) = &mut store { persist(store, &portfolio); }
+                    if let Some(event) = portfolio.execute(&gateway, &intent, now).await {
+                        report_event(log_path, &event).await;
+                    }
+                }
+                if let Some(store) = &mut store { persist(store, &portfolio); }
                 break;
             }
 
-            Some(msg) = price_rx.recv() => {
+            Some(event) = market_rx.recv() => {
+                let msg = match event {
+                    MarketEvent::Trade(msg) => msg,
+                    MarketEvent::Book(update) => { portfolio.on_book(&update); continue; }
+                };
                 last_tick_time = Instant::now();
                 let _ = ui_tx.send(msg.price);
-
-                // --- RESAMPLER: Cada 1 segundo actualizamos cerebro y ATR ---
-                if last_candle_time.elapsed() >= candle_interval {
-                    buffer.add_candle(msg.price, msg.volume);
-                    last_candle_time = Instant::now();
-
-                    if let Some(features) = buffer.get_features() {
-                        if let Ok(prob) = brain.predict_noise(features) {
-                            current_prob = prob;
-                            
-                            // Cálculo de Confianza y ATR
-                            let atrp = buffer.get_atrp();
-                            current_conf = calculate_confidence_score(prob, msg.volume, true, true);
-                            
-                            let max_spread_allowed = atrp * 0.15;
-                            let current_spread_pct = 0.02; // Simulación
-
-                            // LÓGICA DE ENTRADA
-                            if current_conf >= 0.75 && !is_position_open {
-                                if current_spread_pct <= max_spread_allowed {
-                                    let risk_multiplier = if current_conf >= 0.95 { 2.5 } else if current_conf >= 0.90 { 1.8 } else { 1.0 };
-                                    let base_size = risk_manager.calculate_order_size(msg.price, msg.price * 0.99);
-                                    let dynamic_size = base_size * risk_multiplier;
-
-                                    if dynamic_size > 0.0 && executor.execute_buy("BTCUSDT", dynamic_size).await {
-                                        current_qty = dynamic_size;
-                                        entry_price = msg.price;
-                                        is_position_open = true;
-                                        risk_manager.reset_position();
-                                        println!("\n🎯 ENTRADA | Conf: {:.2}% | ATR%: {:.3}%", current_conf * 100.0, atrp);
-                                    }
-                                }
-                            }
-                        }
+                // Paper: órdenes en reposo (entrada maker, stop del exchange) llenadas por este trade
+                for ack in gateway.on_market_trade(&msg.symbol, msg.price) {
+                    if let Some(event) = portfolio.on_order_update(&ack, msg.timestamp) {
+                        report_event(log_path, &event).await;
                     }
                 }
 
-                // LÓGICA DE SALIDA (Se evalúa en cada tick para rapidez)
-                if is_position_open {
-                    let pnl = (msg.price - entry_price) / entry_price * 100.0;
-                    risk_manager.update_highest_price(msg.price);
-                    let trail_stop = risk_manager.calculate_trailing_stop(0.005);
-
-                    if msg.price < trail_stop || pnl < -0.8 || current_prob > 0.75 {
-                        let motivo = if pnl < -0.8 { "STOP LOSS" } else if current_prob > 0.75 { "NOISE" } else { "TRAIL" };
-                        if executor.execute_sell("BTCUSDT", current_qty).await {
-                            log_trade(log_path, motivo, entry_price, msg.price, pnl).await;
-                            is_position_open = false;
-                            println!("\n💰 SALIDA [{}] | PnL: {:.2}%", motivo, pnl);
-                        }
+                if let Some(intent) = portfolio.on_tick(&msg, &brains) {
+                    // La orden pendiente queda en disco antes de enviarla
+                    if let Some(store) = &mut store { persist(store, &portfolio); }
+                    if let Some(event) = portfolio.execute(&gateway, &intent, msg.timestamp).await {
+                        report_event(log_path, &event).await;
                     }
                 }
+                if let Some(store) = &mut store { persist(store, &portfolio); }
 
                 // UI actualizada en cada tick con los últimos valores del segundo
-                refresh_ui(
-                    is_position_open, 
-                    msg.price, 
-                    entry_price, 
-                    current_prob, 
-                    &risk_manager, 
-                    current_conf, 
-                    buffer.get_atrp(), 
-                    buffer.prices.len(), 
-                    buffer.limit
-                );
+                refresh_ui(&portfolio);
+            }
+
+            Some(event) = async { match account_rx.as_mut() { Some(rx) => rx.recv().await, None => std::future::pending().await } } => {
+                if let AccountEvent::Connected = event {
+                    let now = chrono::Utc::now().timestamp_millis() as u64;
+                    for event in portfolio.resync_pending_orders(&gateway, now).await {
+                        report_event(log_path, &event).await;
+                    }
+                }
+                if let Some(event) = portfolio.on_account_event(&event) {
+                    report_event(log_path, &event).await;
+                }
+                if let Some(store) = &mut store { persist(store, &portfolio); }
+            }
+
+            Some((symbol, context)) = macro_rx.recv() => {
+                println!("\n🌍 MACRO {} [{}] | Bull: {} | RSI: {:.1} | Vol: {:.2}% ({:?})",
+                    symbol, context.source, context.is_bull_market, context.rsi, context.volatility_pct, context.volatility_regime);
+                portfolio.set_macro(&symbol, context);
+            }
+
+            _ = reconcile_timer.tick(), if config.reconcile.enabled => {
+                if let Err(e) = reconciler.run(&mut portfolio, &gateway).await {
+                    println!("\n⚠️ Conciliación con la cuenta fallida: {}", e);
+                }
+            }
+
+            _ = confirm_timer.tick(), if account_rx.is_some() => {
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                portfolio.expire_unconfirmed(now, config.exchange.order_confirm_timeout_secs * 1000);
             }
 
             _ = tokio::time::sleep(Duration::from_secs(5)) => {
-                if last_tick_time.elapsed().as_secs() >= 5 && is_position_open {
-                    let _ = executor.get_latest_price("BTCUSDT").await;
+                if last_tick_time.elapsed().as_secs() >= 5 {
+                    for engine in portfolio.engines.iter().filter(|e| e.is_position_open()) {
+                        let _ = gateway.latest_price(&engine.symbol).await;
+                    }
                 }
             }
         }
     }
 }
 
-// ... (Tus funciones auxiliares se mantienen igual)
-
 // --- FUNCIONES AUXILIARES ---
 
-fn calculate_confidence_score(prob_ia: f64, volume: f64, is_bull: bool, rsi_oversold: bool) -> f64 {
-    let mut score = 0.0;
-    if prob_ia < 0.10 { score += 0.55; }
-    else if prob_ia < 0.25 { score += 0.45; }
-    else if prob_ia < 0.35 { score += 0.30; }
-
-    if volume > 2.0 { score += 0.15; }
-    else if volume > 1.0 { score += 0.05; }
-    
-    if is_bull { score += 0.20; }
-    if rsi_oversold { score += 0.10; }
-    score
+fn persist(store: &mut StateStore, portfolio: &Portfolio) {
+    if let Err(e) = store.sync(&portfolio.engines) {
+        println!("\n⚠️ No se pudo guardar el estado en {}: {}", store.path.display(), e);
+    }
+}
+
+async fn report_event(log_path: &str, event: &TradeEvent) {
+    match event {
+        TradeEvent::Entry { symbol, price, qty, fees, confidence, atrp, stop, .. } => {
+            println!("\n🎯 ENTRADA {} | {:.5} @ ${:.2} | Stop: ${:.2} | Comisión: ${:.4} | Conf: {:.2}% | ATR%: {:.3}%", symbol, qty, price, stop, fees, confidence * 100.0, atrp);
+        }
+        TradeEvent::Exit { symbol, reason, entry, exit, qty, pnl_pct, fees, pnl_usd, .. } => {
+            log_trade(log_path, event).await;
+            let net_pct = pnl_usd / (entry * qty) * 100.0;
+            println!("\n💰 SALIDA {} [{}] | {:.5} @ ${:.2} → ${:.2} | PnL bruto: {:.2}% | Neto: {:.2}% ${:.2} (comisiones ${:.4})",
+                symbol, reason, qty, entry, exit, pnl_pct, net_pct, pnl_usd, fees);
+        }
+    }
 }
 
-async fn log_trade(path: &str, motivo: &str, entry: f64, exit: f64, pnl: f64) {
+async fn log_trade(path: &str, event: &TradeEvent) {
+    let TradeEvent::Exit { symbol, order_id, reason, entry, exit, qty, pnl_pct, fees, pnl_usd, .. } = event else { return };
     if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
-        let _ = writeln!(file, "[{}] {} | In: ${:.2} | Out: ${:.2} | PnL: {:.2}%", 
-            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), motivo, entry, exit, pnl);
+        let net_pct = pnl_usd / (entry * qty) * 100.0;
+        let _ = writeln!(file, "[{}] {} {} #{} | Qty: {:.5} | In: ${:.2} | Out: ${:.2} | PnL bruto: {:.2}% | Neto: {:.2}% ${:.2} | Fees: ${:.4}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), symbol, reason, order_id, qty, entry, exit, pnl_pct, net_pct, pnl_usd, fees);
     }
 }
 
-fn refresh_ui(is_open: bool, price: f64, entry: f64, prob: f64, _rm: &PositionManager, conf: f64, atrp: f64, current_candles: usize, limit: usize) {
+/// Una línea con el estado de cada símbolo del portfolio
+fn refresh_ui(portfolio: &Portfolio) {
+    let segments: Vec<String> = portfolio.engines.iter().map(|engine| {
+        let price = portfolio.last_prices.get(&engine.symbol).copied().unwrap_or(0.0);
+        symbol_status(engine, price)
+    }).collect();
+    print!("\r{} ", segments.join(" ‖ "));
+    let _ = io::stdout().flush();
+}
+
+fn symbol_status(engine: &Engine, price: f64) -> String {
+    let is_open = engine.is_position_open();
+    let entry = engine.entry_price();
+    let prob = engine.current_prob;
+    let conf = engine.current_conf;
+    let atrp = engine.buffer.get_atrp();
+    let current_candles = engine.buffer.prices.len();
+    let limit = engine.buffer.limit;
 
     let progreso = if limit > 0 { (current_candles as f64 / limit as f64 * 10.0) as usize } else { 0 };
     let mut bar = String::from("[");
@@ -190,16 +383,20 @@ fn refresh_ui(is_open: bool, price: f64, entry: f64, prob: f64, _rm: &PositionMa
 
     if is_open {
         let pnl = (price - entry) / entry * 100.0;
-        // Agregamos {} para la barra al final
-        print!("\r🛡️ POSICIÓN | PnL: {:.2}% | IA: {:.4} | ATR%: {:.3}% {} ", 
-            pnl, prob, atrp, bar);
+        format!("🛡️ {} | PnL: {:.2}% | IA: {:.4} | ATR%: {:.3}% {}", engine.symbol, pnl, prob, atrp, bar)
     } else {
         // Indicamos si el bot está "CALENTANDO" o "LISTO"
-        let status = if current_candles < limit { "CALENTANDO" } else { "LISTO" };
-        
-        // Corregido: añadidos {} para 'status' y 'bar'
-        print!("\r🔍 {} {} | BTC: ${:.2} | IA: {:.4} | Conf: {:.1}% | ATR%: {:.3}% ", 
-            status, bar, price, prob, conf * 100.0, atrp);
+        let status = if current_candles < limit {
+            "CALENTANDO"
+        } else if engine.exposure.exposure(prob) == Exposure::Blocked {
+            "NO-TRADE"
+        } else {
+            "LISTO"
+        };
+        let spread = engine.book.spread_pct().map(|s| format!(" | Spread: {:.3}%", s)).unwrap_or_default();
+        let ofi = engine.depth.read().ok().and_then(|d| d.order_flow_imbalance()).map(|o| format!(" | OFI: {:+.2}", o)).unwrap_or_default();
+        let edge = engine.last_edge.map(|e| format!(" | Edge: {:+.3}%", e.net_pct)).unwrap_or_default();
+        format!("🔍 {} {} {} | ${:.2} | IA: {:.4} | Conf: {:.1}% | ATR%: {:.3}%{}{}{}",
+            engine.symbol, status, bar, price, prob, conf * 100.0, atrp, spread, ofi, edge)
     }
-    let _ = io::stdout().flush();
-}
\ No newline at end of file
+}
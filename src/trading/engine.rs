@@ -0,0 +1,1122 @@
+use crate::brain::NoiseModel;
+use crate::config::{Config, ExecutionConfig, ProtectionConfig, RiskConfig, StrategyConfig};
+use crate::data::binance_client::PriceMessage;
+use crate::data::correlation::CorrelatedAsset;
+use crate::data::data_buffer::MarketBuffer;
+use crate::data::macro_filter::{MacroFilter, VolatilityRegime};
+use crate::data::depth_book::{LocalOrderBook, SharedBook};
+use crate::data::order_book::OrderBook;
+use crate::data::resampler::CandleResampler;
+use crate::trading::exposure::{Exposure, ExposurePolicy};
+use crate::trading::gateway::paper::split_symbol;
+use crate::trading::gateway::user_stream::ExecutionReport;
+use crate::trading::gateway::{ExchangeGateway, Fill, GatewayResult, OrderAck, OrderSide, OrderStatus};
+use crate::trading::position_manager::PositionManager;
+use crate::trading::viability::{EdgeEstimate, ViabilityCheck};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+
+/// Órdenes ya resueltas por REST que se recuerdan para ignorar su executionReport
+const SETTLED_ORDERS_MEMORY: usize = 32;
+
+/// Lo que el loop de decisión reporta hacia fuera (logs, UI o backtest)
+#[derive(Debug, Clone)]
+pub enum TradeEvent {
+    /// `price` y `qty` son los de la ejecución real; `qty` ya descuenta la comisión cobrada en el activo base.
+    /// `stop` es el stop loss de la posición (`stop_atr_mult` × ATR% bajo `price`)
+    Entry { symbol: String, timestamp: u64, order_id: u64, price: f64, qty: f64, fees: f64, confidence: f64, atrp: f64, stop: f64 },
+    /// `fees` suma la parte de comisión de entrada de esta cantidad y la de salida;
+    /// `pnl_usd` ya la descuenta, `pnl_pct` es solo el movimiento de precio.
+    /// Una posición puede cerrarse en varias salidas parciales: `position_pnl_usd`
+    /// solo viene en la que la deja plana, con el PnL neto de todas ellas.
+    Exit { symbol: String, timestamp: u64, order_id: u64, reason: &'static str, entry: f64, exit: f64, qty: f64, pnl_pct: f64, fees: f64, pnl_usd: f64, position_pnl_usd: Option<f64> },
+}
+
+/// Orden que el motor quiere enviar. Quien la ejecute (Binance, simulador o un test)
+/// debe responder con `Engine::on_order_filled` o `Engine::on_order_rejected`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderIntent {
+    Buy { symbol: String, qty: f64, reference_price: f64 },
+    Sell { symbol: String, qty: f64, reference_price: f64, reason: &'static str },
+    /// Coloca (o sube) el stop del exchange de la posición abierta
+    Protect { symbol: String, qty: f64, stop_price: f64, limit_price: f64 },
+    /// Lleva la LIMIT_MAKER de la entrada al libro actual
+    RepriceEntry { symbol: String },
+    /// Termina la entrada maker: lo que falte a mercado (`market`) o se abandona
+    ExpireEntry { symbol: String, market: bool, reason: &'static str },
+}
+
+impl OrderIntent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            OrderIntent::Buy { symbol, .. }
+            | OrderIntent::Sell { symbol, .. }
+            | OrderIntent::Protect { symbol, .. }
+            | OrderIntent::RepriceEntry { symbol }
+            | OrderIntent::ExpireEntry { symbol, .. } => symbol,
+        }
+    }
+}
+
+/// Máquina de estados de la posición: Flat → PendingEntry → Open → PendingExit → Flat
+#[derive(Debug, Clone, PartialEq)]
+pub enum PositionState {
+    Flat,
+    PendingEntry { qty: f64, price: f64, confidence: f64, atrp: f64 },
+    Open { qty: f64, entry_price: f64 },
+    PendingExit { qty: f64, entry_price: f64, price: f64, reason: &'static str },
+}
+
+/// STOP_LOSS_LIMIT que reposa en el exchange mientras la posición está abierta
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtectiveOrder {
+    pub order_id: u64,
+    pub stop_price: f64,
+    pub limit_price: f64,
+    pub qty: f64,
+}
+
+/// Entrada LIMIT_MAKER en curso (ver `ExecutionConfig`)
+#[derive(Debug, Clone)]
+pub struct MakerEntry {
+    /// Orden en el libro; `None` si el último intento habría cruzado y se reintenta
+    pub order_id: Option<u64>,
+    pub price: f64,
+    /// ms de la última colocación
+    pub placed_at: u64,
+    /// ms de la señal: cuenta para el timeout
+    pub started_at: u64,
+    /// Lo ejecutado por las órdenes anteriores de esta misma entrada
+    pub filled: Option<OrderAck>,
+}
+
+/// Motor de decisión de QuantOS: resampler OHLCV, gate de confianza, trailing stop,
+/// stop loss y salida por ruido. No habla con el exchange: consume ticks y emite
+/// `OrderIntent`, así el bot en vivo, el backtest y los tests lo alimentan igual.
+pub struct Engine {
+    pub symbol: String,
+    pub buffer: MarketBuffer,
+    pub risk_manager: PositionManager,
+    pub state: PositionState,
+    pub current_prob: f64,
+    pub current_conf: f64,
+    pub resampler: CandleResampler,
+    pub params: StrategyConfig,
+    /// Umbrales de ruido de los Pilares 1 y 4 (bloqueo y tamaño reducido)
+    pub exposure: ExposurePolicy,
+    /// Pilar 12: rendimiento esperado neto de costes que exige cada entrada
+    pub viability: ViabilityCheck,
+    /// Estimación de la última vela cerrada
+    pub last_edge: Option<EdgeEstimate>,
+    /// Stop loss y trailing en múltiplos del ATR (también dimensionan la entrada)
+    pub risk: RiskConfig,
+    /// Régimen macro del símbolo; se actualiza desde fuera con cada refresco
+    pub macro_ctx: MacroFilter,
+    /// Libro en vivo (bookTicker + profundidad parcial); vacío en un backtest de aggTrades
+    pub book: OrderBook,
+    /// Libro L2 completo, sincronizado por `start_depth_sync` (nunca en un backtest)
+    pub depth: SharedBook,
+    /// Con motivo, el motor no abre posiciones nuevas (las salidas siguen activas)
+    pub halt_reason: Option<String>,
+    /// Comisión de entrada de la posición viva, en moneda de cotización
+    pub entry_fees: f64,
+    /// PnL neto de las salidas parciales ya ejecutadas de la posición viva
+    pub realized_pnl: f64,
+    /// Saldo del activo base que ya estaba en la cuenta y no es del bot (lo fija `trading::recovery`)
+    pub account_baseline: f64,
+    /// Hay user data stream: una orden sin respuesta REST se confirma por executionReport
+    pub confirm_by_stream: bool,
+    /// ms desde que la orden pendiente espera su executionReport
+    pub unconfirmed_since: Option<u64>,
+    pub protection: ProtectionConfig,
+    /// Stop del exchange que cubre la posición si el bot se cae
+    pub protective_order: Option<ProtectiveOrder>,
+    /// Tras un rechazo, no se vuelve a intentar colocar el stop hasta este timestamp (ms)
+    protective_retry_at: Option<u64>,
+    pub execution: ExecutionConfig,
+    pub maker_entry: Option<MakerEntry>,
+    settled_orders: VecDeque<u64>,
+    stream_fills: Vec<Fill>,
+}
+
+impl Engine {
+    pub fn new(symbol: &str, config: &Config) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            buffer: MarketBuffer::new(config.market.buffer_limit),
+            risk_manager: PositionManager::new(config.risk.balance_usd, config.risk.risk_per_trade, &config.sizing),
+            state: PositionState::Flat,
+            current_prob: 0.5,
+            current_conf: 0.0,
+            resampler: CandleResampler::new(config.market.candle_interval_ms()),
+            params: config.strategy.clone(),
+            exposure: ExposurePolicy::new(&config.strategy),
+            viability: ViabilityCheck::new(config),
+            last_edge: None,
+            risk: config.risk.clone(),
+            macro_ctx: MacroFilter::assumed(&config.macro_filter),
+            book: OrderBook::default(),
+            depth: Arc::new(RwLock::new(LocalOrderBook::new(symbol, config.market.ofi_window_ms))),
+            halt_reason: None,
+            entry_fees: 0.0,
+            realized_pnl: 0.0,
+            account_baseline: 0.0,
+            confirm_by_stream: false,
+            unconfirmed_since: None,
+            protection: config.protection.clone(),
+            protective_order: None,
+            protective_retry_at: None,
+            execution: config.execution.clone(),
+            maker_entry: None,
+            settled_orders: VecDeque::with_capacity(SETTLED_ORDERS_MEMORY),
+            stream_fills: Vec::new(),
+        }
+    }
+
+    /// Retoma una posición abierta antes de un reinicio (ver `trading::recovery`).
+    /// Sin stops guardados (estado de una versión anterior) se calculan con el ATR actual.
+    pub fn restore_position(&mut self, qty: f64, entry_price: f64, highest_price: f64, entry_fees: f64, stop_price: f64, trail_distance: f64) {
+        self.state = PositionState::Open { qty, entry_price };
+        let atrp = self.buffer.get_atrp();
+        self.risk_manager.open_position(entry_price, self.risk.stop_distance(atrp), self.risk.trail_distance(atrp));
+        if stop_price > 0.0 {
+            self.risk_manager.stop_price = stop_price;
+        }
+        if trail_distance > 0.0 {
+            self.risk_manager.trail_distance = trail_distance;
+        }
+        self.risk_manager.highest_price = highest_price;
+        self.entry_fees = entry_fees;
+    }
+
+    pub fn is_position_open(&self) -> bool {
+        !matches!(self.state, PositionState::Flat)
+    }
+
+    /// Precio de entrada de la posición viva (0.0 si estamos planos)
+    pub fn entry_price(&self) -> f64 {
+        match self.state {
+            PositionState::Open { entry_price, .. } | PositionState::PendingExit { entry_price, .. } => entry_price,
+            PositionState::PendingEntry { price, .. } => price,
+            PositionState::Flat => 0.0,
+        }
+    }
+
+    /// Riesgo en USD comprometido por la posición (distancia al stop loss × cantidad)
+    pub fn open_risk_usd(&self) -> f64 {
+        match self.state {
+            PositionState::Flat => 0.0,
+            PositionState::PendingEntry { qty, price, atrp, .. } => qty * price * self.risk.stop_distance(atrp),
+            PositionState::Open { qty, entry_price } | PositionState::PendingExit { qty, entry_price, .. } => {
+                qty * (entry_price - self.risk_manager.stop_price).max(0.0)
+            }
+        }
+    }
+
+    /// Nocional en USD de la posición (o de la entrada pendiente) a precio de entrada
+    pub fn open_notional_usd(&self) -> f64 {
+        match self.state {
+            PositionState::Flat => 0.0,
+            PositionState::PendingEntry { qty, price, .. } => qty * price,
+            PositionState::Open { qty, entry_price } | PositionState::PendingExit { qty, entry_price, .. } => qty * entry_price,
+        }
+    }
+
+    /// Ajusta la cantidad de una entrada pendiente (presupuesto de riesgo compartido)
+    pub fn resize_pending_entry(&mut self, new_qty: f64) -> Option<OrderIntent> {
+        match &mut self.state {
+            PositionState::PendingEntry { qty, price, .. } => {
+                *qty = new_qty;
+                Some(OrderIntent::Buy { symbol: self.symbol.clone(), qty: new_qty, reference_price: *price })
+            }
+            _ => None,
+        }
+    }
+
+    /// Procesa un tick del aggTrade. El reloj es el timestamp del propio trade,
+    /// así una repetición offline toma exactamente las mismas decisiones.
+    /// `corr` aporta el precio del activo correlacionado alineado a cada vela.
+    pub fn on_tick<M: NoiseModel>(&mut self, msg: &PriceMessage, brain: &M, corr: &CorrelatedAsset) -> Option<OrderIntent> {
+        let mut intent = None;
+
+        // --- RESAMPLER: Cada vela cerrada actualiza cerebro y ATR ---
+        if let Some(candle) = self.resampler.push_trade(msg.price, msg.volume, msg.timestamp) {
+            self.buffer.add_candle(&candle, corr.close_at(candle.open_time));
+
+            if let Some(features) = self.buffer.get_features(brain.uses_cross_asset()) {
+                if let Ok(prob) = brain.predict_noise(features) {
+                    self.current_prob = prob;
+
+                    // Cálculo de Confianza y ATR
+                    let atrp = self.buffer.get_atrp();
+                    let cross_bonus = self.params.cross_asset_bonus(self.buffer.get_cross_asset().as_ref());
+                    let exposure = self.exposure.exposure(prob);
+                    self.current_conf = calculate_confidence_score(self.exposure.ia_score(prob), candle.volume, &self.macro_ctx, cross_bonus);
+
+                    let max_spread_allowed = atrp * self.params.max_spread_atr_factor;
+                    let current_spread_pct = self.book.spread_pct().unwrap_or(self.params.assumed_spread_pct);
+                    let maker_entry = self.execution.is_maker() && self.book.is_ready();
+                    let edge = self.viability.estimate(prob, self.risk.stop_distance(atrp) * 100.0, current_spread_pct, maker_entry);
+                    self.last_edge = Some(edge);
+
+                    // LÓGICA DE ENTRADA
+                    if exposure != Exposure::Blocked && self.current_conf >= self.params.entry_confidence && self.state == PositionState::Flat && self.halt_reason.is_none()
+                        && current_spread_pct <= max_spread_allowed && self.viability.is_viable(&edge) {
+                        // Pilar 4: en la zona de incertidumbre el tamaño se reduce
+                        let risk_multiplier = self.params.risk_multiplier(self.current_conf) * exposure.size_factor();
+                        let stop_price = msg.price * (1.0 - self.risk.stop_distance(atrp));
+                        // La señal del modelo ya entra por la confianza del multiplicador
+                        let dynamic_size = self.risk_manager.calculate_order_size(msg.price, stop_price, atrp, risk_multiplier);
+
+                        if dynamic_size > 0.0 && self.slippage_ok(dynamic_size) {
+                            self.state = PositionState::PendingEntry { qty: dynamic_size, price: msg.price, confidence: self.current_conf, atrp };
+                            intent = Some(OrderIntent::Buy { symbol: self.symbol.clone(), qty: dynamic_size, reference_price: msg.price });
+                        }
+                    }
+                }
+            }
+        }
+
+        // ENTRADA MAKER EN CURSO: re-precio, timeout o precio que se escapa
+        if let (None, PositionState::PendingEntry { price, .. }) = (&intent, &self.state) {
+            intent = self.maker_intent(msg.timestamp, msg.price, *price);
+        }
+
+        // LÓGICA DE SALIDA (Se evalúa en cada tick para rapidez)
+        if let PositionState::Open { qty, entry_price } = self.state {
+            self.risk_manager.update_highest_price(msg.price);
+            let trail_stop = self.risk_manager.calculate_trailing_stop();
+            let stop_loss = self.risk_manager.stop_price;
+
+            if msg.price < trail_stop || msg.price < stop_loss || self.current_prob > self.params.exit_noise {
+                let motivo = if msg.price < stop_loss { "STOP LOSS" } else if self.current_prob > self.params.exit_noise { "NOISE" } else { "TRAIL" };
+                intent = self.begin_exit(qty, entry_price, msg.price, motivo);
+            } else {
+                intent = self.protection_intent(msg.timestamp, qty);
+            }
+        }
+
+        intent
+    }
+
+    /// Qué hacer con la LIMIT_MAKER de la entrada: se abandona (o pasa a mercado)
+    /// al agotar `maker_timeout_secs` o si el precio sube `max_chase_pct` sobre la
+    /// señal; se re-precia si el bid la ha superado, como mucho cada `reprice_interval_ms`.
+    fn maker_intent(&self, now: u64, last_price: f64, signal_price: f64) -> Option<OrderIntent> {
+        let entry = self.maker_entry.as_ref()?;
+        let x = &self.execution;
+        let market = x.fallback == "market";
+        if now.saturating_sub(entry.started_at) >= x.maker_timeout_secs * 1000 {
+            return Some(OrderIntent::ExpireEntry { symbol: self.symbol.clone(), market, reason: "TIMEOUT" });
+        }
+        if last_price >= signal_price * (1.0 + x.max_chase_pct / 100.0) {
+            return Some(OrderIntent::ExpireEntry { symbol: self.symbol.clone(), market, reason: "PRECIO ESCAPADO" });
+        }
+        let outbid = entry.order_id.is_none() || self.book.best_bid > entry.price;
+        (outbid && now.saturating_sub(entry.placed_at) >= x.reprice_interval_ms).then(|| OrderIntent::RepriceEntry { symbol: self.symbol.clone() })
+    }
+
+    /// Stop del exchange para la posición abierta: el más alto entre stop loss y
+    /// trailing, `stop_buffer_pct` por debajo para que con el bot vivo salga él antes.
+    /// Solo sube, y a saltos de `amend_step_pct` para no reemplazar la orden en cada tick.
+    fn protection_intent(&self, now: u64, qty: f64) -> Option<OrderIntent> {
+        let p = &self.protection;
+        if !p.enabled || self.protective_retry_at.is_some_and(|at| now < at) {
+            return None;
+        }
+        let stop_price = self.risk_manager.stop_level() * (1.0 - p.stop_buffer_pct / 100.0);
+        if self.protective_order.as_ref().is_some_and(|o| stop_price < o.stop_price * (1.0 + p.amend_step_pct / 100.0)) {
+            return None;
+        }
+        let limit_price = stop_price * (1.0 - p.limit_offset_pct / 100.0);
+        Some(OrderIntent::Protect { symbol: self.symbol.clone(), qty, stop_price, limit_price })
+    }
+
+    /// Con el libro L2 sincronizado, la entrada debe poder llenarse sin pasar de
+    /// `max_slippage_pct`. Sin libro (backtest o resincronizando) no se bloquea;
+    /// un libro sincronizado sin cantidad suficiente sí.
+    fn slippage_ok(&self, qty: f64) -> bool {
+        let Ok(depth) = self.depth.read() else { return true };
+        if !depth.synced {
+            return true;
+        }
+        depth.estimate_slippage(OrderSide::Buy, qty).is_some_and(|estimate| estimate.slippage_pct <= self.params.max_slippage_pct)
+    }
+
+    /// Cierre forzado (Kill-Switch o fin de la repetición) al precio indicado.
+    /// Una entrada maker a medias se retira del libro.
+    pub fn request_close(&mut self, price: f64) -> Option<OrderIntent> {
+        match self.state {
+            PositionState::Open { qty, entry_price } => self.begin_exit(qty, entry_price, price, "MANUAL"),
+            PositionState::PendingEntry { .. } if self.maker_entry.is_some() => {
+                Some(OrderIntent::ExpireEntry { symbol: self.symbol.clone(), market: false, reason: "MANUAL" })
+            }
+            _ => None,
+        }
+    }
+
+    fn begin_exit(&mut self, qty: f64, entry_price: f64, price: f64, reason: &'static str) -> Option<OrderIntent> {
+        self.state = PositionState::PendingExit { qty, entry_price, price, reason };
+        Some(OrderIntent::Sell { symbol: self.symbol.clone(), qty, reference_price: price, reason })
+    }
+
+    /// Confirmación de la orden pendiente con la ejecución real del exchange.
+    /// Sin cantidad o precio medio en la respuesta se usan los de la orden.
+    /// Las comisiones en otros activos (BNB) no se valoran aquí.
+    pub fn on_order_filled(&mut self, timestamp: u64, ack: &OrderAck) -> Option<TradeEvent> {
+        let (base, quote) = split_symbol(&self.symbol).unwrap_or((&self.symbol, ""));
+        let (base_fee, quote_fee) = (ack.commission_in(base), ack.commission_in(quote));
+
+        match self.state.clone() {
+            PositionState::PendingEntry { qty, price, confidence, atrp } => {
+                let fill_price = if ack.avg_price > 0.0 { ack.avg_price } else { price };
+                let executed = if ack.executed_qty > 0.0 { ack.executed_qty } else { qty };
+                // La comisión cobrada en el activo base nunca llega a la cartera
+                let held = executed - base_fee;
+                self.entry_fees = quote_fee + base_fee * fill_price;
+                self.state = PositionState::Open { qty: held, entry_price: fill_price };
+                self.maker_entry = None;
+                // Stops anclados al precio real de entrada con el ATR que dimensionó la orden
+                self.risk_manager.open_position(fill_price, self.risk.stop_distance(atrp), self.risk.trail_distance(atrp));
+                Some(TradeEvent::Entry { symbol: self.symbol.clone(), timestamp, order_id: ack.order_id, price: fill_price, qty: held, fees: self.entry_fees, confidence, atrp, stop: self.risk_manager.stop_price })
+            }
+            PositionState::PendingExit { qty, entry_price, price, reason } => {
+                let fill_price = if ack.avg_price > 0.0 { ack.avg_price } else { price };
+                let executed = if ack.executed_qty > 0.0 { ack.executed_qty.min(qty) } else { qty };
+                let entry_fees = self.entry_fees * executed / qty;
+                let fees = entry_fees + quote_fee + base_fee * fill_price;
+
+                // Una venta parcial deja abierto el resto, que se vuelve a vender en el siguiente tick
+                let pnl_pct = (fill_price - entry_price) / entry_price * 100.0;
+                let pnl_usd = (fill_price - entry_price) * executed - fees;
+                self.realized_pnl += pnl_usd;
+
+                let remaining = qty - executed;
+                let position_pnl_usd = if remaining > qty * 1e-6 {
+                    self.state = PositionState::Open { qty: remaining, entry_price };
+                    self.entry_fees -= entry_fees;
+                    None
+                } else {
+                    self.state = PositionState::Flat;
+                    self.entry_fees = 0.0;
+                    self.risk_manager.reset_position();
+                    // El payoff del Kelly cuenta operaciones, no ejecuciones sueltas
+                    let total = std::mem::take(&mut self.realized_pnl);
+                    self.risk_manager.record_result(total);
+                    Some(total)
+                };
+                Some(TradeEvent::Exit { symbol: self.symbol.clone(), timestamp, order_id: ack.order_id, reason, entry: entry_price, exit: fill_price, qty: executed, pnl_pct, fees, pnl_usd, position_pnl_usd })
+            }
+            _ => None,
+        }
+    }
+
+    /// Cantidad de la orden pendiente tras ajustarla a los filtros del exchange.
+    /// En una salida, el resto que no llega a un paso queda como polvo en la cuenta.
+    pub fn set_pending_qty(&mut self, new_qty: f64) {
+        if let PositionState::PendingEntry { qty, .. } | PositionState::PendingExit { qty, .. } = &mut self.state {
+            *qty = new_qty;
+        }
+    }
+
+    /// Abandona una posición que no se puede vender (por debajo de los mínimos del exchange)
+    pub fn abandon_position(&mut self) {
+        self.state = PositionState::Flat;
+        self.entry_fees = 0.0;
+        self.realized_pnl = 0.0;
+        self.risk_manager.reset_position();
+    }
+
+    /// Orden en reposo llenada por el tape (paper): la entrada maker o el stop del exchange
+    pub fn on_resting_fill(&mut self, timestamp: u64, ack: &OrderAck) -> Option<TradeEvent> {
+        if self.maker_entry.as_ref().is_some_and(|e| e.order_id == Some(ack.order_id)) {
+            self.mark_settled(ack.order_id);
+            println!("\n✅ LIMIT_MAKER {} LLENADA | {:.5} @ ${:.2}", ack.order_id, ack.executed_qty, ack.avg_price);
+            return self.finish_maker_entry(timestamp, Some(ack.clone()));
+        }
+        self.on_protective_fill(timestamp, ack)
+    }
+
+    /// Guarda lo ejecutado por una LIMIT_MAKER ya retirada de la entrada en curso
+    fn add_maker_fill(&mut self, ack: OrderAck) {
+        if let Some(entry) = self.maker_entry.as_mut().filter(|_| ack.executed_qty > 0.0) {
+            entry.filled = Some(match entry.filled.take() {
+                Some(filled) => filled.merge(&ack),
+                None => ack,
+            });
+        }
+    }
+
+    /// Cierra la entrada maker: lo ejecutado por todas sus órdenes (más `last`) abre
+    /// la posición; sin nada ejecutado, la entrada se descarta.
+    fn finish_maker_entry(&mut self, timestamp: u64, last: Option<OrderAck>) -> Option<TradeEvent> {
+        if let Some(ack) = last {
+            self.add_maker_fill(ack);
+        }
+        match self.maker_entry.take().and_then(|e| e.filled) {
+            Some(ack) => self.on_order_filled(timestamp, &ack),
+            None => {
+                self.on_order_rejected();
+                None
+            }
+        }
+    }
+
+    /// El stop del exchange se ejecutó (entero o en parte): esa cantidad sale como
+    /// cualquier otra salida. Llega por executionReport, por el tape en paper o al
+    /// descubrirlo cuando se iba a cancelar. Otras órdenes se ignoran.
+    pub fn on_protective_fill(&mut self, timestamp: u64, ack: &OrderAck) -> Option<TradeEvent> {
+        if self.protective_order.as_ref().is_none_or(|o| o.order_id != ack.order_id) {
+            return None;
+        }
+        let (qty, entry_price) = match self.state {
+            PositionState::Open { qty, entry_price } | PositionState::PendingExit { qty, entry_price, .. } => (qty, entry_price),
+            _ => return None,
+        };
+        self.protective_order = None;
+        self.mark_settled(ack.order_id);
+        println!("\n🛑 {} | STOP DEL EXCHANGE EJECUTADO ({:?}) | {:.5} @ ${:.2}", self.symbol, ack.status, ack.executed_qty, ack.avg_price);
+        self.state = PositionState::PendingExit { qty, entry_price, price: ack.avg_price, reason: "STOP EXCHANGE" };
+        self.on_order_filled(timestamp, ack)
+    }
+
+    fn mark_settled(&mut self, order_id: u64) {
+        if self.settled_orders.len() >= SETTLED_ORDERS_MEMORY {
+            self.settled_orders.pop_front();
+        }
+        self.settled_orders.push_back(order_id);
+        self.unconfirmed_since = None;
+        self.stream_fills.clear();
+    }
+
+    /// Evento del user data stream para este símbolo. Las órdenes ya resueltas por
+    /// REST se ignoran; la pendiente (solo hay una por motor) se resuelve aquí con
+    /// sus ejecuciones acumuladas cuando llega a un estado final.
+    pub fn on_execution(&mut self, report: &ExecutionReport) -> Option<TradeEvent> {
+        if self.settled_orders.contains(&report.order_id) {
+            return None;
+        }
+        if self.protective_order.as_ref().is_some_and(|o| o.order_id == report.order_id) {
+            return self.on_protective_report(report);
+        }
+        let pending_side = match self.state {
+            PositionState::PendingEntry { .. } => Some(OrderSide::Buy),
+            PositionState::PendingExit { .. } => Some(OrderSide::Sell),
+            _ => None,
+        };
+        if pending_side != Some(report.side) {
+            println!("\nℹ️ {} | Orden {} {:?} ajena al bot: {} ({:?})", self.symbol, report.order_id, report.side, report.execution_type, report.status);
+            return None;
+        }
+
+        if let Some(fill) = &report.fill {
+            self.stream_fills.push(fill.clone());
+        }
+        if !report.is_final() {
+            if report.status == OrderStatus::PartiallyFilled {
+                println!("\n🧩 {} | Orden {} parcial: {:.5} ejecutado", self.symbol, report.order_id, report.cum_qty);
+            }
+            return None;
+        }
+
+        let fills = std::mem::take(&mut self.stream_fills);
+        self.mark_settled(report.order_id);
+        if self.maker_entry.is_some() {
+            println!("\n✅ LIMIT_MAKER {} CERRADA POR STREAM ({:?}) | {:.5} ejecutado", report.order_id, report.status, report.cum_qty);
+            return self.finish_maker_entry(report.event_time, Some(report.to_ack(fills)));
+        }
+        if report.cum_qty <= 0.0 {
+            println!("\n⚠️ {} | Orden {} {:?} sin ejecución (executionReport)", self.symbol, report.order_id, report.status);
+            self.on_order_rejected();
+            return None;
+        }
+        println!("\n✅ ORDEN {} CONFIRMADA POR STREAM ({:?}) | {:?} {:.5}", report.order_id, report.status, report.side, report.cum_qty);
+        self.on_order_filled(report.event_time, &report.to_ack(fills))
+    }
+
+    /// executionReport del stop del exchange. Sus ejecuciones parciales se acumulan
+    /// (con la posición abierta no hay otra orden pendiente) hasta el estado final.
+    fn on_protective_report(&mut self, report: &ExecutionReport) -> Option<TradeEvent> {
+        if let Some(fill) = &report.fill {
+            self.stream_fills.push(fill.clone());
+        }
+        if !report.is_final() {
+            if report.status == OrderStatus::PartiallyFilled {
+                println!("\n🧩 {} | Stop del exchange {} parcial: {:.5} ejecutado", self.symbol, report.order_id, report.cum_qty);
+            }
+            return None;
+        }
+        let fills = std::mem::take(&mut self.stream_fills);
+        if report.cum_qty <= 0.0 {
+            println!("\n⚠️ {} | Stop del exchange {} cerrado sin ejecución ({:?}): se vuelve a colocar", self.symbol, report.order_id, report.status);
+            self.protective_order = None;
+            self.mark_settled(report.order_id);
+            return None;
+        }
+        self.on_protective_fill(report.event_time, &report.to_ack(fills))
+    }
+
+    /// La orden pendiente no se ejecutó: volvemos al estado anterior.
+    /// Una salida rechazada se reintenta en el siguiente tick.
+    pub fn on_order_rejected(&mut self) {
+        self.maker_entry = None;
+        self.state = match self.state {
+            PositionState::PendingEntry { .. } => PositionState::Flat,
+            PositionState::PendingExit { qty, entry_price, .. } => PositionState::Open { qty, entry_price },
+            ref other => other.clone(),
+        };
+    }
+}
+
+/// Envía un `OrderIntent` al exchange y devuelve al motor el resultado.
+/// Cualquier cantidad ejecutada cuenta, aunque la orden no se complete.
+/// Antes de enviarla, la cantidad se ajusta a los filtros del símbolo; si no los
+/// pasa se rechaza aquí con el motivo, sin llegar a la API.
+pub async fn execute_intent<G: ExchangeGateway>(engine: &mut Engine, gateway: &G, intent: &OrderIntent, timestamp: u64) -> Option<TradeEvent> {
+    let (symbol, side, mut qty, reference_price) = match intent {
+        OrderIntent::Buy { symbol, qty, reference_price } => (symbol, OrderSide::Buy, *qty, *reference_price),
+        OrderIntent::Sell { symbol, qty, reference_price, .. } => (symbol, OrderSide::Sell, *qty, *reference_price),
+        OrderIntent::Protect { qty, stop_price, limit_price, .. } => return place_protection(engine, gateway, *qty, *stop_price, *limit_price, timestamp).await,
+        OrderIntent::RepriceEntry { .. } => return reprice_maker_entry(engine, gateway, timestamp).await,
+        OrderIntent::ExpireEntry { market, reason, .. } => return expire_maker_entry(engine, gateway, *market, reason, timestamp).await,
+    };
+
+    // El stop del exchange bloquea el saldo que se va a vender: fuera antes de la salida
+    if side == OrderSide::Sell {
+        match cancel_protection(engine, gateway, timestamp).await {
+            Ok(None) => {}
+            // Ya se había ejecutado: esa es la salida (lo que quede se vende en el siguiente tick)
+            Ok(Some(event)) => return Some(event),
+            Err(e) => {
+                println!("\n⚠️ {} | No se pudo cancelar el stop del exchange ({}): la salida se reintenta", symbol, e);
+                engine.on_order_rejected();
+                return None;
+            }
+        }
+    }
+
+    if let Some(filters) = gateway.symbol_filters(symbol) {
+        match filters.validate_order(qty, reference_price, true) {
+            Ok(normalized) => {
+                qty = normalized;
+                engine.set_pending_qty(qty);
+            }
+            Err(reason) if side == OrderSide::Sell => {
+                println!("\n⚠️ POSICIÓN RESIDUAL ABANDONADA: {}", reason);
+                engine.abandon_position();
+                return None;
+            }
+            Err(reason) => {
+                println!("\n⛔ ORDEN RECHAZADA LOCALMENTE: {}", reason);
+                engine.on_order_rejected();
+                return None;
+            }
+        }
+    }
+
+    // Entrada maker: sin libro (backtest sobre aggTrades) no hay bid al que postear y se entra a mercado
+    if side == OrderSide::Buy && engine.execution.is_maker() && engine.book.is_ready() {
+        engine.maker_entry = Some(MakerEntry { order_id: None, price: 0.0, placed_at: timestamp, started_at: timestamp, filled: None });
+        return place_maker_order(engine, gateway, timestamp).await;
+    }
+
+    let result = gateway.market_order(symbol, side, qty).await;
+    if let Ok(ack) = &result {
+        engine.mark_settled(ack.order_id);
+    }
+
+    match result {
+        Ok(ack) if ack.status == OrderStatus::Filled || ack.executed_qty > 0.0 => {
+            let commissions: Vec<String> = ack.commissions().iter().map(|(asset, amount)| format!("{:.8} {}", amount, asset)).collect();
+            println!("\n✅ ORDEN {} EJECUTADA ({:?}) | {:?} {:.5} @ ${:.2} = ${:.2} | Comisión: {} | {} fills",
+                ack.order_id, ack.status, ack.side, ack.executed_qty, ack.avg_price, ack.cum_quote,
+                if commissions.is_empty() { "-".to_string() } else { commissions.join(" + ") }, ack.fills.len());
+            engine.on_order_filled(timestamp, &ack)
+        }
+        Ok(ack) => {
+            println!("\n⚠️ ORDEN {} NO EJECUTADA | Estado: {:?}", ack.order_id, ack.status);
+            engine.on_order_rejected();
+            None
+        }
+        // Un error de red no garantiza que la orden no llegara: el stream lo dirá
+        Err(e) if engine.confirm_by_stream => {
+            println!("\n⏳ ORDEN SIN RESPUESTA ({}): se espera su executionReport", e);
+            engine.unconfirmed_since = Some(timestamp);
+            None
+        }
+        Err(e) => {
+            println!("\n❌ ERROR ORDEN: {}", e);
+            engine.on_order_rejected();
+            None
+        }
+    }
+}
+
+/// Postea la LIMIT_MAKER por lo que falta de la entrada, en el mejor bid más
+/// `maker_inside_ticks` sin llegar al ask. Si el exchange la rechaza (cruzaría el
+/// libro) se reintenta en el siguiente re-precio.
+async fn place_maker_order<G: ExchangeGateway>(engine: &mut Engine, gateway: &G, timestamp: u64) -> Option<TradeEvent> {
+    let PositionState::PendingEntry { qty, .. } = engine.state else { return None };
+    let filled = engine.maker_entry.as_ref()?.filled.as_ref().map_or(0.0, |f| f.executed_qty);
+    let filters = gateway.symbol_filters(&engine.symbol);
+    if !engine.book.is_ready() {
+        engine.maker_entry.as_mut()?.placed_at = timestamp;
+        return None;
+    }
+
+    let tick = filters.as_ref().map_or(0.0, |f| f.tick_size);
+    let inside = engine.book.best_bid + tick * engine.execution.maker_inside_ticks as f64;
+    let mut price = if inside < engine.book.best_ask { inside } else { engine.book.best_bid };
+    let mut remaining = qty - filled;
+    if let Some(filters) = &filters {
+        price = filters.normalize_price(price);
+        match filters.validate_order(remaining, price, false) {
+            Ok(normalized) => remaining = normalized,
+            Err(reason) => {
+                println!("\n⚠️ {} | Resto de la entrada maker no colocable ({}): se entra con lo ejecutado", engine.symbol, reason);
+                return engine.finish_maker_entry(timestamp, None);
+            }
+        }
+    }
+
+    let result = gateway.limit_maker(&engine.symbol, OrderSide::Buy, remaining, price).await;
+    let entry = engine.maker_entry.as_mut()?;
+    entry.placed_at = timestamp;
+    match result {
+        Ok(ack) => {
+            println!("\n📌 {} | LIMIT_MAKER {:.5} @ ${:.2} (orden {})", engine.symbol, remaining, price, ack.order_id);
+            entry.order_id = Some(ack.order_id);
+            entry.price = price;
+        }
+        Err(e) => {
+            println!("\n↩️ {} | LIMIT_MAKER no colocada: {}", engine.symbol, e);
+            entry.order_id = None;
+        }
+    }
+    None
+}
+
+/// Retira la LIMIT_MAKER viva y devuelve su estado final, con lo que llegara a
+/// ejecutar. `Err` si no se pudo cancelar y sigue abierta.
+async fn cancel_maker_order<G: ExchangeGateway>(engine: &mut Engine, gateway: &G) -> GatewayResult<Option<OrderAck>> {
+    let Some(order_id) = engine.maker_entry.as_ref().and_then(|e| e.order_id) else { return Ok(None) };
+    let cancelled = gateway.cancel_order(&engine.symbol, order_id).await;
+    let mut ack = match (cancelled, gateway.order_status(&engine.symbol, order_id).await) {
+        (_, Ok(ack)) if !matches!(ack.status, OrderStatus::New | OrderStatus::PartiallyFilled) => ack,
+        (Err(e), _) => return Err(e),
+        (Ok(()), _) => OrderAck::from_fills(order_id, &engine.symbol, OrderSide::Buy, OrderStatus::Canceled, Vec::new()),
+    };
+    // La consulta no trae las ejecuciones: las comisiones salen de las que mandó el stream
+    if ack.fills.is_empty() {
+        ack.fills = std::mem::take(&mut engine.stream_fills);
+        if ack.executed_qty <= 0.0 {
+            ack = OrderAck::from_fills(order_id, &engine.symbol, OrderSide::Buy, ack.status, ack.fills);
+        }
+    }
+    engine.mark_settled(order_id);
+    if let Some(entry) = engine.maker_entry.as_mut() {
+        entry.order_id = None;
+    }
+    Ok(Some(ack))
+}
+
+/// Re-precio: retira la orden y postea otra al libro actual por lo que falte
+async fn reprice_maker_entry<G: ExchangeGateway>(engine: &mut Engine, gateway: &G, timestamp: u64) -> Option<TradeEvent> {
+    match cancel_maker_order(engine, gateway).await {
+        Ok(Some(ack)) if ack.status == OrderStatus::Filled => return engine.finish_maker_entry(timestamp, Some(ack)),
+        Ok(Some(ack)) => engine.add_maker_fill(ack),
+        Ok(None) => {}
+        Err(e) => {
+            println!("\n⚠️ {} | No se pudo re-preciar la LIMIT_MAKER: {}", engine.symbol, e);
+            engine.maker_entry.as_mut()?.placed_at = timestamp;
+            return None;
+        }
+    }
+    place_maker_order(engine, gateway, timestamp).await
+}
+
+/// Fin de la entrada maker por timeout, precio escapado o cierre manual: lo
+/// ejecutado se queda y el resto va a mercado o se abandona.
+async fn expire_maker_entry<G: ExchangeGateway>(engine: &mut Engine, gateway: &G, market: bool, reason: &str, timestamp: u64) -> Option<TradeEvent> {
+    match cancel_maker_order(engine, gateway).await {
+        Ok(Some(ack)) if ack.status == OrderStatus::Filled => return engine.finish_maker_entry(timestamp, Some(ack)),
+        Ok(Some(ack)) => engine.add_maker_fill(ack),
+        Ok(None) => {}
+        Err(e) => {
+            println!("\n⚠️ {} | No se pudo retirar la LIMIT_MAKER ({}): se reintenta", engine.symbol, e);
+            return None;
+        }
+    }
+
+    let PositionState::PendingEntry { qty, price, .. } = engine.state else { return None };
+    let filled = engine.maker_entry.as_ref()?.filled.as_ref().map_or(0.0, |f| f.executed_qty);
+    println!("\n⌛ {} | Entrada maker terminada ({}): {:.5} de {:.5} ejecutado, {}",
+        engine.symbol, reason, filled, qty, if market { "el resto a mercado" } else { "se abandona el resto" });
+    if !market {
+        return engine.finish_maker_entry(timestamp, None);
+    }
+
+    let mut remaining = qty - filled;
+    if let Some(filters) = gateway.symbol_filters(&engine.symbol) {
+        match filters.validate_order(remaining, price, true) {
+            Ok(normalized) => remaining = normalized,
+            Err(reason) => {
+                println!("\n⚠️ {} | Resto no ejecutable a mercado ({}): se entra con lo ejecutado", engine.symbol, reason);
+                return engine.finish_maker_entry(timestamp, None);
+            }
+        }
+    }
+    match gateway.market_order(&engine.symbol, OrderSide::Buy, remaining).await {
+        Ok(ack) => {
+            println!("\n✅ ORDEN {} EJECUTADA ({:?}) | {:?} {:.5} @ ${:.2} = ${:.2}", ack.order_id, ack.status, ack.side, ack.executed_qty, ack.avg_price, ack.cum_quote);
+            engine.mark_settled(ack.order_id);
+            engine.finish_maker_entry(timestamp, Some(ack))
+        }
+        Err(e) => {
+            println!("\n❌ ERROR ORDEN: {}", e);
+            engine.finish_maker_entry(timestamp, None)
+        }
+    }
+}
+
+/// Cancela el stop del exchange (para vender o para subirlo). Si ya no se puede
+/// cancelar se consulta la orden: llena → se procesa como salida y se devuelve el
+/// evento; cerrada sin ejecución → se olvida; todavía abierta → `Err`.
+async fn cancel_protection<G: ExchangeGateway>(engine: &mut Engine, gateway: &G, timestamp: u64) -> GatewayResult<Option<TradeEvent>> {
+    let Some(order) = engine.protective_order.clone() else { return Ok(None) };
+    let ack = match gateway.cancel_order(&engine.symbol, order.order_id).await {
+        // Lo que se llenó antes de cancelarla solo lo conocemos por el stream
+        Ok(()) => OrderAck::from_fills(order.order_id, &engine.symbol, OrderSide::Sell, OrderStatus::Canceled, std::mem::take(&mut engine.stream_fills)),
+        Err(e) => match gateway.order_status(&engine.symbol, order.order_id).await {
+            Ok(ack) if !matches!(ack.status, OrderStatus::New | OrderStatus::PartiallyFilled) => ack,
+            _ => return Err(e),
+        },
+    };
+    if ack.executed_qty > 0.0 {
+        return Ok(engine.on_protective_fill(timestamp, &ack));
+    }
+    engine.protective_order = None;
+    engine.mark_settled(order.order_id);
+    Ok(None)
+}
+
+/// Coloca el stop del exchange, reemplazando el anterior si lo hay. Entre la
+/// cancelación y la orden nueva la posición queda sin stop un instante.
+/// Cualquier fallo se reintenta pasados `protection.retry_secs`.
+async fn place_protection<G: ExchangeGateway>(engine: &mut Engine, gateway: &G, mut qty: f64, stop_price: f64, limit_price: f64, timestamp: u64) -> Option<TradeEvent> {
+    let retry_at = timestamp + engine.protection.retry_secs * 1000;
+    match cancel_protection(engine, gateway, timestamp).await {
+        Ok(None) => {}
+        Ok(Some(event)) => return Some(event),
+        Err(e) => {
+            println!("\n⚠️ {} | No se pudo reemplazar el stop del exchange: {}", engine.symbol, e);
+            engine.protective_retry_at = Some(retry_at);
+            return None;
+        }
+    }
+
+    if let Some(filters) = gateway.symbol_filters(&engine.symbol) {
+        match filters.validate_order(qty, limit_price, false) {
+            Ok(normalized) => qty = normalized,
+            Err(reason) => {
+                println!("\n⚠️ STOP DEL EXCHANGE NO COLOCADO: {}", reason);
+                engine.protective_retry_at = Some(retry_at);
+                return None;
+            }
+        }
+    }
+
+    match gateway.stop_loss_limit(&engine.symbol, qty, stop_price, limit_price).await {
+        Ok(ack) => {
+            println!("\n🛡️ {} | Stop en el exchange: {:.5} | Stop ${:.2} / Límite ${:.2} (orden {})", engine.symbol, qty, stop_price, limit_price, ack.order_id);
+            engine.protective_order = Some(ProtectiveOrder { order_id: ack.order_id, stop_price, limit_price, qty });
+            engine.protective_retry_at = None;
+        }
+        Err(e) => {
+            println!("\n❌ {} | Error colocando el stop del exchange: {}", engine.symbol, e);
+            engine.protective_retry_at = Some(retry_at);
+        }
+    }
+    None
+}
+
+/// Tras reconectar el user data stream con un listenKey nuevo, los executionReport
+/// del hueco no van a llegar: se consulta por REST cada orden que los esperaba.
+/// - LIMIT_MAKER de la entrada o stop del exchange ya cerrados → se procesan como si
+///   hubiera llegado su report final. Si siguen abiertos, el stream nuevo los verá.
+/// - Orden a mercado sin respuesta (sin id): las ejecuciones del lado pendiente
+///   desde que se envió son suyas. Sin ninguna, `Portfolio::expire_unconfirmed` la
+///   dará por no ejecutada al vencer su plazo.
+pub async fn resync_pending_orders<G: ExchangeGateway>(engine: &mut Engine, gateway: &G, timestamp: u64) -> Option<TradeEvent> {
+    let is_final = |ack: &OrderAck| !matches!(ack.status, OrderStatus::New | OrderStatus::PartiallyFilled);
+    let symbol = engine.symbol.clone();
+
+    if let Some(order_id) = engine.maker_entry.as_ref().and_then(|e| e.order_id) {
+        match gateway.order_status(&symbol, order_id).await {
+            Ok(mut ack) if is_final(&ack) => {
+                if ack.fills.is_empty() {
+                    ack.fills = std::mem::take(&mut engine.stream_fills);
+                }
+                engine.mark_settled(order_id);
+                println!("\n🔄 {} | LIMIT_MAKER {} cerrada durante la desconexión ({:?}) | {:.5} ejecutado", symbol, order_id, ack.status, ack.executed_qty);
+                return engine.finish_maker_entry(timestamp, Some(ack));
+            }
+            Ok(_) => {}
+            Err(e) => println!("\n⚠️ {} | No se pudo consultar la LIMIT_MAKER {}: {}", symbol, order_id, e),
+        }
+    }
+
+    if let Some(order) = engine.protective_order.clone() {
+        match gateway.order_status(&symbol, order.order_id).await {
+            Ok(ack) if is_final(&ack) && ack.executed_qty > 0.0 => return engine.on_protective_fill(timestamp, &ack),
+            Ok(ack) if is_final(&ack) => {
+                println!("\n🔄 {} | Stop del exchange {} cerrado sin ejecución ({:?}): se vuelve a colocar", symbol, order.order_id, ack.status);
+                engine.protective_order = None;
+                engine.mark_settled(order.order_id);
+            }
+            Ok(_) => {}
+            Err(e) => println!("\n⚠️ {} | No se pudo consultar el stop del exchange {}: {}", symbol, order.order_id, e),
+        }
+    }
+
+    let since = engine.unconfirmed_since?;
+    let side = match engine.state {
+        PositionState::PendingEntry { .. } => OrderSide::Buy,
+        PositionState::PendingExit { .. } => OrderSide::Sell,
+        _ => return None,
+    };
+    let trades = match gateway.trade_history(&symbol).await {
+        Ok(trades) => trades,
+        Err(e) => {
+            println!("\n⚠️ {} | No se pudo consultar la orden sin confirmar: {}", symbol, e);
+            return None;
+        }
+    };
+    let fills: Vec<Fill> = trades.iter().filter(|t| t.side == side && t.time >= since).map(|t| Fill {
+        trade_id: Some(t.id),
+        price: t.price,
+        qty: t.qty,
+        commission: t.commission,
+        commission_asset: t.commission_asset.clone(),
+    }).collect();
+    if fills.is_empty() {
+        return None;
+    }
+    let ack = OrderAck::from_fills(0, &symbol, side, OrderStatus::Filled, fills);
+    println!("\n🔄 {} | Orden sin confirmar ejecutada durante la desconexión | {:?} {:.5} @ ${:.2}", symbol, side, ack.executed_qty, ack.avg_price);
+    engine.unconfirmed_since = None;
+    engine.stream_fills.clear();
+    engine.on_order_filled(timestamp, &ack)
+}
+
+/// `ia_score` son los puntos del modelo (ver `ExposurePolicy::ia_score`).
+/// `cross_asset_bonus` es el ajuste del Pilar 6 (ver `StrategyConfig::cross_asset_bonus`)
+pub fn calculate_confidence_score(ia_score: f64, volume: f64, macro_ctx: &MacroFilter, cross_asset_bonus: f64) -> f64 {
+    let mut score = ia_score;
+
+    if volume > 2.0 { score += 0.15; }
+    else if volume > 1.0 { score += 0.05; }
+
+    if macro_ctx.is_bull_market { score += 0.20; }
+    if macro_ctx.rsi_oversold { score += 0.10; }
+    if macro_ctx.volatility_regime == VolatilityRegime::High { score -= 0.10; }
+    score + cross_asset_bonus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::gateway::paper::PaperGateway;
+    use std::error::Error;
+
+    /// Modelo de ruido constante: con 0.05 el motor quiere entrar en cuanto hay features
+    struct FixedNoise(f64);
+
+    impl NoiseModel for FixedNoise {
+        fn predict_noise(&self, _features: Vec<f64>) -> Result<f64, Box<dyn Error>> {
+            Ok(self.0)
+        }
+    }
+
+    struct Harness {
+        engine: Engine,
+        corr: CorrelatedAsset,
+        gateway: PaperGateway,
+        now: u64,
+    }
+
+    impl Harness {
+        fn new(usdt: f64) -> Self {
+            let config = Config::default();
+            Self {
+                engine: Engine::new("BTCUSDT", &config),
+                corr: CorrelatedAsset::new(&config.market.corr_asset, config.market.candle_interval_ms(), config.market.buffer_limit),
+                gateway: PaperGateway::new(&[("USDT", usdt)], 0.001, 0.0),
+                now: 0,
+            }
+        }
+
+        /// Un trade por segundo en el símbolo, en el correlacionado y en el tape del paper
+        fn tick(&mut self, price: f64) -> Option<OrderIntent> {
+            self.now += 1_000;
+            self.corr.push_trade(&PriceMessage { symbol: "ETHUSDT".to_string(), price: price * 30.0, volume: 1.0, timestamp: self.now });
+            self.gateway.on_market_trade("BTCUSDT", price);
+            self.engine.on_tick(&PriceMessage { symbol: "BTCUSDT".to_string(), price, volume: 3.0, timestamp: self.now }, &FixedNoise(0.05), &self.corr)
+        }
+
+        /// Precio oscilante hasta que el motor pide la entrada
+        fn signal(&mut self) -> OrderIntent {
+            for i in 0..100 {
+                if let Some(intent) = self.tick(if i % 2 == 0 { 100.3 } else { 99.7 }) {
+                    return intent;
+                }
+            }
+            panic!("el motor nunca pidió la entrada");
+        }
+
+        async fn execute(&mut self, intent: &OrderIntent) -> Option<TradeEvent> {
+            execute_intent(&mut self.engine, &self.gateway, intent, self.now).await
+        }
+
+        async fn open(&mut self) -> f64 {
+            let intent = self.signal();
+            self.execute(&intent).await;
+            let PositionState::Open { qty, .. } = self.engine.state else { panic!("posición no abierta: {:?}", self.engine.state) };
+            qty
+        }
+    }
+
+    fn partial_ack(side: OrderSide, qty: f64, price: f64) -> OrderAck {
+        let fill = Fill { trade_id: Some(1), price, qty, commission: qty * price * 0.001, commission_asset: "USDT".to_string() };
+        OrderAck::from_fills(99, "BTCUSDT", side, OrderStatus::PartiallyFilled, vec![fill])
+    }
+
+    #[tokio::test]
+    async fn full_cycle_through_paper() {
+        let mut h = Harness::new(10_000.0);
+        let intent = h.signal();
+        let OrderIntent::Buy { qty, reference_price, .. } = intent.clone() else { panic!("se esperaba una compra: {:?}", intent) };
+        assert!(matches!(h.engine.state, PositionState::PendingEntry { .. }));
+
+        let Some(TradeEvent::Entry { price, qty: held, fees, .. }) = h.execute(&intent).await else { panic!("sin evento de entrada") };
+        assert_eq!(price, reference_price);
+        assert_eq!(held, qty);
+        assert!((fees - qty * price * 0.001).abs() < 1e-9);
+        assert_eq!(h.engine.state, PositionState::Open { qty, entry_price: price });
+        assert!(h.engine.risk_manager.stop_price < price);
+
+        // Muy por debajo del stop loss: salida a mercado
+        let exit = h.tick(price * 0.9).expect("el stop loss no pidió la salida");
+        assert!(matches!(exit, OrderIntent::Sell { reason: "STOP LOSS", .. }));
+        assert!(matches!(h.engine.state, PositionState::PendingExit { .. }));
+
+        let Some(TradeEvent::Exit { qty: sold, pnl_usd, position_pnl_usd, .. }) = h.execute(&exit).await else { panic!("sin evento de salida") };
+        assert_eq!(sold, qty);
+        assert!(pnl_usd < 0.0);
+        assert_eq!(position_pnl_usd, Some(pnl_usd));
+        assert_eq!(h.engine.state, PositionState::Flat);
+        assert_eq!(h.engine.entry_fees, 0.0);
+    }
+
+    #[tokio::test]
+    async fn exchange_stop_filled_by_the_tape_closes_the_position() {
+        let mut h = Harness::new(10_000.0);
+        let qty = h.open().await;
+        let PositionState::Open { entry_price, .. } = h.engine.state else { unreachable!() };
+
+        let protect = h.tick(entry_price).expect("sin stop del exchange");
+        assert!(matches!(protect, OrderIntent::Protect { .. }));
+        assert!(h.execute(&protect).await.is_none());
+        let stop = h.engine.protective_order.clone().expect("stop no registrado");
+
+        // El tape cruza el stop sin pasar por el motor (bot caído o hueco de precio)
+        let acks = h.gateway.on_market_trade("BTCUSDT", stop.stop_price * 0.999);
+        assert_eq!(acks.len(), 1);
+        let Some(TradeEvent::Exit { reason, qty: sold, .. }) = h.engine.on_resting_fill(h.now, &acks[0]) else { panic!("sin evento de salida") };
+        assert_eq!(reason, "STOP EXCHANGE");
+        assert!((sold - qty).abs() < 1e-9);
+        assert_eq!(h.engine.state, PositionState::Flat);
+        assert!(h.engine.protective_order.is_none());
+    }
+
+    #[tokio::test]
+    async fn rejected_entry_goes_back_to_flat() {
+        let mut h = Harness::new(10.0);
+        let intent = h.signal();
+        assert!(h.execute(&intent).await.is_none());
+        assert_eq!(h.engine.state, PositionState::Flat);
+    }
+
+    #[tokio::test]
+    async fn partial_entry_opens_with_the_executed_qty() {
+        let mut h = Harness::new(10_000.0);
+        let OrderIntent::Buy { qty, reference_price, .. } = h.signal() else { panic!("se esperaba una compra") };
+
+        let event = h.engine.on_order_filled(h.now, &partial_ack(OrderSide::Buy, qty / 2.0, reference_price));
+        assert!(matches!(event, Some(TradeEvent::Entry { .. })));
+        assert_eq!(h.engine.state, PositionState::Open { qty: qty / 2.0, entry_price: reference_price });
+    }
+
+    #[tokio::test]
+    async fn partial_exit_keeps_the_rest_open_until_it_is_sold() {
+        let mut h = Harness::new(10_000.0);
+        let qty = h.open().await;
+        let PositionState::Open { entry_price, .. } = h.engine.state else { unreachable!() };
+
+        let exit_price = entry_price * 1.01;
+        assert!(h.engine.request_close(exit_price).is_some());
+        let Some(TradeEvent::Exit { pnl_usd: first, position_pnl_usd, .. }) = h.engine.on_order_filled(h.now, &partial_ack(OrderSide::Sell, qty * 0.4, exit_price))
+            else { panic!("sin evento de salida parcial") };
+        assert_eq!(position_pnl_usd, None);
+        let PositionState::Open { qty: rest, .. } = h.engine.state else { panic!("el resto debería seguir abierto") };
+        assert!((rest - qty * 0.6).abs() < 1e-9);
+
+        // El resto se vende por el paper y cierra la posición con el PnL de ambas salidas
+        h.gateway.on_market_trade("BTCUSDT", exit_price);
+        let intent = h.engine.request_close(exit_price).expect("sin orden para el resto");
+        let Some(TradeEvent::Exit { qty: sold, pnl_usd: second, position_pnl_usd, .. }) = h.execute(&intent).await else { panic!("sin evento de salida") };
+        assert!((sold - rest).abs() < 1e-9);
+        assert!((position_pnl_usd.unwrap() - (first + second)).abs() < 1e-9);
+        assert_eq!(h.engine.state, PositionState::Flat);
+        assert_eq!(h.engine.realized_pnl, 0.0);
+    }
+
+    #[tokio::test]
+    async fn stop_filled_while_the_stream_was_down_is_found_on_reconnect() {
+        let mut h = Harness::new(10_000.0);
+        let qty = h.open().await;
+        let PositionState::Open { entry_price, .. } = h.engine.state else { unreachable!() };
+        let protect = h.tick(entry_price).expect("sin stop del exchange");
+        h.execute(&protect).await;
+        let stop = h.engine.protective_order.clone().expect("stop no registrado");
+
+        // El executionReport del stop se pierde con el listenKey anterior
+        assert_eq!(h.gateway.on_market_trade("BTCUSDT", stop.stop_price * 0.999).len(), 1);
+        assert!(matches!(h.engine.state, PositionState::Open { .. }));
+
+        let Some(TradeEvent::Exit { reason, qty: sold, .. }) = resync_pending_orders(&mut h.engine, &h.gateway, h.now).await else { panic!("sin evento de salida") };
+        assert_eq!(reason, "STOP EXCHANGE");
+        assert!((sold - qty).abs() < 1e-9);
+        assert_eq!(h.engine.state, PositionState::Flat);
+    }
+
+    #[tokio::test]
+    async fn unconfirmed_market_order_is_resolved_from_the_trade_history() {
+        let mut h = Harness::new(10_000.0);
+        let OrderIntent::Buy { qty, .. } = h.signal() else { panic!("se esperaba una compra") };
+
+        // Sin pendiente de confirmar no se consulta nada
+        assert!(resync_pending_orders(&mut h.engine, &h.gateway, h.now).await.is_none());
+        h.engine.unconfirmed_since = Some(h.now);
+        assert!(resync_pending_orders(&mut h.engine, &h.gateway, h.now).await.is_none());
+        assert!(matches!(h.engine.state, PositionState::PendingEntry { .. }));
+
+        // La orden llegó al exchange pero la respuesta REST y su executionReport se perdieron
+        h.gateway.market_order("BTCUSDT", OrderSide::Buy, qty).await.unwrap();
+        let Some(TradeEvent::Entry { qty: held, .. }) = resync_pending_orders(&mut h.engine, &h.gateway, h.now).await else { panic!("sin evento de entrada") };
+        assert!((held - qty).abs() < 1e-9);
+        assert!(matches!(h.engine.state, PositionState::Open { .. }));
+        assert!(h.engine.unconfirmed_since.is_none());
+    }
+}
@@ -0,0 +1,111 @@
+use crate::config::StrategyConfig;
+
+/// Aportación del modelo a la puntuación de confianza: (ruido por debajo de, puntos).
+/// Son los tramos de siempre de `calculate_confidence_score`; por encima del último no suma.
+const IA_SCORE_TIERS: [(f64, f64); 3] = [(0.10, 0.55), (0.25, 0.45), (0.35, 0.30)];
+
+/// Zona de exposición según la probabilidad de ruido del modelo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exposure {
+    /// Pilar 1 (No-Trade Intelligence): ruido por encima de `no_trade_threshold`
+    Blocked,
+    /// Zona de incertidumbre: tamaño multiplicado por `risk_reduction_factor`
+    Reduced(f64),
+    /// Ruido por debajo de `high_confidence_threshold`: tamaño completo
+    Full,
+}
+
+impl Exposure {
+    /// Factor que se aplica al tamaño de la entrada
+    pub fn size_factor(self) -> f64 {
+        match self {
+            Exposure::Blocked => 0.0,
+            Exposure::Reduced(factor) => factor,
+            Exposure::Full => 1.0,
+        }
+    }
+}
+
+/// Pilares 1 y 4: traduce la probabilidad de ruido a exposición permitida. Es
+/// lo único que mira los umbrales de ruido; el gate de confianza y el tamaño
+/// de la entrada parten de aquí.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExposurePolicy {
+    pub no_trade_threshold: f64,
+    pub high_confidence_threshold: f64,
+    pub risk_reduction_factor: f64,
+}
+
+impl ExposurePolicy {
+    pub fn new(config: &StrategyConfig) -> Self {
+        Self {
+            no_trade_threshold: config.no_trade_threshold,
+            high_confidence_threshold: config.high_confidence_threshold,
+            risk_reduction_factor: config.risk_reduction_factor,
+        }
+    }
+
+    pub fn exposure(&self, noise: f64) -> Exposure {
+        if noise > self.no_trade_threshold {
+            Exposure::Blocked
+        } else if noise < self.high_confidence_threshold {
+            Exposure::Full
+        } else {
+            Exposure::Reduced(self.risk_reduction_factor)
+        }
+    }
+
+    /// Puntos de confianza que aporta el modelo (`IA_SCORE_TIERS`); nada en la zona bloqueada
+    pub fn ia_score(&self, noise: f64) -> f64 {
+        if self.exposure(noise) == Exposure::Blocked {
+            return 0.0;
+        }
+        IA_SCORE_TIERS.iter().find(|(below, _)| noise < *below).map_or(0.0, |(_, score)| *score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ExposurePolicy {
+        ExposurePolicy::new(&StrategyConfig::default())
+    }
+
+    #[test]
+    fn full_size_below_high_confidence_threshold() {
+        let p = policy();
+        assert_eq!(p.exposure(0.0), Exposure::Full);
+        assert_eq!(p.exposure(0.10), Exposure::Full);
+        assert_eq!(p.exposure(0.2499), Exposure::Full);
+        assert_eq!(p.exposure(0.10).size_factor(), 1.0);
+    }
+
+    #[test]
+    fn reduced_between_thresholds_inclusive() {
+        let p = policy();
+        assert_eq!(p.exposure(0.25), Exposure::Reduced(0.10));
+        assert_eq!(p.exposure(0.50), Exposure::Reduced(0.10));
+        assert_eq!(p.exposure(0.70), Exposure::Reduced(0.10));
+        assert_eq!(p.exposure(0.70).size_factor(), 0.10);
+    }
+
+    #[test]
+    fn blocked_above_no_trade_threshold() {
+        let p = policy();
+        assert_eq!(p.exposure(0.7001), Exposure::Blocked);
+        assert_eq!(p.exposure(1.0).size_factor(), 0.0);
+        assert_eq!(p.ia_score(0.9), 0.0);
+    }
+
+    #[test]
+    fn ia_score_keeps_the_original_tiers() {
+        let p = policy();
+        assert_eq!(p.ia_score(0.05), 0.55);
+        assert_eq!(p.ia_score(0.10), 0.45);
+        assert_eq!(p.ia_score(0.25), 0.30);
+        assert_eq!(p.ia_score(0.35), 0.0);
+        assert_eq!(p.ia_score(0.40), 0.0);
+        assert_eq!(p.ia_score(0.70), 0.0);
+    }
+}
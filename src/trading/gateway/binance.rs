@@ -0,0 +1,258 @@
+use binance::account::*;
+use binance::api::*;
+use binance::config::Config;
+use binance::model::{Order, Transaction};
+use binance::util::build_signed_request;
+use std::collections::{BTreeMap, HashMap};
+use tokio::task;
+use super::symbol_filters::{fetch_symbol_filters, SymbolFilters};
+use super::{AccountTrade, AssetBalance, ExchangeGateway, Fill, GatewayResult, OrderAck, OrderSide, OrderStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinanceNetwork {
+    Testnet,
+    Mainnet,
+}
+
+impl BinanceNetwork {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "testnet" => Some(BinanceNetwork::Testnet),
+            "mainnet" => Some(BinanceNetwork::Mainnet),
+            _ => None,
+        }
+    }
+
+    pub fn config(&self) -> Config {
+        match self {
+            BinanceNetwork::Testnet => Config::testnet(),
+            BinanceNetwork::Mainnet => Config::default(),
+        }
+    }
+}
+
+/// Gateway real de Binance Spot. Órdenes, precios y filtros salen del mismo
+/// endpoint, así testnet y mainnet nunca se mezclan.
+pub struct BinanceGateway {
+    api_key: String,
+    secret_key: String,
+    network: BinanceNetwork,
+    filters: HashMap<String, SymbolFilters>,
+}
+
+impl BinanceGateway {
+    pub fn new(api_key: String, secret_key: String, network: BinanceNetwork) -> Self {
+        Self { api_key, secret_key, network, filters: HashMap::new() }
+    }
+
+    /// Carga (una vez, al arrancar) los filtros de exchangeInfo de los símbolos operados
+    pub async fn load_symbol_filters(&mut self, symbols: &[String]) -> GatewayResult<()> {
+        self.filters = fetch_symbol_filters(&self.network.config().rest_api_endpoint, symbols).await?;
+        Ok(())
+    }
+
+    /// Cantidad ajustada al stepSize del símbolo (sin filtros cargados se envía tal cual)
+    fn normalize_qty(&self, symbol: &str, qty: f64) -> f64 {
+        self.filters.get(symbol).map(|f| f.normalize_qty(qty)).unwrap_or(qty)
+    }
+
+    /// Igual que `normalize_qty`, con el paso de MARKET_LOT_SIZE de las órdenes market
+    fn normalize_market_qty(&self, symbol: &str, qty: f64) -> f64 {
+        self.filters.get(symbol).map(|f| f.normalize_market_qty(qty)).unwrap_or(qty)
+    }
+
+    /// La librería `binance` es bloqueante: cada llamada va a un hilo aparte
+    /// para no frenar el loop del WebSocket (Pong timeout).
+    async fn with_account<T, F>(&self, call: F) -> GatewayResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(Account) -> Result<T, String> + Send + 'static,
+    {
+        let key = self.api_key.clone();
+        let secret = self.secret_key.clone();
+        let config = self.network.config();
+
+        task::spawn_blocking(move || {
+            let account: Account = Binance::new_with_config(Some(key), Some(secret), &config);
+            call(account)
+        }).await?.map_err(|e| e.into())
+    }
+}
+
+/// Parámetros comunes de una orden firmada a mano, con respuesta FULL (id, estado y fills)
+fn order_params(symbol: &str, side: OrderSide, order_type: &str, qty: f64) -> BTreeMap<String, String> {
+    let mut params = BTreeMap::new();
+    params.insert("symbol".to_string(), symbol.to_string());
+    params.insert("side".to_string(), match side { OrderSide::Buy => "BUY", OrderSide::Sell => "SELL" }.to_string());
+    params.insert("type".to_string(), order_type.to_string());
+    params.insert("quantity".to_string(), qty.to_string());
+    params.insert("newOrderRespType".to_string(), "FULL".to_string());
+    params
+}
+
+fn post_order(account: &Account, params: BTreeMap<String, String>) -> Result<Transaction, String> {
+    let request = build_signed_request(params, account.recv_window).map_err(|e| format!("{:?}", e))?;
+    account.client.post_signed::<Transaction>(API::Spot(Spot::Order), request).map_err(|e| format!("{:?}", e))
+}
+
+fn to_ack(tx: Transaction, side: OrderSide) -> OrderAck {
+    let avg_price = if tx.executed_qty > 0.0 { tx.cummulative_quote_qty / tx.executed_qty } else { 0.0 };
+    let fills = tx.fills.unwrap_or_default().into_iter().map(|f| Fill {
+        trade_id: f.trade_id,
+        price: f.price,
+        qty: f.qty,
+        commission: f.commission,
+        commission_asset: f.commission_asset,
+    }).collect();
+    OrderAck {
+        order_id: tx.order_id,
+        symbol: tx.symbol,
+        side,
+        status: OrderStatus::from_binance(&tx.status),
+        executed_qty: tx.executed_qty,
+        cum_quote: tx.cummulative_quote_qty,
+        avg_price,
+        fills,
+    }
+}
+
+/// Orden consultada (`openOrders` / `order`): sin fills, solo los acumulados
+fn order_to_ack(order: Order) -> Option<OrderAck> {
+    let executed_qty: f64 = order.executed_qty.parse().unwrap_or(0.0);
+    let cum_quote: f64 = order.cummulative_quote_qty.parse().unwrap_or(0.0);
+    Some(OrderAck {
+        order_id: order.order_id,
+        side: OrderSide::from_binance(&order.side)?,
+        status: OrderStatus::from_binance(&order.status),
+        avg_price: if executed_qty > 0.0 { cum_quote / executed_qty } else { 0.0 },
+        executed_qty,
+        cum_quote,
+        symbol: order.symbol,
+        fills: Vec::new(),
+    })
+}
+
+impl ExchangeGateway for BinanceGateway {
+    async fn market_order(&self, symbol: &str, side: OrderSide, qty: f64) -> GatewayResult<OrderAck> {
+        let qty = self.normalize_market_qty(symbol, qty);
+        let symbol = symbol.to_string();
+
+        let tx = self.with_account(move |account| {
+            let result = match side {
+                OrderSide::Buy => account.market_buy(symbol, qty),
+                OrderSide::Sell => account.market_sell(symbol, qty),
+            };
+            result.map_err(|e| format!("{:?}", e))
+        }).await?;
+        Ok(to_ack(tx, side))
+    }
+
+    async fn limit_order(&self, symbol: &str, side: OrderSide, qty: f64, price: f64) -> GatewayResult<OrderAck> {
+        let qty = self.normalize_qty(symbol, qty);
+        let price = self.filters.get(symbol).map(|f| f.normalize_price(price)).unwrap_or(price);
+        let symbol = symbol.to_string();
+
+        let tx = self.with_account(move |account| {
+            let result = match side {
+                OrderSide::Buy => account.limit_buy(symbol, qty, price),
+                OrderSide::Sell => account.limit_sell(symbol, qty, price),
+            };
+            result.map_err(|e| format!("{:?}", e))
+        }).await?;
+        Ok(to_ack(tx, side))
+    }
+
+    async fn limit_maker(&self, symbol: &str, side: OrderSide, qty: f64, price: f64) -> GatewayResult<OrderAck> {
+        let qty = self.normalize_qty(symbol, qty);
+        let price = self.filters.get(symbol).map(|f| f.normalize_price(price)).unwrap_or(price);
+        let symbol = symbol.to_string();
+
+        // La librería no trae LIMIT_MAKER: la misma petición firmada que sus órdenes, sin timeInForce
+        let tx = self.with_account(move |account| {
+            let mut params = order_params(&symbol, side, "LIMIT_MAKER", qty);
+            params.insert("price".to_string(), price.to_string());
+            post_order(&account, params)
+        }).await?;
+        Ok(to_ack(tx, side))
+    }
+
+    async fn stop_loss_limit(&self, symbol: &str, qty: f64, stop_price: f64, limit_price: f64) -> GatewayResult<OrderAck> {
+        let qty = self.normalize_qty(symbol, qty);
+        let (stop_price, limit_price) = match self.filters.get(symbol) {
+            Some(f) => (f.normalize_price(stop_price), f.normalize_price(limit_price)),
+            None => (stop_price, limit_price),
+        };
+        let symbol = symbol.to_string();
+
+        // `stop_limit_sell_order` no pide newOrderRespType y Binance responde a un
+        // STOP_LOSS_LIMIT solo con el ACK, que no deserializa como `Transaction`:
+        // la orden quedaría colocada pero sin id en el bot
+        let tx = self.with_account(move |account| {
+            let mut params = order_params(&symbol, OrderSide::Sell, "STOP_LOSS_LIMIT", qty);
+            params.insert("price".to_string(), limit_price.to_string());
+            params.insert("stopPrice".to_string(), stop_price.to_string());
+            params.insert("timeInForce".to_string(), "GTC".to_string());
+            post_order(&account, params)
+        }).await?;
+        Ok(to_ack(tx, OrderSide::Sell))
+    }
+
+    async fn cancel_order(&self, symbol: &str, order_id: u64) -> GatewayResult<()> {
+        let symbol = symbol.to_string();
+        self.with_account(move |account| {
+            account.cancel_order(symbol, order_id).map(|_| ()).map_err(|e| format!("{:?}", e))
+        }).await
+    }
+
+    async fn balances(&self) -> GatewayResult<Vec<AssetBalance>> {
+        let info = self.with_account(|account| account.get_account().map_err(|e| format!("{:?}", e))).await?;
+        Ok(info.balances.iter().map(|b| AssetBalance {
+            asset: b.asset.clone(),
+            free: b.free.parse().unwrap_or(0.0),
+            locked: b.locked.parse().unwrap_or(0.0),
+        }).collect())
+    }
+
+    async fn open_orders(&self, symbol: &str) -> GatewayResult<Vec<OrderAck>> {
+        let symbol = symbol.to_string();
+        let orders = self.with_account(move |account| account.get_open_orders(symbol).map_err(|e| format!("{:?}", e))).await?;
+        Ok(orders.into_iter().filter_map(order_to_ack).collect())
+    }
+
+    async fn order_status(&self, symbol: &str, order_id: u64) -> GatewayResult<OrderAck> {
+        let symbol = symbol.to_string();
+        let order = self.with_account(move |account| account.order_status(symbol, order_id).map_err(|e| format!("{:?}", e))).await?;
+        let side = order.side.clone();
+        order_to_ack(order).ok_or_else(|| format!("Orden {} con lado desconocido: {}", order_id, side).into())
+    }
+
+    async fn trade_history(&self, symbol: &str) -> GatewayResult<Vec<AccountTrade>> {
+        let symbol = symbol.to_string();
+        let query = symbol.clone();
+        let trades = self.with_account(move |account| account.trade_history(query).map_err(|e| format!("{:?}", e))).await?;
+        Ok(trades.into_iter().map(|t| AccountTrade {
+            id: t.id,
+            symbol: symbol.clone(),
+            side: if t.is_buyer { OrderSide::Buy } else { OrderSide::Sell },
+            price: t.price,
+            qty: t.qty,
+            commission: t.commission.parse().unwrap_or(0.0),
+            commission_asset: t.commission_asset,
+            time: t.time,
+        }).collect())
+    }
+
+    async fn latest_price(&self, symbol: &str) -> GatewayResult<f64> {
+        let url = format!("{}/api/v3/ticker/price?symbol={}", self.network.config().rest_api_endpoint, symbol);
+        let client = reqwest::Client::new();
+        let resp = client.get(url).send().await?.json::<serde_json::Value>().await?;
+
+        let price_str = resp["price"].as_str().ok_or("No price in JSON")?;
+        let price: f64 = price_str.parse()?;
+        Ok(price)
+    }
+
+    fn symbol_filters(&self, symbol: &str) -> Option<SymbolFilters> {
+        self.filters.get(symbol).cloned()
+    }
+}
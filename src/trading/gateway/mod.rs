@@ -0,0 +1,175 @@
+pub mod binance;
+pub mod paper;
+pub mod symbol_filters;
+pub mod user_stream;
+
+use symbol_filters::SymbolFilters;
+
+use std::error::Error;
+use std::future::Future;
+
+pub type GatewayResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+}
+
+impl OrderSide {
+    pub fn from_binance(side: &str) -> Option<Self> {
+        match side {
+            "BUY" => Some(OrderSide::Buy),
+            "SELL" => Some(OrderSide::Sell),
+            _ => None,
+        }
+    }
+}
+
+impl OrderStatus {
+    /// Traduce el campo `status` de la API de Binance
+    pub fn from_binance(status: &str) -> Self {
+        match status {
+            "FILLED" => OrderStatus::Filled,
+            "PARTIALLY_FILLED" => OrderStatus::PartiallyFilled,
+            "CANCELED" | "EXPIRED" => OrderStatus::Canceled,
+            "REJECTED" => OrderStatus::Rejected,
+            _ => OrderStatus::New,
+        }
+    }
+}
+
+/// Ejecución individual de una orden (`fills` de la respuesta de Binance)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub trade_id: Option<u64>,
+    pub price: f64,
+    pub qty: f64,
+    pub commission: f64,
+    pub commission_asset: String,
+}
+
+/// Respuesta del exchange a una orden enviada
+#[derive(Debug, Clone)]
+pub struct OrderAck {
+    pub order_id: u64,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub status: OrderStatus,
+    pub executed_qty: f64,
+    /// Importe ejecutado en moneda de cotización (`cummulativeQuoteQty`)
+    pub cum_quote: f64,
+    pub avg_price: f64, // 0.0 si todavía no hay ejecución
+    pub fills: Vec<Fill>,
+}
+
+impl OrderAck {
+    /// Ack a partir de las ejecuciones sueltas de una orden
+    pub fn from_fills(order_id: u64, symbol: &str, side: OrderSide, status: OrderStatus, fills: Vec<Fill>) -> Self {
+        let executed_qty: f64 = fills.iter().map(|f| f.qty).sum();
+        let cum_quote: f64 = fills.iter().map(|f| f.qty * f.price).sum();
+        let avg_price = if executed_qty > 0.0 { cum_quote / executed_qty } else { 0.0 };
+        Self { order_id, symbol: symbol.to_string(), side, status, executed_qty, cum_quote, avg_price, fills }
+    }
+
+    /// Suma la ejecución de otra orden de la misma operación (re-precios de una entrada).
+    /// El id y el estado pasan a ser los de la última.
+    pub fn merge(self, next: &OrderAck) -> OrderAck {
+        let executed_qty = self.executed_qty + next.executed_qty;
+        let cum_quote = self.cum_quote + next.cum_quote;
+        let mut fills = self.fills;
+        fills.extend(next.fills.iter().cloned());
+        OrderAck {
+            order_id: next.order_id,
+            symbol: self.symbol,
+            side: self.side,
+            status: next.status,
+            executed_qty,
+            cum_quote,
+            avg_price: if executed_qty > 0.0 { cum_quote / executed_qty } else { 0.0 },
+            fills,
+        }
+    }
+
+    /// Comisión total de cada activo en que se cobró, en orden de aparición
+    pub fn commissions(&self) -> Vec<(String, f64)> {
+        let mut totals: Vec<(String, f64)> = Vec::new();
+        for fill in &self.fills {
+            match totals.iter_mut().find(|(asset, _)| *asset == fill.commission_asset) {
+                Some((_, total)) => *total += fill.commission,
+                None => totals.push((fill.commission_asset.clone(), fill.commission)),
+            }
+        }
+        totals
+    }
+
+    pub fn commission_in(&self, asset: &str) -> f64 {
+        self.fills.iter().filter(|f| f.commission_asset == asset).map(|f| f.commission).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetBalance {
+    pub asset: String,
+    pub free: f64,
+    pub locked: f64,
+}
+
+/// Ejecución propia en el exchange (`/api/v3/myTrades`)
+#[derive(Debug, Clone)]
+pub struct AccountTrade {
+    pub id: u64,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub price: f64,
+    pub qty: f64,
+    pub commission: f64,
+    pub commission_asset: String,
+    /// ms desde epoch
+    pub time: u64,
+}
+
+/// Todo lo que el motor necesita de un exchange. Hay una implementación real
+/// (Binance mainnet/testnet) y una de paper trading que llena contra el tape.
+pub trait ExchangeGateway {
+    fn market_order(&self, symbol: &str, side: OrderSide, qty: f64) -> impl Future<Output = GatewayResult<OrderAck>> + Send;
+    fn limit_order(&self, symbol: &str, side: OrderSide, qty: f64, price: f64) -> impl Future<Output = GatewayResult<OrderAck>> + Send;
+    /// Limit post-only (LIMIT_MAKER): el exchange la rechaza si se fuera a ejecutar como taker
+    fn limit_maker(&self, symbol: &str, side: OrderSide, qty: f64, price: f64) -> impl Future<Output = GatewayResult<OrderAck>> + Send;
+    /// Venta STOP_LOSS_LIMIT (GTC) que reposa en el exchange: al tocar `stop_price` entra una limit a `limit_price`
+    fn stop_loss_limit(&self, symbol: &str, qty: f64, stop_price: f64, limit_price: f64) -> impl Future<Output = GatewayResult<OrderAck>> + Send;
+    fn cancel_order(&self, symbol: &str, order_id: u64) -> impl Future<Output = GatewayResult<()>> + Send;
+    fn balances(&self) -> impl Future<Output = GatewayResult<Vec<AssetBalance>>> + Send;
+    fn open_orders(&self, symbol: &str) -> impl Future<Output = GatewayResult<Vec<OrderAck>>> + Send;
+    /// Estado actual de una orden propia (abierta o ya cerrada)
+    fn order_status(&self, symbol: &str, order_id: u64) -> impl Future<Output = GatewayResult<OrderAck>> + Send;
+    /// Últimas ejecuciones propias del símbolo, de la más antigua a la más reciente
+    fn trade_history(&self, symbol: &str) -> impl Future<Output = GatewayResult<Vec<AccountTrade>>> + Send;
+    fn latest_price(&self, symbol: &str) -> impl Future<Output = GatewayResult<f64>> + Send;
+
+    /// Filtros de exchangeInfo del símbolo, si el gateway los tiene cargados
+    fn symbol_filters(&self, _symbol: &str) -> Option<SymbolFilters> {
+        None
+    }
+
+    /// Comisiones (maker, taker) que aplica el propio gateway; `None` si son las de la cuenta
+    fn fee_rates(&self) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// Cada trade del stream pasa por aquí. Solo lo usa el paper trading para
+    /// llenar órdenes contra el tape y devuelve las que ese trade llenó; un
+    /// exchange real lo ignora (sus ejecuciones llegan por el user data stream).
+    fn on_market_trade(&self, _symbol: &str, _price: f64) -> Vec<OrderAck> {
+        Vec::new()
+    }
+}
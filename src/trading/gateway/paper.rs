@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use super::symbol_filters::SymbolFilters;
+use super::{AccountTrade, AssetBalance, ExchangeGateway, Fill, GatewayResult, OrderAck, OrderSide, OrderStatus};
+
+/// Monedas de cotización reconocidas al separar un símbolo (BTCUSDT → BTC / USDT)
+const QUOTE_ASSETS: [&str; 5] = ["USDT", "USDC", "FDUSD", "BTC", "ETH"];
+
+pub fn split_symbol(symbol: &str) -> Option<(&str, &str)> {
+    QUOTE_ASSETS.iter().find_map(|quote| {
+        symbol.strip_suffix(quote).filter(|base| !base.is_empty()).map(|base| (base, *quote))
+    })
+}
+
+struct RestingOrder {
+    order_id: u64,
+    symbol: String,
+    side: OrderSide,
+    qty: f64,
+    price: f64,
+    /// Stop todavía no disparado: hasta entonces la limit no entra en el libro
+    stop_price: Option<f64>,
+}
+
+struct PaperState {
+    balances: HashMap<String, AssetBalance>,
+    last_prices: HashMap<String, f64>,
+    open_orders: Vec<RestingOrder>,
+    /// Órdenes que ya no están abiertas (llenas o canceladas), para `order_status`
+    closed_orders: HashMap<u64, OrderAck>,
+    trades: Vec<AccountTrade>,
+    next_order_id: u64,
+}
+
+/// Paper trading: las órdenes se llenan contra el tape en vivo (o el de un backtest).
+/// Market → último trade ± slippage. Limit → cuando un trade cruza el precio.
+/// La comisión se cobra siempre en la moneda de cotización, como al pagar con BNB:
+/// `maker_fee_rate` para las limit que se llenan en reposo, `fee_rate` para el resto.
+/// Con filtros de exchangeInfo cargados, rechaza lo mismo que rechazaría Binance.
+pub struct PaperGateway {
+    pub fee_rate: f64,
+    pub maker_fee_rate: f64,
+    pub slippage: f64, // Fracción del precio, ej: 0.0005 = 5 bps
+    filters: HashMap<String, SymbolFilters>,
+    state: Mutex<PaperState>,
+}
+
+impl PaperGateway {
+    pub fn new(initial_balances: &[(&str, f64)], fee_rate: f64, slippage: f64) -> Self {
+        let balances = initial_balances.iter().map(|(asset, free)| {
+            (asset.to_string(), AssetBalance { asset: asset.to_string(), free: *free, locked: 0.0 })
+        }).collect();
+
+        Self {
+            fee_rate,
+            maker_fee_rate: fee_rate,
+            slippage,
+            filters: HashMap::new(),
+            state: Mutex::new(PaperState { balances, last_prices: HashMap::new(), open_orders: Vec::new(), closed_orders: HashMap::new(), trades: Vec::new(), next_order_id: 1 }),
+        }
+    }
+
+    pub fn with_maker_fee(mut self, maker_fee_rate: f64) -> Self {
+        self.maker_fee_rate = maker_fee_rate;
+        self
+    }
+
+    pub fn with_filters(mut self, filters: HashMap<String, SymbolFilters>) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Cantidad ajustada y validada contra los filtros del símbolo, si los hay
+    fn apply_filters(&self, symbol: &str, qty: f64, price: f64, is_market: bool) -> GatewayResult<f64> {
+        match self.filters.get(symbol) {
+            Some(filters) => Ok(filters.validate_order(qty, price, is_market)?),
+            None => Ok(qty),
+        }
+    }
+
+    fn ack(order_id: u64, symbol: &str, side: OrderSide, status: OrderStatus, fills: Vec<Fill>) -> OrderAck {
+        OrderAck::from_fills(order_id, symbol, side, status, fills)
+    }
+}
+
+impl PaperState {
+    fn balance(&mut self, asset: &str) -> &mut AssetBalance {
+        self.balances.entry(asset.to_string()).or_insert_with(|| AssetBalance { asset: asset.to_string(), free: 0.0, locked: 0.0 })
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_order_id += 1;
+        self.next_order_id - 1
+    }
+
+    /// Qué activo y cuánto bloquea una orden (USDT para compras, base para ventas)
+    fn required(symbol: &str, side: OrderSide, qty: f64, price: f64, fee_rate: f64) -> GatewayResult<(String, f64)> {
+        let (base, quote) = split_symbol(symbol).ok_or_else(|| format!("Símbolo no soportado en paper: {}", symbol))?;
+        Ok(match side {
+            OrderSide::Buy => (quote.to_string(), qty * price * (1.0 + fee_rate)),
+            OrderSide::Sell => (base.to_string(), qty),
+        })
+    }
+
+    fn settle(&mut self, symbol: &str, side: OrderSide, qty: f64, price: f64, fee_rate: f64) -> GatewayResult<Fill> {
+        let (base, quote) = split_symbol(symbol).ok_or_else(|| format!("Símbolo no soportado en paper: {}", symbol))?;
+        let notional = qty * price;
+        let fee = notional * fee_rate;
+        match side {
+            OrderSide::Buy => {
+                self.balance(quote).free -= notional + fee;
+                self.balance(base).free += qty;
+            }
+            OrderSide::Sell => {
+                self.balance(base).free -= qty;
+                self.balance(quote).free += notional - fee;
+            }
+        }
+        let fill = Fill { trade_id: Some(self.trades.len() as u64 + 1), price, qty, commission: fee, commission_asset: quote.to_string() };
+        let trade = AccountTrade {
+            id: self.trades.len() as u64 + 1,
+            symbol: symbol.to_string(),
+            side,
+            price,
+            qty,
+            commission: fee,
+            commission_asset: quote.to_string(),
+            time: chrono::Utc::now().timestamp_millis() as u64,
+        };
+        self.trades.push(trade);
+        Ok(fill)
+    }
+
+    /// Guarda la orden como cerrada y la devuelve
+    fn close(&mut self, ack: OrderAck) -> OrderAck {
+        self.closed_orders.insert(ack.order_id, ack.clone());
+        ack
+    }
+
+    fn check_funds(&mut self, symbol: &str, side: OrderSide, qty: f64, price: f64, fee_rate: f64) -> GatewayResult<(String, f64)> {
+        let (asset, amount) = Self::required(symbol, side, qty, price, fee_rate)?;
+        let free = self.balance(&asset).free;
+        if free < amount {
+            return Err(format!("Saldo insuficiente de {}: {:.8} < {:.8}", asset, free, amount).into());
+        }
+        Ok((asset, amount))
+    }
+}
+
+impl ExchangeGateway for PaperGateway {
+    async fn market_order(&self, symbol: &str, side: OrderSide, qty: f64) -> GatewayResult<OrderAck> {
+        let mut state = self.state.lock().unwrap();
+        let last = *state.last_prices.get(symbol).ok_or("Paper: todavía no hay trades para este símbolo")?;
+        let price = match side {
+            OrderSide::Buy => last * (1.0 + self.slippage),
+            OrderSide::Sell => last * (1.0 - self.slippage),
+        };
+        let qty = self.apply_filters(symbol, qty, last, true)?;
+
+        state.check_funds(symbol, side, qty, price, self.fee_rate)?;
+        let fill = state.settle(symbol, side, qty, price, self.fee_rate)?;
+        let order_id = state.next_id();
+        Ok(state.close(Self::ack(order_id, symbol, side, OrderStatus::Filled, vec![fill])))
+    }
+
+    async fn limit_order(&self, symbol: &str, side: OrderSide, qty: f64, price: f64) -> GatewayResult<OrderAck> {
+        let price = self.filters.get(symbol).map(|f| f.normalize_price(price)).unwrap_or(price);
+        let qty = self.apply_filters(symbol, qty, price, false)?;
+        let mut state = self.state.lock().unwrap();
+        let (asset, amount) = state.check_funds(symbol, side, qty, price, self.fee_rate)?;
+        let order_id = state.next_id();
+
+        // Una limit que ya cruza el último trade se ejecuta al momento a su precio
+        let marketable = state.last_prices.get(symbol).is_some_and(|&last| match side {
+            OrderSide::Buy => last <= price,
+            OrderSide::Sell => last >= price,
+        });
+        if marketable {
+            let fill = state.settle(symbol, side, qty, price, self.fee_rate)?;
+            return Ok(state.close(Self::ack(order_id, symbol, side, OrderStatus::Filled, vec![fill])));
+        }
+
+        let balance = state.balance(&asset);
+        balance.free -= amount;
+        balance.locked += amount;
+        state.open_orders.push(RestingOrder { order_id, symbol: symbol.to_string(), side, qty, price, stop_price: None });
+        Ok(Self::ack(order_id, symbol, side, OrderStatus::New, Vec::new()))
+    }
+
+    async fn limit_maker(&self, symbol: &str, side: OrderSide, qty: f64, price: f64) -> GatewayResult<OrderAck> {
+        let price = self.filters.get(symbol).map(|f| f.normalize_price(price)).unwrap_or(price);
+        let qty = self.apply_filters(symbol, qty, price, false)?;
+        let mut state = self.state.lock().unwrap();
+        // Como en Binance: si cruza el último trade se rechaza en lugar de ejecutarse como taker
+        let crosses = state.last_prices.get(symbol).is_some_and(|&last| match side {
+            OrderSide::Buy => last <= price,
+            OrderSide::Sell => last >= price,
+        });
+        if crosses {
+            return Err(format!("LIMIT_MAKER {:?} @ {} se ejecutaría como taker", side, price).into());
+        }
+        let (asset, amount) = state.check_funds(symbol, side, qty, price, self.fee_rate)?;
+        let order_id = state.next_id();
+
+        let balance = state.balance(&asset);
+        balance.free -= amount;
+        balance.locked += amount;
+        state.open_orders.push(RestingOrder { order_id, symbol: symbol.to_string(), side, qty, price, stop_price: None });
+        Ok(Self::ack(order_id, symbol, side, OrderStatus::New, Vec::new()))
+    }
+
+    async fn stop_loss_limit(&self, symbol: &str, qty: f64, stop_price: f64, limit_price: f64) -> GatewayResult<OrderAck> {
+        let (stop_price, limit_price) = match self.filters.get(symbol) {
+            Some(f) => (f.normalize_price(stop_price), f.normalize_price(limit_price)),
+            None => (stop_price, limit_price),
+        };
+        let qty = self.apply_filters(symbol, qty, limit_price, false)?;
+        let mut state = self.state.lock().unwrap();
+        let (asset, amount) = state.check_funds(symbol, OrderSide::Sell, qty, limit_price, self.fee_rate)?;
+        let order_id = state.next_id();
+
+        let balance = state.balance(&asset);
+        balance.free -= amount;
+        balance.locked += amount;
+        state.open_orders.push(RestingOrder { order_id, symbol: symbol.to_string(), side: OrderSide::Sell, qty, price: limit_price, stop_price: Some(stop_price) });
+        Ok(Self::ack(order_id, symbol, OrderSide::Sell, OrderStatus::New, Vec::new()))
+    }
+
+    async fn cancel_order(&self, symbol: &str, order_id: u64) -> GatewayResult<()> {
+        let mut state = self.state.lock().unwrap();
+        let idx = state.open_orders.iter().position(|o| o.order_id == order_id && o.symbol == symbol)
+            .ok_or_else(|| format!("Orden {} no encontrada", order_id))?;
+        let order = state.open_orders.remove(idx);
+
+        let (asset, amount) = PaperState::required(&order.symbol, order.side, order.qty, order.price, self.fee_rate)?;
+        let balance = state.balance(&asset);
+        balance.locked -= amount;
+        balance.free += amount;
+        state.close(Self::ack(order.order_id, &order.symbol, order.side, OrderStatus::Canceled, Vec::new()));
+        Ok(())
+    }
+
+    async fn balances(&self) -> GatewayResult<Vec<AssetBalance>> {
+        Ok(self.state.lock().unwrap().balances.values().cloned().collect())
+    }
+
+    async fn open_orders(&self, symbol: &str) -> GatewayResult<Vec<OrderAck>> {
+        let state = self.state.lock().unwrap();
+        Ok(state.open_orders.iter()
+            .filter(|o| o.symbol == symbol)
+            .map(|o| Self::ack(o.order_id, &o.symbol, o.side, OrderStatus::New, Vec::new()))
+            .collect())
+    }
+
+    async fn order_status(&self, symbol: &str, order_id: u64) -> GatewayResult<OrderAck> {
+        let state = self.state.lock().unwrap();
+        if let Some(o) = state.open_orders.iter().find(|o| o.order_id == order_id && o.symbol == symbol) {
+            return Ok(Self::ack(o.order_id, &o.symbol, o.side, OrderStatus::New, Vec::new()));
+        }
+        state.closed_orders.get(&order_id).filter(|o| o.symbol == symbol).cloned().ok_or_else(|| format!("Orden {} no encontrada", order_id).into())
+    }
+
+    async fn trade_history(&self, symbol: &str) -> GatewayResult<Vec<AccountTrade>> {
+        Ok(self.state.lock().unwrap().trades.iter().filter(|t| t.symbol == symbol).cloned().collect())
+    }
+
+    async fn latest_price(&self, symbol: &str) -> GatewayResult<f64> {
+        let state = self.state.lock().unwrap();
+        state.last_prices.get(symbol).copied().ok_or_else(|| "Paper: todavía no hay trades para este símbolo".into())
+    }
+
+    fn symbol_filters(&self, symbol: &str) -> Option<SymbolFilters> {
+        self.filters.get(symbol).cloned()
+    }
+
+    fn fee_rates(&self) -> Option<(f64, f64)> {
+        Some((self.maker_fee_rate, self.fee_rate))
+    }
+
+    fn on_market_trade(&self, symbol: &str, price: f64) -> Vec<OrderAck> {
+        let mut state = self.state.lock().unwrap();
+        state.last_prices.insert(symbol.to_string(), price);
+
+        // Los stops alcanzados pasan a ser limit y se evalúan con este mismo trade
+        let mut triggered = Vec::new();
+        for order in state.open_orders.iter_mut().filter(|o| o.symbol == symbol) {
+            let hit = order.stop_price.is_some_and(|stop| match order.side {
+                OrderSide::Buy => price >= stop,
+                OrderSide::Sell => price <= stop,
+            });
+            if hit {
+                order.stop_price = None;
+                triggered.push(order.order_id);
+            }
+        }
+
+        let (crossed, resting): (Vec<_>, Vec<_>) = std::mem::take(&mut state.open_orders).into_iter().partition(|o| {
+            o.symbol == symbol && o.stop_price.is_none() && match o.side {
+                OrderSide::Buy => price <= o.price,
+                OrderSide::Sell => price >= o.price,
+            }
+        });
+        state.open_orders = resting;
+
+        let mut filled = Vec::new();
+        for order in crossed {
+            if let Ok((asset, amount)) = PaperState::required(&order.symbol, order.side, order.qty, order.price, self.fee_rate) {
+                let balance = state.balance(&asset);
+                balance.locked -= amount;
+                balance.free += amount;
+                // Un stop recién disparado cruza como taker al precio del trade; una limit en reposo, a su precio
+                let (fill_price, fee_rate) = if triggered.contains(&order.order_id) { (price, self.fee_rate) } else { (order.price, self.maker_fee_rate) };
+                if let Ok(fill) = state.settle(&order.symbol, order.side, order.qty, fill_price, fee_rate) {
+                    filled.push(state.close(Self::ack(order.order_id, &order.symbol, order.side, OrderStatus::Filled, vec![fill])));
+                }
+            }
+        }
+        filled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gateway() -> PaperGateway {
+        PaperGateway::new(&[("USDT", 1_000.0)], 0.001, 0.0).with_maker_fee(0.0002)
+    }
+
+    #[tokio::test]
+    async fn resting_limit_pays_maker_fee() {
+        let gw = gateway();
+        gw.on_market_trade("BTCUSDT", 100.0);
+        let ack = gw.limit_maker("BTCUSDT", OrderSide::Buy, 1.0, 99.0).await.unwrap();
+        assert_eq!(ack.status, OrderStatus::New);
+
+        let filled = gw.on_market_trade("BTCUSDT", 98.5);
+        assert_eq!(filled.len(), 1);
+        assert!((filled[0].fills[0].commission - 99.0 * 0.0002).abs() < 1e-12);
+        assert_eq!(gw.fee_rates(), Some((0.0002, 0.001)));
+    }
+
+    #[tokio::test]
+    async fn market_and_triggered_stop_pay_taker_fee() {
+        let gw = gateway();
+        gw.on_market_trade("BTCUSDT", 100.0);
+        let entry = gw.market_order("BTCUSDT", OrderSide::Buy, 1.0).await.unwrap();
+        assert!((entry.fills[0].commission - 100.0 * 0.001).abs() < 1e-12);
+
+        gw.stop_loss_limit("BTCUSDT", 1.0, 95.0, 94.0).await.unwrap();
+        let filled = gw.on_market_trade("BTCUSDT", 94.5);
+        assert_eq!(filled.len(), 1);
+        assert!((filled[0].fills[0].commission - 94.5 * 0.001).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,277 @@
+use super::GatewayResult;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Filtros de `/api/v3/exchangeInfo` que afectan a una orden de spot:
+/// LOT_SIZE (cantidad), MARKET_LOT_SIZE (cantidad de las market, además de LOT_SIZE),
+/// PRICE_FILTER (precio) y MIN_NOTIONAL / NOTIONAL (importe).
+/// Un paso o tick de 0 significa que el filtro no aplica.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolFilters {
+    pub symbol: String,
+    pub min_qty: f64,
+    pub max_qty: f64,
+    pub step_size: f64,
+    /// MARKET_LOT_SIZE: Binance suele publicar un paso de 0 (rige el de LOT_SIZE)
+    /// y un maxQty más bajo que el de LOT_SIZE según la liquidez del par
+    pub market_min_qty: f64,
+    pub market_max_qty: f64,
+    pub market_step_size: f64,
+    pub min_price: f64,
+    pub max_price: f64,
+    pub tick_size: f64,
+    pub min_notional: f64,
+    /// Si el mínimo de importe también se exige a las órdenes market
+    pub min_notional_market: bool,
+    qty_decimals: u32,
+    market_qty_decimals: u32,
+    price_decimals: u32,
+}
+
+impl SymbolFilters {
+    /// Parsea la entrada de un símbolo (`symbols[i]` de exchangeInfo)
+    pub fn from_exchange_info(info: &Value) -> Option<Self> {
+        let symbol = info["symbol"].as_str()?.to_string();
+        let mut filters = Self {
+            symbol,
+            min_qty: 0.0,
+            max_qty: f64::MAX,
+            step_size: 0.0,
+            market_min_qty: 0.0,
+            market_max_qty: f64::MAX,
+            market_step_size: 0.0,
+            min_price: 0.0,
+            max_price: f64::MAX,
+            tick_size: 0.0,
+            min_notional: 0.0,
+            min_notional_market: false,
+            qty_decimals: 8,
+            market_qty_decimals: 8,
+            price_decimals: 8,
+        };
+        for filter in info["filters"].as_array()? {
+            let num = |key: &str| filter[key].as_str().and_then(|v| v.parse::<f64>().ok());
+            match filter["filterType"].as_str()? {
+                "LOT_SIZE" => {
+                    filters.min_qty = num("minQty")?;
+                    filters.max_qty = num("maxQty").filter(|q| *q > 0.0).unwrap_or(f64::MAX);
+                    filters.step_size = num("stepSize")?;
+                    filters.qty_decimals = decimals(filter["stepSize"].as_str()?);
+                }
+                "MARKET_LOT_SIZE" => {
+                    filters.market_min_qty = num("minQty")?;
+                    filters.market_max_qty = num("maxQty").filter(|q| *q > 0.0).unwrap_or(f64::MAX);
+                    filters.market_step_size = num("stepSize")?;
+                    filters.market_qty_decimals = decimals(filter["stepSize"].as_str()?);
+                }
+                "PRICE_FILTER" => {
+                    filters.min_price = num("minPrice")?;
+                    filters.max_price = num("maxPrice").filter(|p| *p > 0.0).unwrap_or(f64::MAX);
+                    filters.tick_size = num("tickSize")?;
+                    filters.price_decimals = decimals(filter["tickSize"].as_str()?);
+                }
+                // MIN_NOTIONAL es el filtro antiguo; NOTIONAL lo sustituye en la mayoría de pares
+                "MIN_NOTIONAL" => {
+                    filters.min_notional = num("minNotional")?;
+                    filters.min_notional_market = filter["applyToMarket"].as_bool().unwrap_or(true);
+                }
+                "NOTIONAL" => {
+                    filters.min_notional = num("minNotional")?;
+                    filters.min_notional_market = filter["applyMinToMarket"].as_bool().unwrap_or(true);
+                }
+                _ => {}
+            }
+        }
+        Some(filters)
+    }
+
+    /// Cantidad redondeada hacia abajo al `stepSize` (nunca se pide más de lo calculado)
+    pub fn normalize_qty(&self, qty: f64) -> f64 {
+        round_to(floor_to_step(qty, self.step_size), self.qty_decimals)
+    }
+
+    /// Cantidad de una orden market: al paso de LOT_SIZE y, si lo hay, al de MARKET_LOT_SIZE
+    pub fn normalize_market_qty(&self, qty: f64) -> f64 {
+        let qty = self.normalize_qty(qty);
+        if self.market_step_size <= 0.0 { return qty; }
+        round_to(floor_to_step(qty, self.market_step_size), self.market_qty_decimals.max(self.qty_decimals))
+    }
+
+    /// Precio redondeado al `tickSize` más cercano
+    pub fn normalize_price(&self, price: f64) -> f64 {
+        if self.tick_size <= 0.0 { return price; }
+        round_to((price / self.tick_size).round() * self.tick_size, self.price_decimals)
+    }
+
+    /// Normaliza la cantidad y comprueba que la orden pase los filtros al precio
+    /// indicado (el de referencia en una market). `Err` trae el motivo legible.
+    pub fn validate_order(&self, qty: f64, price: f64, is_market: bool) -> Result<f64, String> {
+        let normalized = if is_market { self.normalize_market_qty(qty) } else { self.normalize_qty(qty) };
+        if normalized <= 0.0 || normalized < self.min_qty {
+            return Err(format!("{} | cantidad {:.8} por debajo de LOT_SIZE minQty {} (step {})", self.symbol, qty, self.min_qty, self.step_size));
+        }
+        if normalized > self.max_qty {
+            return Err(format!("{} | cantidad {:.8} por encima de LOT_SIZE maxQty {}", self.symbol, normalized, self.max_qty));
+        }
+        if is_market && normalized < self.market_min_qty {
+            return Err(format!("{} | cantidad {:.8} por debajo de MARKET_LOT_SIZE minQty {}", self.symbol, normalized, self.market_min_qty));
+        }
+        if is_market && normalized > self.market_max_qty {
+            return Err(format!("{} | cantidad {:.8} por encima de MARKET_LOT_SIZE maxQty {}", self.symbol, normalized, self.market_max_qty));
+        }
+        if !is_market && (price < self.min_price || price > self.max_price) {
+            return Err(format!("{} | precio {:.8} fuera de PRICE_FILTER [{}, {}]", self.symbol, price, self.min_price, self.max_price));
+        }
+        let notional = normalized * price;
+        if (!is_market || self.min_notional_market) && notional < self.min_notional {
+            return Err(format!("{} | importe ${:.2} por debajo de MIN_NOTIONAL ${}", self.symbol, notional, self.min_notional));
+        }
+        Ok(normalized)
+    }
+}
+
+/// Descarga los filtros de los símbolos indicados de una sola vez
+pub async fn fetch_symbol_filters(endpoint: &str, symbols: &[String]) -> GatewayResult<HashMap<String, SymbolFilters>> {
+    let url = format!("{}/api/v3/exchangeInfo", endpoint);
+    let list = serde_json::to_string(symbols)?;
+    let info: Value = reqwest::Client::new().get(url).query(&[("symbols", list)]).send().await?.error_for_status()?.json().await?;
+
+    let mut filters = HashMap::new();
+    for entry in info["symbols"].as_array().ok_or("exchangeInfo sin 'symbols'")? {
+        let parsed = SymbolFilters::from_exchange_info(entry).ok_or_else(|| format!("exchangeInfo: filtros inválidos en {}", entry["symbol"]))?;
+        filters.insert(parsed.symbol.clone(), parsed);
+    }
+    if let Some(missing) = symbols.iter().find(|s| !filters.contains_key(*s)) {
+        return Err(format!("exchangeInfo no devuelve {}", missing).into());
+    }
+    Ok(filters)
+}
+
+fn floor_to_step(value: f64, step: f64) -> f64 {
+    if step <= 0.0 { return value; }
+    // El épsilon evita que 0.3 / 0.1 = 2.9999... pierda un paso entero
+    (value / step + 1e-9).floor() * step
+}
+
+fn round_to(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+/// Decimales significativos de un paso en texto: "0.00001000" → 5
+fn decimals(step: &str) -> u32 {
+    step.split_once('.').map(|(_, frac)| frac.trim_end_matches('0').len() as u32).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Entrada de BTCUSDT tal como la devuelve exchangeInfo (filtros recortados)
+    const BTCUSDT_INFO: &str = r#"{
+        "symbol": "BTCUSDT", "status": "TRADING", "baseAsset": "BTC", "quoteAsset": "USDT",
+        "filters": [
+            {"filterType": "PRICE_FILTER", "minPrice": "0.01000000", "maxPrice": "1000000.00000000", "tickSize": "0.01000000"},
+            {"filterType": "LOT_SIZE", "minQty": "0.00001000", "maxQty": "9000.00000000", "stepSize": "0.00001000"},
+            {"filterType": "ICEBERG_PARTS", "limit": 10},
+            {"filterType": "MARKET_LOT_SIZE", "minQty": "0.00000000", "maxQty": "85.50000000", "stepSize": "0.00000000"},
+            {"filterType": "NOTIONAL", "minNotional": "5.00000000", "applyMinToMarket": true,
+             "maxNotional": "9000000.00000000", "applyMaxToMarket": false, "avgPriceMins": 5}
+        ]
+    }"#;
+
+    fn btcusdt() -> SymbolFilters {
+        SymbolFilters::from_exchange_info(&serde_json::from_str(BTCUSDT_INFO).unwrap()).unwrap()
+    }
+
+    /// Par con el filtro antiguo MIN_NOTIONAL, pasos de 0.1 / 0.001 y paso propio en MARKET_LOT_SIZE
+    fn legacy(apply_to_market: bool) -> SymbolFilters {
+        let info = serde_json::json!({
+            "symbol": "XYZUSDT",
+            "filters": [
+                {"filterType": "PRICE_FILTER", "minPrice": "0.00100000", "maxPrice": "0.00000000", "tickSize": "0.00100000"},
+                {"filterType": "LOT_SIZE", "minQty": "0.10000000", "maxQty": "0.00000000", "stepSize": "0.10000000"},
+                {"filterType": "MARKET_LOT_SIZE", "minQty": "2.00000000", "maxQty": "500.00000000", "stepSize": "1.00000000"},
+                {"filterType": "MIN_NOTIONAL", "minNotional": "10.00000000", "applyToMarket": apply_to_market, "avgPriceMins": 5}
+            ]
+        });
+        SymbolFilters::from_exchange_info(&info).unwrap()
+    }
+
+    #[test]
+    fn parses_exchange_info_filters() {
+        let f = btcusdt();
+        assert_eq!(f.symbol, "BTCUSDT");
+        assert_eq!((f.min_qty, f.max_qty, f.step_size), (0.00001, 9000.0, 0.00001));
+        assert_eq!((f.min_price, f.max_price, f.tick_size), (0.01, 1_000_000.0, 0.01));
+        assert_eq!((f.market_min_qty, f.market_max_qty, f.market_step_size), (0.0, 85.5, 0.0));
+        assert_eq!(f.min_notional, 5.0);
+        assert!(f.min_notional_market);
+
+        let old = legacy(false);
+        assert_eq!(old.min_notional, 10.0);
+        assert!(!old.min_notional_market);
+        // Un máximo de 0 significa sin límite
+        assert_eq!((old.max_qty, old.max_price), (f64::MAX, f64::MAX));
+        assert!(legacy(true).min_notional_market);
+    }
+
+    #[test]
+    fn floors_to_the_step_at_exact_and_near_multiples() {
+        let f = legacy(true);
+        assert_eq!(f.normalize_qty(0.3), 0.3);
+        assert_eq!(f.normalize_qty(0.1 + 0.2), 0.3);
+        assert_eq!(f.normalize_qty(0.39999), 0.3);
+        assert_eq!(f.normalize_qty(2.0), 2.0);
+        assert_eq!(f.normalize_price(1.2344), 1.234);
+        assert_eq!(f.normalize_price(1.2346), 1.235);
+        assert_eq!(f.normalize_price(0.003), 0.003);
+
+        let btc = btcusdt();
+        assert_eq!(btc.normalize_qty(0.001), 0.001);
+        assert_eq!(btc.normalize_qty(0.0012345), 0.00123);
+        assert_eq!(btc.normalize_qty(0.00099999999), 0.00099);
+    }
+
+    #[test]
+    fn decimals_ignore_trailing_zeros() {
+        assert_eq!(decimals("0.00100000"), 3);
+        assert_eq!(decimals("0.00001000"), 5);
+        assert_eq!(decimals("1.00000000"), 0);
+        assert_eq!(decimals("10"), 0);
+    }
+
+    #[test]
+    fn rejects_orders_below_min_notional_with_the_reason() {
+        let f = btcusdt();
+        let err = f.validate_order(0.0001, 40_000.0, true).unwrap_err();
+        assert!(err.contains("MIN_NOTIONAL"), "{}", err);
+        assert!(err.contains("$4.00"), "{}", err);
+        assert_eq!(f.validate_order(0.000135, 40_000.0, true), Ok(0.00013));
+
+        let err = f.validate_order(0.000001, 40_000.0, false).unwrap_err();
+        assert!(err.contains("LOT_SIZE minQty"), "{}", err);
+
+        // Con applyToMarket = false el mínimo solo se exige a las límite
+        assert!(legacy(false).validate_order(2.0, 1.0, true).is_ok());
+        assert!(legacy(false).validate_order(2.0, 1.0, false).is_err());
+        assert!(legacy(true).validate_order(2.0, 1.0, true).is_err());
+    }
+
+    #[test]
+    fn market_orders_honor_market_lot_size() {
+        let f = legacy(false);
+        assert_eq!(f.normalize_market_qty(2.7), 2.0);
+        assert_eq!(f.validate_order(2.7, 10.0, true), Ok(2.0));
+        assert_eq!(f.validate_order(2.7, 10.0, false), Ok(2.7));
+        assert!(f.validate_order(1.5, 100.0, true).unwrap_err().contains("MARKET_LOT_SIZE minQty"));
+        assert!(f.validate_order(1.5, 100.0, false).is_ok());
+        assert!(f.validate_order(600.0, 1.0, true).unwrap_err().contains("MARKET_LOT_SIZE maxQty"));
+
+        // Paso 0 en MARKET_LOT_SIZE: rige el de LOT_SIZE
+        let btc = btcusdt();
+        assert_eq!(btc.normalize_market_qty(0.0012345), 0.00123);
+        assert!(btc.validate_order(90.0, 40_000.0, true).is_err());
+        assert!(btc.validate_order(90.0, 40_000.0, false).is_ok());
+    }
+}
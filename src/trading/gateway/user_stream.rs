@@ -0,0 +1,309 @@
+use super::binance::BinanceNetwork;
+use super::{AssetBalance, Fill, GatewayResult, OrderAck, OrderSide, OrderStatus};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+/// Binance caduca el listenKey a los 60 minutos sin keepalive
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Evento de la cuenta que llega por el user data stream
+#[derive(Debug, Clone)]
+pub enum AccountEvent {
+    Execution(ExecutionReport),
+    /// `outboundAccountPosition`: solo los activos que cambiaron
+    Balances(Vec<AssetBalance>),
+    /// Conectado con un listenKey nuevo: los eventos desde la última conexión se
+    /// perdieron y las órdenes pendientes hay que consultarlas por REST
+    Connected,
+}
+
+/// `executionReport`: cada cambio de estado de una orden propia (nueva, parcial, llena, cancelada...)
+#[derive(Debug, Clone)]
+pub struct ExecutionReport {
+    pub symbol: String,
+    pub order_id: u64,
+    pub side: OrderSide,
+    pub status: OrderStatus,
+    /// `x`: NEW, TRADE, CANCELED, EXPIRED, REJECTED...
+    pub execution_type: String,
+    /// Ejecución de este evento (solo con `execution_type == "TRADE"`)
+    pub fill: Option<Fill>,
+    pub cum_qty: f64,
+    pub cum_quote: f64,
+    pub event_time: u64,
+}
+
+impl ExecutionReport {
+    /// La orden ya no cambiará (llena, cancelada, rechazada o expirada)
+    pub fn is_final(&self) -> bool {
+        !matches!(self.status, OrderStatus::New | OrderStatus::PartiallyFilled)
+    }
+
+    /// Ack final con los acumulados del report y las ejecuciones recibidas por el camino
+    pub fn to_ack(&self, fills: Vec<Fill>) -> OrderAck {
+        OrderAck {
+            order_id: self.order_id,
+            symbol: self.symbol.clone(),
+            side: self.side,
+            status: self.status,
+            executed_qty: self.cum_qty,
+            cum_quote: self.cum_quote,
+            avg_price: if self.cum_qty > 0.0 { self.cum_quote / self.cum_qty } else { 0.0 },
+            fills,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawExecutionReport {
+    #[serde(rename = "E")]
+    event_time: u64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "S")]
+    side: String,
+    #[serde(rename = "x")]
+    execution_type: String,
+    #[serde(rename = "X")]
+    status: String,
+    #[serde(rename = "i")]
+    order_id: u64,
+    #[serde(rename = "l")]
+    last_qty: String,
+    #[serde(rename = "z")]
+    cum_qty: String,
+    #[serde(rename = "L")]
+    last_price: String,
+    #[serde(rename = "n")]
+    commission: String,
+    #[serde(rename = "N")]
+    commission_asset: Option<String>,
+    #[serde(rename = "t")]
+    trade_id: i64,
+    #[serde(rename = "Z")]
+    cum_quote: String,
+}
+
+#[derive(Deserialize)]
+struct RawBalance {
+    #[serde(rename = "a")]
+    asset: String,
+    #[serde(rename = "f")]
+    free: String,
+    #[serde(rename = "l")]
+    locked: String,
+}
+
+#[derive(Deserialize)]
+struct RawAccountPosition {
+    #[serde(rename = "B")]
+    balances: Vec<RawBalance>,
+}
+
+fn parse_event(text: &str) -> Option<AccountEvent> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    let num = |s: &str| s.parse::<f64>().unwrap_or(0.0);
+    match value["e"].as_str()? {
+        "executionReport" => {
+            let raw: RawExecutionReport = serde_json::from_value(value).ok()?;
+            let fill = (raw.execution_type == "TRADE").then(|| Fill {
+                trade_id: u64::try_from(raw.trade_id).ok(),
+                price: num(&raw.last_price),
+                qty: num(&raw.last_qty),
+                commission: num(&raw.commission),
+                commission_asset: raw.commission_asset.clone().unwrap_or_default(),
+            });
+            Some(AccountEvent::Execution(ExecutionReport {
+                side: OrderSide::from_binance(&raw.side)?,
+                status: OrderStatus::from_binance(&raw.status),
+                symbol: raw.symbol,
+                order_id: raw.order_id,
+                execution_type: raw.execution_type,
+                fill,
+                cum_qty: num(&raw.cum_qty),
+                cum_quote: num(&raw.cum_quote),
+                event_time: raw.event_time,
+            }))
+        }
+        "outboundAccountPosition" => {
+            let raw: RawAccountPosition = serde_json::from_value(value).ok()?;
+            Some(AccountEvent::Balances(raw.balances.into_iter().map(|b| AssetBalance {
+                free: num(&b.free),
+                locked: num(&b.locked),
+                asset: b.asset,
+            }).collect()))
+        }
+        _ => None,
+    }
+}
+
+async fn listen_key_request(network: BinanceNetwork, api_key: &str, method: reqwest::Method, listen_key: Option<&str>) -> GatewayResult<serde_json::Value> {
+    let mut url = format!("{}/api/v3/userDataStream", network.config().rest_api_endpoint);
+    if let Some(key) = listen_key {
+        url.push_str(&format!("?listenKey={}", key));
+    }
+    let resp = reqwest::Client::new().request(method, url).header("X-MBX-APIKEY", api_key).send().await?;
+    Ok(resp.error_for_status()?.json().await?)
+}
+
+/// User data stream de Binance: pide un listenKey, lo mantiene vivo cada 30 minutos
+/// y reconecta (con un listenKey nuevo) si el socket cae o Binance lo da por caducado.
+/// Reenvía `executionReport` y `outboundAccountPosition` al loop principal, y
+/// `Connected` en cada conexión para que resincronice las órdenes pendientes.
+pub async fn start_user_stream(network: BinanceNetwork, api_key: String, tx: UnboundedSender<AccountEvent>) {
+    loop {
+        let listen_key = match listen_key_request(network, &api_key, reqwest::Method::POST, None).await {
+            Ok(resp) => match resp["listenKey"].as_str() {
+                Some(key) => key.to_string(),
+                None => { println!("\n❌ User stream: respuesta sin listenKey"); tokio::time::sleep(RECONNECT_DELAY).await; continue; }
+            },
+            Err(e) => { println!("\n❌ User stream: no se pudo crear el listenKey: {}", e); tokio::time::sleep(RECONNECT_DELAY).await; continue; }
+        };
+
+        let url = format!("{}/{}", network.config().ws_endpoint, listen_key);
+        match connect_async(url.as_str()).await {
+            Ok((mut ws_stream, _)) => {
+                println!("\n👤 User data stream conectado");
+                if tx.send(AccountEvent::Connected).is_err() { return; }
+                let mut keepalive = tokio::time::interval_at(tokio::time::Instant::now() + KEEPALIVE_INTERVAL, KEEPALIVE_INTERVAL);
+
+                loop {
+                    tokio::select! {
+                        msg = ws_stream.next() => {
+                            match msg {
+                                Some(Ok(Message::Text(text))) => {
+                                    if text.contains("\"listenKeyExpired\"") {
+                                        println!("\n⚠️ User stream: listenKey caducado, reconectando...");
+                                        break;
+                                    }
+                                    if let Some(event) = parse_event(&text) {
+                                        if tx.send(event).is_err() { return; }
+                                    }
+                                }
+                                Some(Ok(Message::Ping(payload))) => {
+                                    let _ = ws_stream.send(Message::Pong(payload)).await;
+                                }
+                                Some(Err(e)) => {
+                                    println!("\n❌ Error en el user stream: {:?}", e);
+                                    break;
+                                }
+                                None => break,
+                                _ => {}
+                            }
+                        }
+                        _ = keepalive.tick() => {
+                            if let Err(e) = listen_key_request(network, &api_key, reqwest::Method::PUT, Some(&listen_key)).await {
+                                println!("\n❌ User stream: keepalive fallido ({}), reconectando...", e);
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            Err(e) => println!("\n❌ User stream: error de conexión: {:?}. Reintentando...", e),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// executionReport real de Binance (orden LIMIT nueva) con los campos indicados sustituidos
+    fn execution_report(overrides: &[(&str, serde_json::Value)]) -> String {
+        let mut report = json!({
+            "e": "executionReport", "E": 1700000000123u64, "s": "BTCUSDT", "c": "web_abc", "S": "BUY", "o": "LIMIT", "f": "GTC",
+            "q": "0.00200000", "p": "37000.00000000", "P": "0.00000000", "F": "0.00000000", "g": -1, "C": "", "x": "NEW", "X": "NEW",
+            "r": "NONE", "i": 4293153, "l": "0.00000000", "z": "0.00000000", "L": "0.00000000", "n": "0", "N": null,
+            "T": 1700000000120u64, "t": -1, "v": 0, "I": 8641984, "w": true, "m": false, "M": false, "O": 1700000000000u64,
+            "Z": "0.00000000", "Y": "0.00000000", "Q": "0.00000000", "W": 1700000000000u64, "V": "EXPIRE_MAKER"
+        });
+        for (key, value) in overrides {
+            report[*key] = value.clone();
+        }
+        report.to_string()
+    }
+
+    /// Ejecución de `last_qty` (acumulado `cum_qty`) a `price` con la comisión cobrada en `asset`
+    fn trade(status: &str, (last_qty, cum_qty): (&str, &str), price: &str, (commission, asset): (&str, &str), trade_id: i64, cum_quote: &str) -> String {
+        execution_report(&[("x", json!("TRADE")), ("X", json!(status)), ("l", json!(last_qty)), ("z", json!(cum_qty)), ("L", json!(price)),
+            ("n", json!(commission)), ("N", json!(asset)), ("t", json!(trade_id)), ("Z", json!(cum_quote))])
+    }
+
+    fn report(text: &str) -> ExecutionReport {
+        match parse_event(text) {
+            Some(AccountEvent::Execution(report)) => report,
+            other => panic!("no es un executionReport: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn partial_fill_carries_its_fill_and_cumulative_totals() {
+        let r = report(&trade("PARTIALLY_FILLED", ("0.00050000", "0.00050000"), "37000.00000000", ("0.00000050", "BTC"), 28457, "18.50000000"));
+        assert_eq!((r.symbol.as_str(), r.order_id, r.side, r.status), ("BTCUSDT", 4293153, OrderSide::Buy, OrderStatus::PartiallyFilled));
+        assert_eq!(r.event_time, 1700000000123);
+        assert!(!r.is_final());
+        assert_eq!(r.fill, Some(Fill { trade_id: Some(28457), price: 37000.0, qty: 0.0005, commission: 0.0000005, commission_asset: "BTC".to_string() }));
+        assert_eq!((r.cum_qty, r.cum_quote), (0.0005, 18.5));
+    }
+
+    #[test]
+    fn filled_report_builds_the_final_ack() {
+        let partial = report(&trade("PARTIALLY_FILLED", ("0.00050000", "0.00050000"), "37000.00000000", ("0.00000050", "BTC"), 28457, "18.50000000"));
+        let r = report(&trade("FILLED", ("0.00150000", "0.00200000"), "36990.00000000", ("0.00000150", "BTC"), 28458, "73.98500000"));
+        assert!(r.is_final());
+        let ack = r.to_ack(vec![partial.fill.unwrap(), r.fill.clone().unwrap()]);
+        assert_eq!(ack.status, OrderStatus::Filled);
+        assert_eq!(ack.executed_qty, 0.002);
+        assert!((ack.avg_price - 73.985 / 0.002).abs() < 1e-9);
+        assert!((ack.commission_in("BTC") - 0.000002).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bnb_commission_asset_is_kept() {
+        let r = report(&trade("FILLED", ("0.00200000", "0.00200000"), "37000.00000000", ("0.00010123", "BNB"), 28459, "74.00000000"));
+        let fill = r.fill.unwrap();
+        assert_eq!(fill.commission_asset, "BNB");
+        assert_eq!(fill.commission, 0.00010123);
+    }
+
+    #[test]
+    fn canceled_and_expired_orders_are_final_without_a_fill() {
+        let canceled = report(&execution_report(&[("x", json!("CANCELED")), ("X", json!("CANCELED")), ("z", json!("0.00050000")), ("Z", json!("18.50000000"))]));
+        assert_eq!(canceled.status, OrderStatus::Canceled);
+        assert!(canceled.is_final());
+        assert!(canceled.fill.is_none());
+        assert_eq!(canceled.cum_qty, 0.0005);
+
+        let expired = report(&execution_report(&[("x", json!("EXPIRED")), ("X", json!("EXPIRED"))]));
+        assert_eq!(expired.status, OrderStatus::Canceled);
+        assert!(expired.is_final());
+        assert_eq!(expired.to_ack(Vec::new()).avg_price, 0.0);
+
+        let new = report(&execution_report(&[]));
+        assert!(!new.is_final());
+        assert!(new.fill.is_none());
+    }
+
+    #[test]
+    fn account_position_lists_the_changed_balances() {
+        let text = r#"{"e":"outboundAccountPosition","E":1700000000123,"u":1700000000120,
+            "B":[{"a":"BTC","f":"0.00200000","l":"0.00000000"},{"a":"USDT","f":"926.01500000","l":"50.00000000"}]}"#;
+        let Some(AccountEvent::Balances(balances)) = parse_event(text) else { panic!("sin saldos") };
+        assert_eq!(balances.len(), 2);
+        assert_eq!((balances[0].asset.as_str(), balances[0].free, balances[0].locked), ("BTC", 0.002, 0.0));
+        assert_eq!((balances[1].asset.as_str(), balances[1].free, balances[1].locked), ("USDT", 926.015, 50.0));
+    }
+
+    #[test]
+    fn unknown_events_are_ignored() {
+        assert!(parse_event(r#"{"e":"balanceUpdate","E":1,"a":"BNB","d":"0.1","T":1}"#).is_none());
+        assert!(parse_event("no es json").is_none());
+    }
+}
@@ -1,2 +1,10 @@
 pub mod position_manager;
-pub mod executor; // Añade esta línea
\ No newline at end of file
+pub mod gateway;
+pub mod engine;
+pub mod portfolio;
+pub mod state_store;
+pub mod recovery;
+pub mod reconciler;
+pub mod risk_gate;
+pub mod exposure;
+pub mod viability;
@@ -0,0 +1,224 @@
+use crate::brain::NoiseModel;
+use crate::config::Config;
+use crate::data::binance_client::PriceMessage;
+use crate::data::correlation::CorrelatedAsset;
+use crate::data::depth_book::SharedBook;
+use crate::data::macro_filter::MacroFilter;
+use crate::data::order_book::BookUpdate;
+use crate::trading::engine::{execute_intent, resync_pending_orders, Engine, OrderIntent, TradeEvent};
+use crate::trading::gateway::user_stream::AccountEvent;
+use crate::trading::gateway::{AssetBalance, ExchangeGateway, OrderAck};
+use crate::trading::risk_gate::RiskGate;
+use std::collections::HashMap;
+
+/// Un `Engine` por símbolo (buffer, resampler y posición propios) bajo un único
+/// presupuesto de riesgo de cuenta. Las entradas que no caben se recortan o se descartan.
+/// El activo correlacionado se sigue aparte y alimenta las features de todos.
+/// Cada entrada pasa además por el `RiskGate`, que ve todas las ejecuciones.
+pub struct Portfolio {
+    pub engines: Vec<Engine>,
+    pub corr: CorrelatedAsset,
+    pub last_prices: HashMap<String, f64>,
+    /// Riesgo total en USD que pueden sumar las posiciones abiertas
+    pub max_open_risk_usd: f64,
+    /// `max_open_risk_usd` en fracción del capital de riesgo
+    pub max_open_risk: f64,
+    pub max_open_positions: usize,
+    /// Saldos en tiempo real del user data stream (vacío sin stream)
+    pub balances: HashMap<String, AssetBalance>,
+    pub risk_gate: RiskGate,
+    /// El último rechazo por presupuesto ya se notificó; se rearma con la siguiente entrada que quepa
+    budget_exhausted: bool,
+}
+
+impl Portfolio {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            engines: config.market.symbols.iter().map(|symbol| Engine::new(symbol, config)).collect(),
+            corr: CorrelatedAsset::new(&config.market.corr_asset, config.market.candle_interval_ms(), config.market.buffer_limit),
+            last_prices: HashMap::new(),
+            max_open_risk_usd: config.risk.balance_usd * config.risk.max_open_risk,
+            max_open_risk: config.risk.max_open_risk,
+            max_open_positions: config.risk.max_open_positions,
+            balances: HashMap::new(),
+            risk_gate: RiskGate::new(&config.risk_gate),
+            budget_exhausted: false,
+        }
+    }
+
+    /// Símbolos a suscribir: los operados más el correlacionado
+    pub fn stream_symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self.engines.iter().map(|e| e.symbol.clone()).collect();
+        if !symbols.contains(&self.corr.symbol) {
+            symbols.push(self.corr.symbol.clone());
+        }
+        symbols
+    }
+
+    /// Libros L2 de los símbolos operados, para `start_depth_sync`
+    pub fn depth_books(&self) -> Vec<SharedBook> {
+        self.engines.iter().map(|e| e.depth.clone()).collect()
+    }
+
+    pub fn engine(&self, symbol: &str) -> Option<&Engine> {
+        self.engines.iter().find(|e| e.symbol == symbol)
+    }
+
+    fn engine_mut(&mut self, symbol: &str) -> Option<&mut Engine> {
+        self.engines.iter_mut().find(|e| e.symbol == symbol)
+    }
+
+    pub fn set_macro(&mut self, symbol: &str, context: MacroFilter) {
+        if let Some(engine) = self.engine_mut(symbol) {
+            engine.macro_ctx = context;
+        }
+    }
+
+    /// Nuevo capital de riesgo (conciliación con la cuenta): tamaño de cada motor y presupuesto compartido
+    pub fn set_risk_balance(&mut self, balance_usd: f64) {
+        for engine in &mut self.engines {
+            engine.risk_manager.balance_usd = balance_usd;
+        }
+        self.max_open_risk_usd = balance_usd * self.max_open_risk;
+    }
+
+    /// Comisiones que cobra de verdad el gateway (paper y backtest), para la viabilidad
+    pub fn set_fee_tier(&mut self, maker_fee: f64, taker_fee: f64) {
+        for engine in &mut self.engines {
+            engine.viability.maker_fee = maker_fee;
+            engine.viability.taker_fee = taker_fee;
+        }
+    }
+
+    /// Con user data stream, las órdenes sin respuesta REST esperan su executionReport
+    pub fn set_stream_confirmations(&mut self, enabled: bool) {
+        for engine in &mut self.engines {
+            engine.confirm_by_stream = enabled;
+        }
+    }
+
+    pub fn on_account_event(&mut self, event: &AccountEvent) -> Option<TradeEvent> {
+        match event {
+            AccountEvent::Execution(report) => {
+                let event = self.engine_mut(&report.symbol)?.on_execution(report)?;
+                self.risk_gate.on_event(&event);
+                Some(event)
+            }
+            AccountEvent::Balances(balances) => {
+                for balance in balances {
+                    self.balances.insert(balance.asset.clone(), balance.clone());
+                }
+                None
+            }
+            AccountEvent::Connected => None,
+        }
+    }
+
+    /// Consulta por REST las órdenes que esperaban un executionReport perdido en la reconexión
+    pub async fn resync_pending_orders<G: ExchangeGateway>(&mut self, gateway: &G, timestamp: u64) -> Vec<TradeEvent> {
+        let mut events = Vec::new();
+        for engine in &mut self.engines {
+            if let Some(event) = resync_pending_orders(engine, gateway, timestamp).await {
+                self.risk_gate.on_event(&event);
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Orden en reposo llenada por el tape (paper y backtest): entrada maker o stop del exchange
+    pub fn on_order_update(&mut self, ack: &OrderAck, timestamp: u64) -> Option<TradeEvent> {
+        let event = self.engine_mut(&ack.symbol)?.on_resting_fill(timestamp, ack)?;
+        self.risk_gate.on_event(&event);
+        Some(event)
+    }
+
+    /// Órdenes que llevan más de `timeout_ms` sin respuesta REST ni executionReport: se dan por no ejecutadas
+    pub fn expire_unconfirmed(&mut self, now: u64, timeout_ms: u64) {
+        for engine in &mut self.engines {
+            if engine.unconfirmed_since.is_some_and(|since| now.saturating_sub(since) >= timeout_ms) {
+                println!("\n⌛ {} | Sin confirmación de la orden pendiente: se da por no ejecutada", engine.symbol);
+                engine.unconfirmed_since = None;
+                engine.on_order_rejected();
+            }
+        }
+    }
+
+    pub fn on_book(&mut self, update: &BookUpdate) {
+        if let Some(engine) = self.engine_mut(update.symbol()) {
+            engine.book.apply(update);
+        }
+    }
+
+    pub fn open_positions(&self) -> usize {
+        self.engines.iter().filter(|e| e.is_position_open()).count()
+    }
+
+    pub fn open_risk_usd(&self) -> f64 {
+        self.engines.iter().map(|e| e.open_risk_usd()).sum()
+    }
+
+    /// Enruta el tick al motor de su símbolo con el modelo que le toca en `brains`.
+    /// Los símbolos sin motor o sin modelo solo actualizan precios.
+    pub fn on_tick<M: NoiseModel>(&mut self, msg: &PriceMessage, brains: &HashMap<String, M>) -> Option<OrderIntent> {
+        // El motor que emite una compra está plano: el riesgo de los demás es todo el comprometido
+        let open_positions = self.open_positions();
+        let available_risk = self.max_open_risk_usd - self.open_risk_usd();
+        let account_notional: f64 = self.engines.iter().map(|e| e.open_notional_usd()).sum();
+        let symbol_notional = self.engine(&msg.symbol).map_or(0.0, |e| e.open_notional_usd());
+        let max_positions = self.max_open_positions;
+
+        self.last_prices.insert(msg.symbol.clone(), msg.price);
+        if msg.symbol == self.corr.symbol {
+            self.corr.push_trade(msg);
+        }
+        let brain = brains.get(&msg.symbol)?;
+        let engine = self.engines.iter_mut().find(|e| e.symbol == msg.symbol)?;
+        let intent = engine.on_tick(msg, brain, &self.corr)?;
+
+        let OrderIntent::Buy { qty, reference_price, .. } = intent else { return Some(intent) };
+        if open_positions >= max_positions || available_risk <= 0.0 {
+            if !self.budget_exhausted {
+                println!("\n⛔ {} | Entrada descartada: presupuesto de riesgo agotado ({} posiciones abiertas)", engine.symbol, open_positions);
+                self.budget_exhausted = true;
+            }
+            engine.on_order_rejected();
+            return None;
+        }
+        self.budget_exhausted = false;
+
+        let risk = engine.open_risk_usd();
+        let (qty, intent) = if risk > available_risk {
+            let allowed_qty = qty * available_risk / risk;
+            println!("\n✂️ {} | Entrada recortada a {:.5} por el presupuesto de riesgo", engine.symbol, allowed_qty);
+            (allowed_qty, engine.resize_pending_entry(allowed_qty)?)
+        } else {
+            (qty, intent)
+        };
+
+        if self.risk_gate.check_entry(&engine.symbol, qty * reference_price, symbol_notional, account_notional, msg.timestamp).is_err() {
+            engine.on_order_rejected();
+            return None;
+        }
+        Some(intent)
+    }
+
+    /// Cierre de todas las posiciones al último precio visto (Kill-Switch o fin del backtest)
+    pub fn close_all(&mut self) -> Vec<OrderIntent> {
+        let mut intents = Vec::new();
+        for engine in &mut self.engines {
+            if let Some(&price) = self.last_prices.get(&engine.symbol) {
+                intents.extend(engine.request_close(price));
+            }
+        }
+        intents
+    }
+
+    /// Ejecuta la orden con el motor del símbolo correspondiente
+    pub async fn execute<G: ExchangeGateway>(&mut self, gateway: &G, intent: &OrderIntent, timestamp: u64) -> Option<TradeEvent> {
+        let engine = self.engine_mut(intent.symbol())?;
+        let event = execute_intent(engine, gateway, intent, timestamp).await?;
+        self.risk_gate.on_event(&event);
+        Some(event)
+    }
+}
@@ -1,27 +1,97 @@
+use crate::config::{SizingConfig, SizingPolicy};
+use std::collections::VecDeque;
 
 pub struct PositionManager {
     pub balance_usd: f64,
     pub risk_percentage: f64, // Ej: 0.02 para 2%
     pub highest_price: f64,
+    /// Stop loss de la posición viva, fijado al entrar (0.0 sin posición)
+    pub stop_price: f64,
+    /// Distancia del trailing bajo el máximo, en fracción (0.005 = 0.5%)
+    pub trail_distance: f64,
+    pub sizing: SizingConfig,
+    /// PnL (USD) de las últimas operaciones cerradas, para la tasa de acierto y el payoff del Kelly
+    pub results: VecDeque<f64>,
 }
 
 impl PositionManager {
-    pub fn new(balance: f64, risk: f64) -> Self {
-        Self { balance_usd: balance, risk_percentage: risk, highest_price: 0.0, }
+    pub fn new(balance: f64, risk: f64, sizing: &SizingConfig) -> Self {
+        Self {
+            balance_usd: balance,
+            risk_percentage: risk,
+            highest_price: 0.0,
+            stop_price: 0.0,
+            trail_distance: 0.0,
+            sizing: sizing.clone(),
+            results: VecDeque::with_capacity(sizing.kelly_lookback),
+        }
     }
 
-    /// Calcula la cantidad de BTC a comprar basada en la distancia al Stop Loss
-    pub fn calculate_order_size(&self, entry_price: f64, stop_loss: f64) -> f64 {
-        let amount_to_risk = self.balance_usd * self.risk_percentage;
+    /// Calcula la cantidad de BTC a comprar según la política de `sizing`:
+    ///
+    /// - fixed_fractional: arriesga `risk_percentage` del balance hasta el stop.
+    /// - vol_target: nocional cuyo ATR% equivale a `target_vol_pct` del balance.
+    /// - kelly: arriesga `kelly_fraction` del Kelly con la tasa de acierto y el payoff históricos.
+    ///
+    /// `multiplier` (confianza) escala el resultado antes de aplicar los topes de riesgo y nocional.
+    pub fn calculate_order_size(&self, entry_price: f64, stop_loss: f64, atrp: f64, multiplier: f64) -> f64 {
         let risk_per_unit = (entry_price - stop_loss).abs();
+        if risk_per_unit == 0.0 || entry_price <= 0.0 { return 0.0; }
+
+        let s = &self.sizing;
+        let btc_quantity = match s.policy {
+            SizingPolicy::VolTarget if atrp > 0.0 => self.balance_usd * s.target_vol_pct / atrp / entry_price,
+            SizingPolicy::VolTarget => 0.0,
+            SizingPolicy::Kelly => self.balance_usd * self.kelly_risk() / risk_per_unit,
+            SizingPolicy::FixedFractional => self.balance_usd * self.risk_percentage / risk_per_unit,
+        } * multiplier;
+
+        // Topes duros: riesgo hasta el stop y nocional (1.0 = leverage 1x)
+        let max_by_risk = self.balance_usd * s.max_risk_per_trade / risk_per_unit;
+        let max_by_notional = self.balance_usd * s.max_notional / entry_price;
+        btc_quantity.min(max_by_risk).min(max_by_notional).max(0.0)
+    }
+
+    /// Fracción del balance a arriesgar según Kelly: f* = p − (1 − p) / b, por `kelly_fraction`.
+    /// `p` y `b` salen de las mismas operaciones cerradas: `1 − P(ruido)` del modelo no es una
+    /// tasa de acierto calibrada y ya escala el tamaño a través de la confianza (`multiplier`).
+    /// Sin ventaja (f* ≤ 0) no se arriesga nada.
+    pub fn kelly_risk(&self) -> f64 {
+        let p = self.win_rate().unwrap_or(self.sizing.default_win_rate);
+        let b = self.payoff_ratio().unwrap_or(self.sizing.default_payoff_ratio);
+        let kelly = p - (1.0 - p) / b;
+        (kelly * self.sizing.kelly_fraction).max(0.0)
+    }
 
-        if risk_per_unit == 0.0 { return 0.0; }
+    /// Fracción de operaciones recordadas con ganancia; `None` hasta tener `kelly_min_trades`
+    pub fn win_rate(&self) -> Option<f64> {
+        if self.results.len() < self.sizing.kelly_min_trades {
+            return None;
+        }
+        Some(self.results.iter().filter(|&&pnl| pnl > 0.0).count() as f64 / self.results.len() as f64)
+    }
+
+    /// Ganancia media / pérdida media de las operaciones recordadas; `None` hasta
+    /// tener `kelly_min_trades` con ganancias y pérdidas
+    pub fn payoff_ratio(&self) -> Option<f64> {
+        if self.results.len() < self.sizing.kelly_min_trades {
+            return None;
+        }
+        let (wins, losses): (Vec<f64>, Vec<f64>) = self.results.iter().partition(|&&pnl| pnl > 0.0);
+        if wins.is_empty() || losses.is_empty() {
+            return None;
+        }
+        let avg_win = wins.iter().sum::<f64>() / wins.len() as f64;
+        let avg_loss = losses.iter().map(|l| l.abs()).sum::<f64>() / losses.len() as f64;
+        (avg_loss > 0.0).then(|| avg_win / avg_loss)
+    }
 
-        let btc_quantity = amount_to_risk / risk_per_unit;
-        
-        // Limitar para no exceder el balance total (leverage 1x por ahora)
-        let max_possible = self.balance_usd / entry_price;
-        if btc_quantity > max_possible { max_possible } else { btc_quantity }
+    /// Resultado de una salida ejecutada, para la tasa de acierto y el payoff del Kelly
+    pub fn record_result(&mut self, pnl_usd: f64) {
+        if self.results.len() >= self.sizing.kelly_lookback {
+            self.results.pop_front();
+        }
+        self.results.push_back(pnl_usd);
     }
 
     // En src/trading/position_manager.rs
@@ -31,11 +101,131 @@ impl PositionManager {
         }
     }
 
-    pub fn calculate_trailing_stop(&self, trail_percent: f64) -> f64 {
-        self.highest_price * (1.0 - trail_percent)
+    pub fn calculate_trailing_stop(&self) -> f64 {
+        self.highest_price * (1.0 - self.trail_distance)
+    }
+
+    /// Nivel de salida efectivo: el más alto entre el stop loss y el trailing
+    pub fn stop_level(&self) -> f64 {
+        self.stop_price.max(self.calculate_trailing_stop())
+    }
+
+    /// Fija los stops de una posición recién abierta (distancias en fracción del precio)
+    pub fn open_position(&mut self, entry_price: f64, stop_distance: f64, trail_distance: f64) {
+        self.highest_price = 0.0;
+        self.stop_price = entry_price * (1.0 - stop_distance);
+        self.trail_distance = trail_distance;
     }
 
     pub fn reset_position(&mut self) {
         self.highest_price = 0.0;
+        self.stop_price = 0.0;
+        self.trail_distance = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(policy: SizingPolicy) -> PositionManager {
+        let sizing = SizingConfig { policy, ..SizingConfig::default() };
+        PositionManager::new(1000.0, 0.01, &sizing)
     }
-}
\ No newline at end of file
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn fixed_fractional_risks_the_configured_fraction_to_the_stop() {
+        let pm = manager(SizingPolicy::FixedFractional);
+        // $10 de riesgo con $2 por unidad hasta el stop
+        assert!(approx(pm.calculate_order_size(100.0, 98.0, 0.1, 1.0), 5.0));
+        assert!(approx(pm.calculate_order_size(100.0, 98.0, 0.1, 1.5), 7.5));
+    }
+
+    #[test]
+    fn multiplier_never_exceeds_the_risk_cap() {
+        let pm = manager(SizingPolicy::FixedFractional);
+        // 2.5 unidades × 3 arriesgarían $30; el tope es el 2% ($20) → 5 unidades
+        assert!(approx(pm.calculate_order_size(100.0, 96.0, 0.1, 3.0), 5.0));
+    }
+
+    #[test]
+    fn tight_stop_is_capped_by_notional() {
+        let pm = manager(SizingPolicy::FixedFractional);
+        // $10 / $0.5 = 20 unidades = $2000, por encima de 1× el balance
+        assert!(approx(pm.calculate_order_size(100.0, 99.5, 0.1, 1.0), 10.0));
+    }
+
+    #[test]
+    fn vol_target_scales_inversely_with_atr() {
+        let pm = manager(SizingPolicy::VolTarget);
+        // 0.1% del balance / 0.2% de ATR → $500 de nocional
+        assert!(approx(pm.calculate_order_size(100.0, 99.0, 0.2, 1.0), 5.0));
+        // Con ATR 0.05% serían $2000: lo limita el nocional
+        assert!(approx(pm.calculate_order_size(100.0, 99.0, 0.05, 1.0), 10.0));
+        assert_eq!(pm.calculate_order_size(100.0, 99.0, 0.0, 1.0), 0.0);
+    }
+
+    /// Gestor Kelly con `wins` ganancias de `win` y `losses` pérdidas de `loss` recordadas
+    fn kelly_with(wins: usize, win: f64, losses: usize, loss: f64) -> PositionManager {
+        let mut pm = manager(SizingPolicy::Kelly);
+        pm.sizing.kelly_min_trades = wins + losses;
+        for _ in 0..wins { pm.record_result(win); }
+        for _ in 0..losses { pm.record_result(-loss); }
+        pm
+    }
+
+    #[test]
+    fn kelly_fraction_follows_edge_sign() {
+        // p = 0.6, b = 1: f* = 0.6 − 0.4 = 0.2, por un cuarto de Kelly
+        assert!(approx(kelly_with(6, 10.0, 4, 10.0).kelly_risk(), 0.05));
+        assert!(approx(kelly_with(5, 10.0, 5, 10.0).kelly_risk(), 0.0));
+        assert_eq!(kelly_with(4, 10.0, 6, 10.0).kelly_risk(), 0.0);
+        assert_eq!(kelly_with(4, 10.0, 6, 10.0).calculate_order_size(100.0, 90.0, 0.1, 1.0), 0.0);
+    }
+
+    #[test]
+    fn kelly_is_fractional_and_capped() {
+        let mut pm = kelly_with(6, 10.0, 4, 10.0);
+        // 5% del balance hasta un stop de $10 → 5 unidades, tope del 2% → 2
+        assert!(approx(pm.calculate_order_size(100.0, 90.0, 0.1, 1.0), 2.0));
+        pm.sizing.kelly_fraction = 0.05;
+        // 0.2 × 0.05 = 1% → $10 / $10
+        assert!(approx(pm.calculate_order_size(100.0, 90.0, 0.1, 1.0), 1.0));
+        // La confianza del modelo solo escala el resultado
+        assert!(approx(pm.calculate_order_size(100.0, 90.0, 0.1, 0.5), 0.5));
+    }
+
+    #[test]
+    fn kelly_uses_defaults_until_there_are_enough_trades() {
+        let mut pm = manager(SizingPolicy::Kelly);
+        pm.sizing.kelly_min_trades = 2;
+        // Por defecto p = 0.55 y b = 1: f* = 0.1
+        assert_eq!(pm.win_rate(), None);
+        assert!(approx(pm.kelly_risk(), 0.025));
+        pm.record_result(20.0);
+        assert_eq!(pm.win_rate(), None);
+        assert_eq!(pm.payoff_ratio(), None);
+    }
+
+    #[test]
+    fn kelly_uses_historical_win_rate_and_payoff() {
+        let mut pm = manager(SizingPolicy::Kelly);
+        pm.sizing.kelly_min_trades = 2;
+        pm.sizing.kelly_lookback = 2;
+        pm.record_result(20.0);
+        pm.record_result(-10.0);
+        assert!(approx(pm.win_rate().unwrap(), 0.5));
+        assert!(approx(pm.payoff_ratio().unwrap(), 2.0));
+        // p = 0.5, b = 2: f* = 0.5 − 0.5 / 2 = 0.25
+        assert!(approx(pm.kelly_risk(), 0.0625));
+        // La ventana olvida la ganancia: sin ganancias no hay payoff histórico ni ventaja
+        pm.record_result(-5.0);
+        assert_eq!(pm.win_rate(), Some(0.0));
+        assert_eq!(pm.payoff_ratio(), None);
+        assert_eq!(pm.kelly_risk(), 0.0);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::config::{Config, ReconcileConfig};
+use crate::trading::engine::PositionState;
+use crate::trading::gateway::paper::split_symbol;
+use crate::trading::gateway::{ExchangeGateway, GatewayResult, OrderSide};
+use crate::trading::portfolio::Portfolio;
+use std::collections::HashMap;
+
+/// Compara periódicamente la cuenta real con lo que el bot cree tener.
+///
+/// - Equity = moneda de cotización + activos base de los símbolos al último precio.
+///   Una fracción (`capital_fraction`) pasa a ser el capital de riesgo del portfolio.
+/// - Deriva = saldo del activo base - posición del bot - lo que ya había en la cuenta
+///   al arrancar (`Engine::account_baseline`, que fija `trading::recovery`). La posición ya descuenta las comisiones cobradas en el activo base,
+///   así que solo quedan movimientos ajenos al bot.
+/// - Una deriva que cuadra con el neto de las ejecuciones nuevas de `myTrades` puede
+///   ser una orden del bot todavía sin confirmar: no cuenta hasta la siguiente pasada,
+///   donde esas ejecuciones ya no son nuevas.
+/// - Cualquier otra deriva por encima de `max_drift_usd` alerta; `drift_strikes` seguidas
+///   bloquean las entradas del símbolo hasta reiniciar (las salidas siguen).
+pub struct AccountReconciler {
+    pub config: ReconcileConfig,
+    pub quote_asset: String,
+    /// Último equity completo medido
+    pub equity: Option<f64>,
+    last_trade_id: HashMap<String, u64>,
+    strikes: HashMap<String, u32>,
+}
+
+impl AccountReconciler {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            config: config.reconcile.clone(),
+            quote_asset: config.market.quote_asset().to_string(),
+            equity: None,
+            last_trade_id: HashMap::new(),
+            strikes: HashMap::new(),
+        }
+    }
+
+    pub async fn run<G: ExchangeGateway>(&mut self, portfolio: &mut Portfolio, gateway: &G) -> GatewayResult<()> {
+        let balances = gateway.balances().await?;
+        let held = |asset: &str| balances.iter().filter(|b| b.asset == asset).map(|b| b.free + b.locked).sum::<f64>();
+
+        let mut equity = held(&self.quote_asset);
+        let mut equity_complete = true;
+
+        for engine in &mut portfolio.engines {
+            let Some(base) = split_symbol(&engine.symbol).map(|(base, _)| base.to_string()) else { continue };
+            let in_account = held(&base);
+            let price = portfolio.last_prices.get(&engine.symbol).copied();
+            match price {
+                Some(price) => equity += in_account * price,
+                None if in_account > 0.0 => equity_complete = false,
+                None => {}
+            }
+
+            // Ejecuciones nuevas desde la última pasada y su efecto neto sobre el activo base
+            let trades = gateway.trade_history(&engine.symbol).await?;
+            let last_id = self.last_trade_id.get(&engine.symbol).copied();
+            let new_trades: Vec<_> = trades.iter().filter(|t| last_id.is_some_and(|id| t.id > id)).collect();
+            let trades_net: f64 = new_trades.iter().map(|t| {
+                let qty = if t.side == OrderSide::Buy { t.qty } else { -t.qty };
+                if t.commission_asset == base { qty - t.commission } else { qty }
+            }).sum();
+            // Con el historial vacío, la primera ejecución ya cuenta como nueva
+            let newest = trades.iter().map(|t| t.id).max().or(last_id).unwrap_or(0);
+            self.last_trade_id.insert(engine.symbol.clone(), newest);
+
+            let bot_qty = match engine.state {
+                PositionState::Open { qty, .. } | PositionState::PendingExit { qty, .. } => qty,
+                PositionState::Flat | PositionState::PendingEntry { .. } => 0.0,
+            };
+            let baseline = engine.account_baseline;
+
+            let drift = in_account - bot_qty - baseline;
+            let value = price.unwrap_or(engine.entry_price());
+            let drift_usd = drift.abs() * value;
+            let strikes = self.strikes.entry(engine.symbol.clone()).or_insert(0);
+            if drift_usd <= self.config.max_drift_usd {
+                *strikes = 0;
+                continue;
+            }
+            if !new_trades.is_empty() && (drift - trades_net).abs() * value <= self.config.max_drift_usd {
+                println!("\nℹ️ {} | Deriva {:+.5} {} explicada por {} ejecuciones nuevas (neto {:+.5}): se revisa en la siguiente pasada",
+                    engine.symbol, drift, base, new_trades.len(), trades_net);
+                continue;
+            }
+
+            *strikes += 1;
+            println!("\n🚨 {} | Deriva sin explicar: cuenta {:.5} {} vs bot {:.5} + previo {:.5} ({:+.5}, ${:.2}) | {} ejecuciones nuevas (neto {:+.5}) [{}/{}]",
+                engine.symbol, in_account, base, bot_qty, baseline, drift, drift_usd, new_trades.len(), trades_net, strikes, self.config.drift_strikes);
+            if *strikes >= self.config.drift_strikes && engine.halt_reason.is_none() {
+                println!("\n⛔ {} | Entradas bloqueadas hasta revisar la cuenta y reiniciar", engine.symbol);
+                engine.halt_reason = Some(format!("deriva de {:+.5} {} sin explicar", drift, base));
+            }
+        }
+
+        // Sin precio para algún activo en cartera el equity saldría bajo: mejor no tocar el capital
+        if equity_complete {
+            let risk_balance = equity * self.config.capital_fraction;
+            if self.equity.is_none_or(|prev| (prev - equity).abs() / prev > 0.001) {
+                println!("\n💼 Cuenta: equity ${:.2} | Capital de riesgo ${:.2}", equity, risk_balance);
+            }
+            self.equity = Some(equity);
+            portfolio.set_risk_balance(risk_balance);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::gateway::paper::PaperGateway;
+
+    #[tokio::test]
+    async fn drift_matching_new_executions_waits_one_pass() {
+        let config = Config::default();
+        let mut portfolio = Portfolio::new(&config);
+        let mut reconciler = AccountReconciler::new(&config);
+        let gateway = PaperGateway::new(&[("USDT", 1_000.0)], 0.0, 0.0);
+        gateway.on_market_trade("BTCUSDT", 100.0);
+        portfolio.last_prices.insert("BTCUSDT".to_string(), 100.0);
+        reconciler.run(&mut portfolio, &gateway).await.unwrap();
+
+        // Compra que el bot no conoce (o que todavía no ha confirmado)
+        gateway.market_order("BTCUSDT", OrderSide::Buy, 0.1).await.unwrap();
+        reconciler.run(&mut portfolio, &gateway).await.unwrap();
+        assert_eq!(reconciler.strikes.get("BTCUSDT"), Some(&0));
+
+        // Sin ejecuciones nuevas ya no se explica: cuenta hasta bloquear las entradas
+        for strike in 1..=config.reconcile.drift_strikes {
+            assert!(portfolio.engine("BTCUSDT").unwrap().halt_reason.is_none());
+            reconciler.run(&mut portfolio, &gateway).await.unwrap();
+            assert_eq!(reconciler.strikes.get("BTCUSDT"), Some(&strike));
+        }
+        assert!(portfolio.engine("BTCUSDT").unwrap().halt_reason.is_some());
+    }
+
+    #[tokio::test]
+    async fn holdings_from_before_the_bot_are_not_drift() {
+        let config = Config::default();
+        let mut portfolio = Portfolio::new(&config);
+        let mut reconciler = AccountReconciler::new(&config);
+        let gateway = PaperGateway::new(&[("USDT", 1_000.0), ("BTC", 0.5)], 0.0, 0.0);
+        portfolio.last_prices.insert("BTCUSDT".to_string(), 100.0);
+        crate::trading::recovery::reconcile(&mut portfolio, &gateway, &[], 0.01).await.unwrap();
+        assert_eq!(portfolio.engine("BTCUSDT").unwrap().account_baseline, 0.5);
+
+        for _ in 0..=config.reconcile.drift_strikes {
+            reconciler.run(&mut portfolio, &gateway).await.unwrap();
+        }
+        assert_eq!(reconciler.strikes.get("BTCUSDT"), Some(&0));
+        assert!(portfolio.engine("BTCUSDT").unwrap().halt_reason.is_none());
+        assert_eq!(reconciler.equity, Some(1_050.0));
+    }
+}
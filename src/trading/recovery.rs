@@ -0,0 +1,137 @@
+use crate::trading::engine::PositionState;
+use crate::trading::gateway::paper::split_symbol;
+use crate::trading::gateway::{ExchangeGateway, GatewayResult};
+use crate::trading::portfolio::Portfolio;
+use crate::trading::state_store::{PersistedPosition, PositionPhase};
+
+/// Cuadra el estado guardado con la cuenta real antes de volver a operar.
+/// El exchange manda: la cantidad restaurada nunca supera lo que hay en la cuenta.
+///
+/// Del saldo del activo base se descuenta lo que ya era ajeno al bot en el arranque anterior
+/// (`account_baseline`; sin él, estado de una versión anterior, se cuenta todo):
+/// un saldo previo no puede convertir una compra nunca llenada en posición abierta
+/// ni esconder una posición cerrada fuera del bot.
+///
+/// - Disponible ≥ cantidad guardada × (1 - `qty_tolerance`) → se retoma entera.
+/// - Disponible parcial → se retoma con lo que hay.
+/// - Disponible por debajo de `qty_tolerance` × cantidad → se da por cerrada (o nunca llenada).
+/// - El stop del exchange guardado que sigue abierto se retoma con la posición.
+/// - Cualquier otra orden abierta en el exchange → el símbolo no abre posiciones nuevas hasta revisarlas.
+///
+/// Si la cuenta no responde se devuelve el error: no se opera a ciegas.
+pub async fn reconcile<G: ExchangeGateway>(portfolio: &mut Portfolio, gateway: &G, saved: &[PersistedPosition], qty_tolerance: f64) -> GatewayResult<()> {
+    let balances = gateway.balances().await?;
+    let held = |asset: &str| balances.iter().filter(|b| b.asset == asset).map(|b| b.free + b.locked).sum::<f64>();
+
+    for position in saved.iter().filter(|p| portfolio.engine(&p.symbol).is_none()) {
+        println!("⚠️ {} | Posición guardada de {:.5} para un símbolo que ya no se opera: revísala a mano", position.symbol, position.qty);
+    }
+
+    for engine in &mut portfolio.engines {
+        let Some(base) = split_symbol(&engine.symbol).map(|(base, _)| base.to_string()) else { continue };
+        let in_account = held(&base);
+        let position = saved.iter().find(|p| p.symbol == engine.symbol);
+        let baseline = position.map_or(in_account, |p| p.account_baseline.unwrap_or(0.0));
+        let available = (in_account - baseline).max(0.0);
+
+        match position {
+            Some(p) if available >= p.qty * (1.0 - qty_tolerance) => {
+                engine.restore_position(p.qty, p.entry_price, p.highest_price, p.entry_fees, p.stop_price, p.trail_distance);
+                println!("♻️ {} | Posición restaurada: {:.5} @ ${:.2} (máx. ${:.2}, stop ${:.2})", engine.symbol, p.qty, p.entry_price, p.highest_price, engine.risk_manager.stop_price);
+            }
+            Some(p) if available > p.qty * qty_tolerance => {
+                engine.restore_position(available, p.entry_price, p.highest_price, p.entry_fees * available / p.qty, p.stop_price, p.trail_distance);
+                println!("⚠️ {} | Guardado {:.5} ({:?}) pero la cuenta tiene {:.5} {} del bot: se retoma lo que hay", engine.symbol, p.qty, p.phase, available, base);
+            }
+            Some(p) => {
+                let detail = match p.phase {
+                    PositionPhase::PendingEntry => "la compra pendiente no se llenó",
+                    PositionPhase::PendingExit => "la venta pendiente se llenó",
+                    PositionPhase::Open => "se cerró fuera del bot",
+                };
+                println!("⚠️ {} | Guardado {:.5} pero la cuenta tiene {:.5} {} del bot: {}. Quedamos planos", engine.symbol, p.qty, available, base, detail);
+            }
+            None if in_account > 0.0 => {
+                println!("ℹ️ {} | {:.5} {} en la cuenta sin posición guardada: el bot no los gestiona", engine.symbol, in_account, base);
+            }
+            None => {}
+        }
+        // Todo lo que no retoma el bot queda como saldo ajeno para el próximo arranque
+        let bot_qty = match engine.state {
+            PositionState::Open { qty, .. } => qty,
+            _ => 0.0,
+        };
+        engine.account_baseline = in_account - bot_qty;
+
+        let mut orders = gateway.open_orders(&engine.symbol).await?;
+        let protective = position.and_then(|p| p.protective_order.clone());
+        if let Some(stop) = protective.filter(|stop| engine.is_position_open() && orders.iter().any(|o| o.order_id == stop.order_id)) {
+            println!("🛡️ {} | Stop del exchange retomado: orden {} (stop ${:.2})", engine.symbol, stop.order_id, stop.stop_price);
+            orders.retain(|o| o.order_id != stop.order_id);
+            engine.protective_order = Some(stop);
+        }
+        if !orders.is_empty() {
+            let ids: Vec<String> = orders.iter().map(|o| format!("{} {:?}", o.order_id, o.side)).collect();
+            println!("⛔ {} | Órdenes abiertas en el exchange [{}]: sin entradas nuevas hasta cancelarlas y reiniciar", engine.symbol, ids.join(", "));
+            engine.halt_reason = Some(format!("{} órdenes abiertas sin conciliar", orders.len()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::trading::gateway::paper::PaperGateway;
+
+    fn saved(phase: PositionPhase, qty: f64, account_baseline: Option<f64>) -> PersistedPosition {
+        PersistedPosition { symbol: "BTCUSDT".to_string(), phase, qty, entry_price: 100.0, highest_price: 100.0, entry_fees: 0.1,
+            stop_price: 99.0, trail_distance: 0.01, protective_order: None, account_baseline }
+    }
+
+    async fn recover(btc_in_account: f64, position: PersistedPosition) -> Portfolio {
+        let mut portfolio = Portfolio::new(&Config::default());
+        let gateway = PaperGateway::new(&[("USDT", 1_000.0), ("BTC", btc_in_account)], 0.0, 0.0);
+        reconcile(&mut portfolio, &gateway, &[position], 0.01).await.unwrap();
+        portfolio
+    }
+
+    fn state(portfolio: &Portfolio) -> PositionState {
+        portfolio.engine("BTCUSDT").unwrap().state.clone()
+    }
+
+    #[tokio::test]
+    async fn unfilled_entry_is_not_revived_by_previous_holdings() {
+        let portfolio = recover(0.5, saved(PositionPhase::PendingEntry, 0.1, Some(0.5))).await;
+        assert!(matches!(state(&portfolio), PositionState::Flat));
+        assert_eq!(portfolio.engine("BTCUSDT").unwrap().account_baseline, 0.5);
+    }
+
+    #[tokio::test]
+    async fn position_closed_outside_the_bot_is_detected() {
+        let portfolio = recover(0.5, saved(PositionPhase::Open, 0.1, Some(0.5))).await;
+        assert!(matches!(state(&portfolio), PositionState::Flat));
+    }
+
+    #[tokio::test]
+    async fn position_on_top_of_holdings_is_restored() {
+        let portfolio = recover(0.6, saved(PositionPhase::Open, 0.1, Some(0.5))).await;
+        let PositionState::Open { qty, .. } = state(&portfolio) else { panic!("posición no restaurada") };
+        assert!((qty - 0.1).abs() < 1e-9);
+        assert!((portfolio.engine("BTCUSDT").unwrap().account_baseline - 0.5).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn partial_balance_restores_what_belongs_to_the_bot() {
+        let portfolio = recover(0.55, saved(PositionPhase::Open, 0.1, Some(0.5))).await;
+        let PositionState::Open { qty, .. } = state(&portfolio) else { panic!("posición no restaurada") };
+        assert!((qty - 0.05).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn legacy_state_without_baseline_counts_the_whole_balance() {
+        let portfolio = recover(0.1, saved(PositionPhase::Open, 0.1, None)).await;
+        assert!(matches!(state(&portfolio), PositionState::Open { .. }));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::config::RiskGateConfig;
+use crate::trading::engine::TradeEvent;
+use std::collections::{HashMap, VecDeque};
+
+const DAY_MS: u64 = 86_400_000;
+const HOUR_MS: u64 = 3_600_000;
+
+/// Salidas que cuentan como stop-out para el enfriamiento del símbolo
+const STOP_REASONS: [&str; 2] = ["STOP LOSS", "STOP EXCHANGE"];
+
+/// Control de riesgo previo a cada entrada, por encima del tamaño que calcula
+/// `PositionManager` y del presupuesto de riesgo del portfolio:
+///
+/// - Pérdida realizada del día (UTC) ≥ `max_daily_loss_usd` → sin entradas hasta el día siguiente.
+/// - `max_consecutive_losses` pérdidas seguidas → igual, hasta el día siguiente.
+/// - Más de `max_trades_per_hour` entradas en la última hora → se bloquea la entrada.
+/// - Nocional del símbolo o de la cuenta por encima de su máximo → se bloquea la entrada.
+/// - Tras un stop loss, el símbolo no vuelve a entrar en `stop_loss_cooldown_secs`.
+///
+/// Las salidas nunca pasan por aquí. El reloj es el de los ticks, así el backtest
+/// bloquea exactamente lo mismo que el bot en vivo.
+pub struct RiskGate {
+    pub config: RiskGateConfig,
+    /// Día UTC (ms / 86.400.000) de los contadores diarios
+    day: Option<u64>,
+    pub daily_pnl: f64,
+    pub consecutive_losses: u32,
+    /// Motivo del bloqueo de todas las entradas hasta el cambio de día
+    pub halt_reason: Option<String>,
+    entries: VecDeque<u64>,
+    last_stop: HashMap<String, u64>,
+    /// Último motivo de bloqueo notificado, para no repetirlo en cada vela
+    last_block: Option<String>,
+}
+
+impl RiskGate {
+    pub fn new(config: &RiskGateConfig) -> Self {
+        Self {
+            config: config.clone(),
+            day: None,
+            daily_pnl: 0.0,
+            consecutive_losses: 0,
+            halt_reason: None,
+            entries: VecDeque::new(),
+            last_stop: HashMap::new(),
+            last_block: None,
+        }
+    }
+
+    /// Reinicia los contadores diarios al cambiar el día UTC
+    fn roll_day(&mut self, now: u64) {
+        let day = now / DAY_MS;
+        if self.day.is_some_and(|d| d == day) {
+            return;
+        }
+        if self.day.is_some() && self.halt_reason.is_some() {
+            println!("\n🗓️ Nuevo día UTC: límites diarios reiniciados, se reanudan las entradas");
+        }
+        self.day = Some(day);
+        self.daily_pnl = 0.0;
+        self.consecutive_losses = 0;
+        self.halt_reason = None;
+    }
+
+    /// Actualiza los contadores con cada entrada y salida ejecutada. La pérdida del
+    /// día suma cada salida; la racha cuenta posiciones cerradas, no salidas parciales.
+    pub fn on_event(&mut self, event: &TradeEvent) {
+        match event {
+            TradeEvent::Entry { timestamp, .. } => {
+                self.roll_day(*timestamp);
+                self.entries.push_back(*timestamp);
+            }
+            TradeEvent::Exit { symbol, timestamp, reason, pnl_usd, position_pnl_usd, .. } => {
+                self.roll_day(*timestamp);
+                self.daily_pnl += pnl_usd;
+                if let Some(position_pnl) = position_pnl_usd {
+                    self.consecutive_losses = if *position_pnl < 0.0 { self.consecutive_losses + 1 } else { 0 };
+                }
+                if STOP_REASONS.contains(reason) {
+                    self.last_stop.insert(symbol.clone(), *timestamp);
+                }
+                if !self.config.enabled || self.halt_reason.is_some() {
+                    return;
+                }
+
+                let c = &self.config;
+                let halt = if -self.daily_pnl >= c.max_daily_loss_usd {
+                    Some(format!("pérdida del día ${:.2} (máximo ${:.2})", -self.daily_pnl, c.max_daily_loss_usd))
+                } else if self.consecutive_losses >= c.max_consecutive_losses {
+                    Some(format!("{} pérdidas seguidas", self.consecutive_losses))
+                } else {
+                    None
+                };
+                if let Some(reason) = halt {
+                    println!("\n🛑 GATE DE RIESGO | Entradas detenidas hasta el próximo día UTC: {}", reason);
+                    self.halt_reason = Some(reason);
+                }
+            }
+        }
+    }
+
+    /// Comprueba una entrada de `notional` USD en `symbol`. `symbol_notional` y
+    /// `account_notional` son los nocionales ya comprometidos (sin esta orden).
+    /// Cada motivo de bloqueo se notifica una vez, no en cada vela que lo repite.
+    pub fn check_entry(&mut self, symbol: &str, notional: f64, symbol_notional: f64, account_notional: f64, now: u64) -> Result<(), String> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let result = self.evaluate(symbol, notional, symbol_notional, account_notional, now);
+        match &result {
+            Err(reason) if self.last_block.as_ref() != Some(reason) => {
+                println!("\n⛔ {} | Entrada bloqueada por el gate de riesgo: {}", symbol, reason);
+                self.last_block = Some(reason.clone());
+            }
+            Err(_) => {}
+            Ok(()) => self.last_block = None,
+        }
+        result
+    }
+
+    fn evaluate(&mut self, symbol: &str, notional: f64, symbol_notional: f64, account_notional: f64, now: u64) -> Result<(), String> {
+        self.roll_day(now);
+        while self.entries.front().is_some_and(|&t| now.saturating_sub(t) >= HOUR_MS) {
+            self.entries.pop_front();
+        }
+
+        let c = &self.config;
+        if let Some(reason) = &self.halt_reason {
+            return Err(format!("día detenido ({})", reason));
+        }
+        if self.last_stop.get(symbol).is_some_and(|&t| now.saturating_sub(t) < c.stop_loss_cooldown_secs * 1000) {
+            return Err(format!("enfriamiento de {}s tras un stop loss", c.stop_loss_cooldown_secs));
+        }
+        if self.entries.len() >= c.max_trades_per_hour as usize {
+            return Err(format!("{} entradas en la última hora (máximo {})", self.entries.len(), c.max_trades_per_hour));
+        }
+        if symbol_notional + notional > c.max_symbol_notional_usd {
+            return Err(format!("nocional del símbolo por encima de ${:.2}", c.max_symbol_notional_usd));
+        }
+        if account_notional + notional > c.max_account_notional_usd {
+            return Err(format!("nocional de la cuenta por encima de ${:.2} (${:.2} abiertos)", c.max_account_notional_usd, account_notional));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Medianoche UTC de un día cualquiera
+    const DAY0: u64 = 20_000 * DAY_MS;
+
+    fn gate() -> RiskGate {
+        RiskGate::new(&RiskGateConfig {
+            enabled: true,
+            max_daily_loss_usd: 50.0,
+            max_consecutive_losses: 3,
+            max_trades_per_hour: 2,
+            max_symbol_notional_usd: 100.0,
+            max_account_notional_usd: 150.0,
+            stop_loss_cooldown_secs: 60,
+        })
+    }
+
+    fn entry(timestamp: u64) -> TradeEvent {
+        TradeEvent::Entry { symbol: "BTCUSDT".to_string(), timestamp, order_id: 1, price: 100.0, qty: 0.1, fees: 0.0, confidence: 0.5, atrp: 0.1, stop: 99.0 }
+    }
+
+    /// Salida que cierra la posición con `pnl_usd` (sin parciales)
+    fn exit(timestamp: u64, reason: &'static str, pnl_usd: f64) -> TradeEvent {
+        TradeEvent::Exit { symbol: "BTCUSDT".to_string(), timestamp, order_id: 2, reason, entry: 100.0, exit: 100.0, qty: 0.1,
+            pnl_pct: 0.0, fees: 0.0, pnl_usd, position_pnl_usd: Some(pnl_usd) }
+    }
+
+    fn allowed(gate: &mut RiskGate, symbol: &str, now: u64) -> bool {
+        gate.check_entry(symbol, 10.0, 0.0, 0.0, now).is_ok()
+    }
+
+    #[test]
+    fn utc_day_rollover_resets_daily_counters() {
+        let mut g = gate();
+        g.on_event(&exit(DAY0 + 1_000, "TAKE PROFIT", -20.0));
+        g.on_event(&exit(DAY0 + 2_000, "TAKE PROFIT", -10.0));
+        assert_eq!(g.daily_pnl, -30.0);
+        assert_eq!(g.consecutive_losses, 2);
+
+        assert!(allowed(&mut g, "BTCUSDT", DAY0 + DAY_MS - 1));
+        assert_eq!(g.daily_pnl, -30.0);
+        assert!(allowed(&mut g, "BTCUSDT", DAY0 + DAY_MS));
+        assert_eq!(g.daily_pnl, 0.0);
+        assert_eq!(g.consecutive_losses, 0);
+    }
+
+    #[test]
+    fn daily_loss_limit_halts_entries_until_the_next_day() {
+        let mut g = gate();
+        g.on_event(&exit(DAY0 + 1_000, "TAKE PROFIT", -30.0));
+        assert!(allowed(&mut g, "ETHUSDT", DAY0 + 2_000));
+        g.on_event(&exit(DAY0 + 3_000, "TAKE PROFIT", 5.0));
+        g.on_event(&exit(DAY0 + 4_000, "TAKE PROFIT", -25.0));
+        assert!(g.halt_reason.is_some());
+
+        let err = g.check_entry("ETHUSDT", 10.0, 0.0, 0.0, DAY0 + 5_000).unwrap_err();
+        assert!(err.contains("día detenido"));
+        assert!(!allowed(&mut g, "ETHUSDT", DAY0 + DAY_MS - 1));
+        assert!(allowed(&mut g, "ETHUSDT", DAY0 + DAY_MS));
+        assert!(g.halt_reason.is_none());
+    }
+
+    #[test]
+    fn consecutive_losses_halt_until_the_next_day() {
+        let mut g = gate();
+        for i in 1..=3 {
+            g.on_event(&exit(DAY0 + i * 1_000, "TAKE PROFIT", -1.0));
+        }
+        assert!(g.check_entry("ETHUSDT", 10.0, 0.0, 0.0, DAY0 + 10_000).unwrap_err().contains("3 pérdidas seguidas"));
+        assert!(allowed(&mut g, "ETHUSDT", DAY0 + DAY_MS));
+    }
+
+    #[test]
+    fn a_win_resets_the_losing_streak() {
+        let mut g = gate();
+        g.on_event(&exit(DAY0 + 1_000, "TAKE PROFIT", -1.0));
+        g.on_event(&exit(DAY0 + 2_000, "TAKE PROFIT", -1.0));
+        g.on_event(&exit(DAY0 + 3_000, "TAKE PROFIT", 1.0));
+        assert_eq!(g.consecutive_losses, 0);
+        g.on_event(&exit(DAY0 + 4_000, "TAKE PROFIT", -1.0));
+        g.on_event(&exit(DAY0 + 5_000, "TAKE PROFIT", -1.0));
+        assert!(g.halt_reason.is_none());
+        assert!(allowed(&mut g, "ETHUSDT", DAY0 + 6_000));
+
+        // Una salida parcial no cierra la posición y no cuenta para la racha
+        let mut partial = exit(DAY0 + 7_000, "TAKE PROFIT", -1.0);
+        if let TradeEvent::Exit { position_pnl_usd, .. } = &mut partial {
+            *position_pnl_usd = None;
+        }
+        g.on_event(&partial);
+        assert_eq!(g.consecutive_losses, 2);
+        assert!(g.halt_reason.is_none());
+    }
+
+    #[test]
+    fn trades_per_hour_window_expires() {
+        let mut g = gate();
+        g.on_event(&entry(DAY0 + 1_000));
+        g.on_event(&entry(DAY0 + 2_000));
+        let err = g.check_entry("BTCUSDT", 10.0, 0.0, 0.0, DAY0 + 3_000).unwrap_err();
+        assert!(err.contains("2 entradas en la última hora"));
+
+        // La primera entrada sale de la ventana justo una hora después
+        assert!(!allowed(&mut g, "BTCUSDT", DAY0 + 1_000 + HOUR_MS - 1));
+        assert!(allowed(&mut g, "BTCUSDT", DAY0 + 1_000 + HOUR_MS));
+    }
+
+    #[test]
+    fn symbol_and_account_notional_caps() {
+        let mut g = gate();
+        let now = DAY0 + 1_000;
+        assert!(g.check_entry("BTCUSDT", 40.0, 60.0, 60.0, now).is_ok());
+        assert!(g.check_entry("BTCUSDT", 41.0, 60.0, 60.0, now).unwrap_err().contains("nocional del símbolo"));
+        assert!(g.check_entry("BTCUSDT", 40.0, 0.0, 110.0, now).is_ok());
+        assert!(g.check_entry("BTCUSDT", 41.0, 0.0, 110.0, now).unwrap_err().contains("nocional de la cuenta"));
+        // Al cerrar posiciones el nocional comprometido baja y la entrada vuelve a pasar
+        assert!(g.check_entry("BTCUSDT", 41.0, 0.0, 0.0, now).is_ok());
+    }
+
+    #[test]
+    fn stop_loss_cooldown_blocks_only_the_stopped_symbol() {
+        let mut g = gate();
+        g.on_event(&exit(DAY0 + 1_000, "STOP LOSS", 1.0));
+        assert!(g.check_entry("BTCUSDT", 10.0, 0.0, 0.0, DAY0 + 2_000).unwrap_err().contains("enfriamiento"));
+        assert!(allowed(&mut g, "ETHUSDT", DAY0 + 2_000));
+        assert!(!allowed(&mut g, "BTCUSDT", DAY0 + 60_999));
+        assert!(allowed(&mut g, "BTCUSDT", DAY0 + 61_000));
+
+        // Una salida que no es stop no activa el enfriamiento
+        g.on_event(&exit(DAY0 + 62_000, "TAKE PROFIT", 1.0));
+        assert!(allowed(&mut g, "BTCUSDT", DAY0 + 63_000));
+    }
+}
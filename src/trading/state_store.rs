@@ -0,0 +1,136 @@
+use crate::trading::engine::{Engine, PositionState, ProtectiveOrder};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const STATE_VERSION: u32 = 1;
+
+/// Los cambios que solo mueven el máximo del trailing se escriben como mucho una vez por segundo
+const HIGHEST_PRICE_THROTTLE: Duration = Duration::from_secs(1);
+
+/// Fase de la posición en el momento de guardarla
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionPhase {
+    /// Compra enviada sin confirmar: puede haberse llenado o no
+    PendingEntry,
+    Open,
+    /// Venta enviada sin confirmar
+    PendingExit,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersistedPosition {
+    pub symbol: String,
+    pub phase: PositionPhase,
+    pub qty: f64,
+    /// En `PendingEntry` es el precio de referencia de la compra
+    pub entry_price: f64,
+    /// Máximo del trailing stop (`PositionManager::highest_price`)
+    pub highest_price: f64,
+    /// Comisión de entrada en moneda de cotización
+    #[serde(default)]
+    pub entry_fees: f64,
+    /// Stop loss de la posición (`PositionManager::stop_price`); 0.0 en estados anteriores
+    #[serde(default)]
+    pub stop_price: f64,
+    /// Distancia del trailing en fracción del precio; 0.0 en estados anteriores
+    #[serde(default)]
+    pub trail_distance: f64,
+    /// Stop del exchange que cubría la posición
+    #[serde(default)]
+    pub protective_order: Option<ProtectiveOrder>,
+    /// Saldo del activo base ajeno al bot (medido al arrancar); `None` en estados anteriores
+    #[serde(default)]
+    pub account_baseline: Option<f64>,
+}
+
+impl PersistedPosition {
+    pub fn from_engine(engine: &Engine) -> Option<Self> {
+        let (phase, qty, entry_price) = match engine.state {
+            PositionState::Flat => return None,
+            PositionState::PendingEntry { qty, price, .. } => (PositionPhase::PendingEntry, qty, price),
+            PositionState::Open { qty, entry_price } => (PositionPhase::Open, qty, entry_price),
+            PositionState::PendingExit { qty, entry_price, .. } => (PositionPhase::PendingExit, qty, entry_price),
+        };
+        Some(Self { symbol: engine.symbol.clone(), phase, qty, entry_price, highest_price: engine.risk_manager.highest_price, entry_fees: engine.entry_fees,
+            stop_price: engine.risk_manager.stop_price, trail_distance: engine.risk_manager.trail_distance, protective_order: engine.protective_order.clone(),
+            account_baseline: Some(engine.account_baseline) })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub version: u32,
+    /// ms desde epoch
+    pub saved_at: u64,
+    pub positions: Vec<PersistedPosition>,
+}
+
+/// Estado de las posiciones en disco (`<state_dir>/positions_<modo>.json`).
+/// Cada escritura va a un temporal que luego se renombra, así un corte a mitad
+/// nunca deja el archivo a medias.
+pub struct StateStore {
+    pub path: PathBuf,
+    last_saved: Option<Vec<PersistedPosition>>,
+    last_write: Option<Instant>,
+}
+
+impl StateStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), last_saved: None, last_write: None }
+    }
+
+    /// `None` si no hay estado guardado (primer arranque)
+    pub fn load(&self) -> Result<Option<StateSnapshot>, Box<dyn Error>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&self.path)?;
+        let snapshot: StateSnapshot = serde_json::from_str(&content).map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        if snapshot.version != STATE_VERSION {
+            return Err(format!("{}: versión de estado {} (se esperaba {})", self.path.display(), snapshot.version, STATE_VERSION).into());
+        }
+        Ok(Some(snapshot))
+    }
+
+    pub fn save(&mut self, positions: Vec<PersistedPosition>) -> Result<(), Box<dyn Error>> {
+        let snapshot = StateSnapshot { version: STATE_VERSION, saved_at: chrono::Utc::now().timestamp_millis() as u64, positions };
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = tmp_path(&self.path);
+        fs::write(&tmp, serde_json::to_string_pretty(&snapshot)?)?;
+        fs::rename(&tmp, &self.path)?;
+
+        self.last_saved = Some(snapshot.positions);
+        self.last_write = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Guarda solo si algo cambió. Un cambio de fase o cantidad se escribe al
+    /// momento; uno que solo sube `highest_price`, con `HIGHEST_PRICE_THROTTLE`.
+    pub fn sync(&mut self, engines: &[Engine]) -> Result<(), Box<dyn Error>> {
+        let positions: Vec<PersistedPosition> = engines.iter().filter_map(PersistedPosition::from_engine).collect();
+        let Some(last) = &self.last_saved else { return self.save(positions) };
+        if *last == positions {
+            return Ok(());
+        }
+
+        let same_positions = last.len() == positions.len()
+            && last.iter().zip(&positions).all(|(a, b)| PersistedPosition { highest_price: b.highest_price, ..a.clone() } == *b);
+        let throttled = self.last_write.is_some_and(|t| t.elapsed() < HIGHEST_PRICE_THROTTLE);
+        if same_positions && throttled {
+            return Ok(());
+        }
+        self.save(positions)
+    }
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
@@ -0,0 +1,110 @@
+use crate::config::{Config, ViabilityConfig};
+
+/// Descuento de Binance spot sobre la comisión al pagarla en BNB
+pub const BNB_FEE_DISCOUNT: f64 = 0.25;
+
+/// Rendimiento esperado de una entrada, en % del nocional
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgeEstimate {
+    /// Esperanza del movimiento: p × R × stop − (1 − p) × stop
+    pub gross_pct: f64,
+    /// Comisiones de entrada y salida, spread y slippage
+    pub cost_pct: f64,
+    pub net_pct: f64,
+}
+
+/// Pilar 12: viabilidad de una entrada frente a sus costes. Combina la
+/// probabilidad del modelo, el stop que implica el ATR, el spread en vivo
+/// y las comisiones de la cuenta (con el descuento BNB si aplica).
+///
+/// La salida se supone siempre taker (stop, trailing o ruido); la entrada es
+/// maker solo si va como LIMIT_MAKER, que además se ahorra medio spread.
+#[derive(Debug, Clone)]
+pub struct ViabilityCheck {
+    pub config: ViabilityConfig,
+    /// Comisiones efectivas en fracción del nocional
+    pub maker_fee: f64,
+    pub taker_fee: f64,
+}
+
+impl ViabilityCheck {
+    pub fn new(config: &Config) -> Self {
+        let discount = if config.exchange.bnb_fee_discount { 1.0 - BNB_FEE_DISCOUNT } else { 1.0 };
+        Self {
+            config: config.viability.clone(),
+            maker_fee: config.exchange.maker_fee * discount,
+            taker_fee: config.exchange.trading_fee * discount,
+        }
+    }
+
+    /// `noise` es la probabilidad de ruido del modelo; `stop_pct` (la distancia al stop
+    /// por ATR) y `spread_pct` van en %. El objetivo es `target_r_multiple` veces el stop,
+    /// así ganancia y pérdida salen del mismo ATR con los mismos límites.
+    pub fn estimate(&self, noise: f64, stop_pct: f64, spread_pct: f64, maker_entry: bool) -> EdgeEstimate {
+        let p = 1.0 - noise;
+        let gross_pct = p * self.config.target_r_multiple * stop_pct - (1.0 - p) * stop_pct;
+
+        let (entry_fee, spread_cost, slippage) = if maker_entry {
+            (self.maker_fee, spread_pct / 2.0, self.config.slippage_pct)
+        } else {
+            (self.taker_fee, spread_pct, self.config.slippage_pct * 2.0)
+        };
+        let cost_pct = (entry_fee + self.taker_fee) * 100.0 + spread_cost + slippage;
+        EdgeEstimate { gross_pct, cost_pct, net_pct: gross_pct - cost_pct }
+    }
+
+    pub fn is_viable(&self, edge: &EdgeEstimate) -> bool {
+        !self.config.enabled || edge.net_pct >= self.config.min_edge_pct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(bnb: bool) -> ViabilityCheck {
+        let mut config = Config::default();
+        config.exchange.bnb_fee_discount = bnb;
+        ViabilityCheck::new(&config)
+    }
+
+    /// Stop de la configuración por defecto con velas de 1s (ATR% ≈ 0.03 → mínimo de 0.3%)
+    fn default_stop_pct() -> f64 {
+        Config::default().risk.stop_distance(0.03) * 100.0
+    }
+
+    #[test]
+    fn realistic_setup_with_default_config_passes() {
+        let v = check(false);
+        let edge = v.estimate(0.20, default_stop_pct(), 0.01, false);
+        // 0.8 × 0.6 − 0.2 × 0.3 = 0.42 bruto; 0.2 comisiones + 0.01 spread + 0.02 slippage
+        assert!((edge.gross_pct - 0.42).abs() < 1e-9);
+        assert!((edge.cost_pct - 0.23).abs() < 1e-9);
+        assert!(v.is_viable(&edge));
+    }
+
+    #[test]
+    fn weak_signal_does_not_cover_round_trip_costs() {
+        let v = check(false);
+        let edge = v.estimate(0.40, default_stop_pct(), 0.01, false);
+        assert!(edge.net_pct < v.config.min_edge_pct);
+        assert!(!v.is_viable(&edge));
+    }
+
+    #[test]
+    fn bnb_discount_and_maker_entry_lower_costs() {
+        let taker = check(false).estimate(0.3, 0.3, 0.02, false);
+        let bnb = check(true).estimate(0.3, 0.3, 0.02, false);
+        let maker = check(false).estimate(0.3, 0.3, 0.02, true);
+        assert!((taker.cost_pct - bnb.cost_pct - 0.05).abs() < 1e-9);
+        assert!(maker.cost_pct < taker.cost_pct);
+        assert_eq!(taker.gross_pct, maker.gross_pct);
+    }
+
+    #[test]
+    fn disabled_check_accepts_everything() {
+        let mut v = check(false);
+        v.config.enabled = false;
+        assert!(v.is_viable(&v.estimate(0.9, 0.3, 0.5, false)));
+    }
+}
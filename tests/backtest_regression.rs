@@ -0,0 +1,62 @@
+//! Regresión del backtest completo sobre un tape fijo (`tests/fixtures/agg_trades.jsonl`:
+//! 10 minutos sintéticos de aggTrades de BTCUSDT y ETHUSDT con tramos de subida,
+//! rango y caída). Si cambia la lógica del motor, del gate de riesgo o del paper,
+//! estos números cambian: revisar el motivo antes de actualizarlos.
+
+use quantos_core::backtest::replay::load_agg_trades;
+use quantos_core::backtest::runner::{run_backtest, BacktestReport};
+use quantos_core::brain::NoiseModel;
+use quantos_core::config::Config;
+use std::collections::HashMap;
+use std::error::Error;
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/agg_trades.jsonl");
+
+/// Modelo determinista: menos ruido cuanto más eficiente es el movimiento (feature 3)
+struct EfficiencyModel;
+
+impl NoiseModel for EfficiencyModel {
+    fn predict_noise(&self, features: Vec<f64>) -> Result<f64, Box<dyn Error>> {
+        Ok((0.5 - features[3] * 0.6).clamp(0.0, 1.0))
+    }
+}
+
+async fn run(config: &Config) -> BacktestReport {
+    let messages = load_agg_trades(FIXTURE).expect("fixture ilegible");
+    let brains: HashMap<String, EfficiencyModel> = config.market.symbols.iter().map(|s| (s.clone(), EfficiencyModel)).collect();
+    run_backtest(&messages, &brains, config).await
+}
+
+fn config() -> Config {
+    let mut config = Config::default();
+    config.macro_filter.enabled = false;
+    config.backtest.fee_rate = 0.001;
+    // Distinto del capital de riesgo a propósito: el informe parte del saldo de la cuenta
+    config.backtest.account_balance = 25_000.0;
+    config
+}
+
+#[tokio::test]
+async fn fixture_replay_is_stable() {
+    let report = run(&config()).await;
+
+    let summary: Vec<(&str, u64, u64, &str)> = report.trades.iter().map(|t| (t.symbol.as_str(), t.entry_time, t.exit_time, t.reason)).collect();
+    assert_eq!(summary, vec![
+        ("BTCUSDT", 1700000014119, 1700000084883, "STOP EXCHANGE"),
+        ("BTCUSDT", 1700000458339, 1700000459560, "STOP LOSS"),
+    ]);
+    assert!((report.trades[0].pnl_usd - 84.815926).abs() < 1e-5);
+    assert!((report.trades[1].pnl_usd + 5.942060).abs() < 1e-5);
+    assert!((report.final_equity() - 25_078.873866).abs() < 1e-5);
+}
+
+#[tokio::test]
+async fn report_starts_from_the_funded_account() {
+    let config = config();
+    let report = run(&config).await;
+
+    assert_eq!(report.initial_balance, config.backtest.account_balance);
+    assert_eq!(report.equity_curve.first().map(|p| p.equity), Some(config.backtest.account_balance));
+    let pnl: f64 = report.trades.iter().map(|t| t.pnl_usd).sum();
+    assert!((report.final_equity() - report.initial_balance - pnl).abs() < 1e-9);
+}